@@ -0,0 +1,150 @@
+# Type stubs for the chesslibrary extension module. Keep these in step with src/python when functions or classes change
+
+from typing import Iterator, Literal, Optional, Union
+
+Colour = Literal["white", "black"]
+Piece = Literal["pawn", "knight", "bishop", "rook", "queen", "king"]
+
+class IllegalMoveError(ValueError):
+    """A move that is illegal in the position, or that can't be read"""
+
+class InvalidFenError(ValueError):
+    """A FEN that is badly formed"""
+
+class InvalidSquareError(ValueError):
+    """A square name or index that isn't on the board"""
+
+class InvalidColourError(ValueError):
+    """A colour that isn't 'white' or 'black'"""
+
+class Square:
+    # Squares are numbered like python-chess, A1 as 0 and H8 as 63
+    def __new__(cls, square: Union[int, str]) -> Square: ...
+    @property
+    def index(self) -> int: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def file(self) -> int: ...
+    @property
+    def rank(self) -> int: ...
+    def bitboard(self) -> Bitboard: ...
+    def __int__(self) -> int: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+class Bitboard:
+    # The raw value has H1 as bit 0 and A8 as bit 63
+    def __new__(cls, value: int = 0) -> Bitboard: ...
+    @property
+    def value(self) -> int: ...
+    def squares(self) -> list[Square]: ...
+    def __iter__(self) -> Iterator[Square]: ...
+    def __len__(self) -> int: ...
+    def __bool__(self) -> bool: ...
+    def __contains__(self, square: Square) -> bool: ...
+    def __and__(self, other: Bitboard) -> Bitboard: ...
+    def __or__(self, other: Bitboard) -> Bitboard: ...
+    def __xor__(self, other: Bitboard) -> Bitboard: ...
+    def __invert__(self) -> Bitboard: ...
+    def __int__(self) -> int: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+class Move:
+    @property
+    def from_square(self) -> Square: ...
+    # For castling this is the square of the rook
+    @property
+    def to_square(self) -> Square: ...
+    @property
+    def piece(self) -> Optional[Piece]: ...
+    @property
+    def promotion(self) -> Optional[Piece]: ...
+    @property
+    def captured(self) -> Optional[Piece]: ...
+    @property
+    def is_capture(self) -> bool: ...
+    @property
+    def is_castle(self) -> bool: ...
+    @property
+    def is_en_passant(self) -> bool: ...
+    def uci(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+class Outcome:
+    termination: Literal["checkmate", "stalemate", "insufficient_material", "fifty_moves", "threefold_repetition", "resignation", "timeout", "adjudication", "variant_win"]
+    winner: Optional[Colour]
+    def result(self) -> Literal["1-0", "0-1", "1/2-1/2"]: ...
+
+class Board:
+    # variant is one of "standard", "kingofthehill", "threecheck", "atomic", "antichess" or "horde"
+    def __new__(cls, fen: Optional[str] = None, variant: str = "standard") -> Board: ...
+    @staticmethod
+    def chess960(index: int) -> Board: ...
+    def fen(self) -> str: ...
+    @property
+    def variant(self) -> str: ...
+    @property
+    def turn(self) -> Colour: ...
+    @property
+    def halfmove_clock(self) -> int: ...
+    @property
+    def fullmove_number(self) -> int: ...
+    @property
+    def move_stack(self) -> list[Move]: ...
+    @property
+    def occupied(self) -> Bitboard: ...
+    def legal_moves(self) -> list[Move]: ...
+    def is_legal(self, mv: Move) -> bool: ...
+    # clock is the seconds left for the side making the move, which is written to the PGN
+    def push(self, mv: Move, clock: Optional[float] = None) -> None: ...
+    def parse_uci(self, uci: str) -> Move: ...
+    def parse_san(self, san: str) -> Move: ...
+    def push_uci(self, uci: str, clock: Optional[float] = None) -> Move: ...
+    def push_san(self, san: str, clock: Optional[float] = None) -> Move: ...
+    def pop(self) -> Move: ...
+    def undo_to(self, ply: int) -> None: ...
+    @property
+    def ply(self) -> int: ...
+    def peek(self) -> Optional[Move]: ...
+    def san(self, mv: Move) -> str: ...
+    def is_check(self) -> bool: ...
+    def is_checkmate(self) -> bool: ...
+    def is_stalemate(self) -> bool: ...
+    def is_insufficient_material(self) -> bool: ...
+    def outcome(self) -> Optional[Outcome]: ...
+    def is_game_over(self) -> bool: ...
+    def resign(self, colour: Colour) -> None: ...
+    def timeout(self, colour: Colour) -> None: ...
+    def pgn(self, tags: Optional[dict[str, str]] = None) -> str: ...
+    # A FEN letter, upper case for white
+    def piece_at(self, square: Square) -> Optional[str]: ...
+    def pieces(self, piece: Piece, colour: Colour) -> Bitboard: ...
+    def zobrist_hash(self) -> int: ...
+    def perft(self, depth: int) -> int: ...
+    def copy(self) -> Board: ...
+
+class AnalysisLine:
+    uci_move: str
+    evaluation: int
+    pv: list[str]
+    depth: int
+
+# board_starting_position is "startpos" or a FEN, and move_list is a space separated list of UCI moves played from it. Times are
+# (base, increment) in seconds, and evaluations are in hundredths of a centipawn from the side to move
+def pick_move(board_starting_position: str, bot_time: tuple[int, int], bot_colour: Colour, move_list: str) -> tuple[str, int]: ...
+def start_ponder(board_starting_position: str, move_list: str) -> Optional[str]: ...
+def ponder_hit(bot_time: tuple[int, int], opponent_move: str) -> Optional[tuple[str, int]]: ...
+def stop_ponder() -> None: ...
+def analyse(board_starting_position: str, move_list: str, search_time_ms: int, multi_pv: int) -> list[AnalysisLine]: ...
+def find_mate(board_starting_position: str, move_list: str, max_moves: int, search_time_ms: Optional[int] = None) -> Optional[list[str]]: ...
+
+def load_eval_params(path: str) -> None: ...
+def load_syzygy(path: str) -> int: ...
+# Only there when built with the "nnue" feature
+def load_nnue(path: Optional[str] = None) -> None: ...
+def load_book(path: str, max_depth: int = 20, selection: Literal["random", "best"] = "random") -> None: ...
+def unload_book() -> None: ...
+def make_book(pgn_path: str, output_path: str, max_plies: int = 20) -> int: ...
@@ -0,0 +1,263 @@
+use chesslibrary::board::Board;
+use chesslibrary::colour::Colour;
+use chesslibrary::engine::search::{search, SearchControl};
+use chesslibrary::movegen::get_legal_moves;
+use chesslibrary::role::Role;
+use std::fs::{self, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, available_parallelism};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "Usage: datagen [--games <n>] [--nodes <n>] [--threads <n>] [--output <file>] [--random-plies <n>] [--book <file>] [--seed <n>]";
+
+// Games longer than this are called a draw
+const MAX_GAME_PLIES: usize = 400;
+
+// A game is given to a side once the search has it this many centipawns ahead for ADJUDICATION_PLIES plies in a row
+const ADJUDICATION_SCORE: i32 = 1000;
+const ADJUDICATION_PLIES: usize = 8;
+
+struct Options {
+    games: usize,
+    nodes: u64,
+    threads: usize,
+    output: String,
+    random_plies: usize,
+    book: Vec<String>,
+    seed: u64,
+}
+
+// Plays the engine against itself at a fixed number of nodes per move and writes "fen | score | result" for the positions it saw. Scores
+// are centipawns and results are 1.0, 0.5 or 0.0, both from whites point of view, so the file can be given straight to the tuner
+fn main() {
+    let options = parse_options();
+
+    let output = OpenOptions::new().create(true).append(true).open(&options.output).unwrap_or_else(|error| fail(&format!("Could not open {}: {}", options.output, error)));
+    let mut output = BufWriter::new(output);
+
+    let options = Arc::new(options);
+    let games_started = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel::<Vec<String>>();
+
+    // Each thread plays whole games on its own boards and sends the finished lines back to be written
+    for thread_index in 0..options.threads {
+        let options = Arc::clone(&options);
+        let games_started = Arc::clone(&games_started);
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let mut random = Random::new(options.seed.wrapping_add(thread_index as u64 * 0x9e37_79b9_7f4a_7c15));
+            while games_started.fetch_add(1, Ordering::Relaxed) < options.games {
+                if sender.send(play_game(&options, &mut random)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let start_time = Instant::now();
+    let mut games = 0;
+    let mut positions = 0;
+    for lines in receiver {
+        for line in &lines {
+            writeln!(output, "{}", line).unwrap_or_else(|error| fail(&format!("Could not write {}: {}", options.output, error)));
+        }
+        games += 1;
+        positions += lines.len();
+        if games % 10 == 0 {
+            output.flush().unwrap_or_else(|error| fail(&format!("Could not write {}: {}", options.output, error)));
+            eprintln!("{} games, {} positions, {:.1} positions per second", games, positions, positions as f64 / start_time.elapsed().as_secs_f64());
+        }
+    }
+    output.flush().unwrap_or_else(|error| fail(&format!("Could not write {}: {}", options.output, error)));
+    eprintln!("Finished {} games with {} positions written to {}", games, positions, options.output);
+}
+
+// Plays one game and returns its lines
+fn play_game(options: &Options, random: &mut Random) -> Vec<String> {
+    let mut board = match options.book.is_empty() {
+        true => Board::starting_position(),
+        false => Board::from_fen(options.book[random.below(options.book.len())].clone()),
+    };
+
+    // Random moves so games don't all follow the same path. Openings that end the game early are started again
+    for _ in 0..options.random_plies {
+        let moves = get_legal_moves(&board);
+        if moves.is_empty() {
+            return play_game(options, random);
+        }
+        board.play_unsafe(moves[random.below(moves.len())]);
+    }
+
+    let mut positions: Vec<(String, i32)> = Vec::new();
+    let mut history: Vec<u64> = vec![board.zobrist_hash()];
+    let mut plies_since_irreversible = 0;
+    // Counts every ply of the game, where history starts again after each capture or pawn move
+    let mut game_plies = options.random_plies;
+    let mut adjudication_plies = 0;
+
+    // From whites point of view
+    let result: f64 = loop {
+        let legal_moves = get_legal_moves(&board);
+        if legal_moves.is_empty() {
+            if board.is_check(&board.turn) {
+                break if board.turn == Colour::White { 0.0 } else { 1.0 };
+            }
+            break 0.5;
+        }
+        if (plies_since_irreversible >= 100) | is_repetition(&history) | board.is_insufficient_material() | (game_plies >= MAX_GAME_PLIES) {
+            break 0.5;
+        }
+
+        let control = SearchControl::with_node_limit(options.nodes);
+        let search_result = search(&board, &control, 1);
+        let Some(best_move) = search_result.best_move else {
+            break 0.5;
+        };
+
+        // Mates come back as i32::MAX or i32::MIN, which have no sensible centipawn score, so they are clamped and never recorded
+        let is_mate_score = (search_result.evaluation == i32::MAX) | (search_result.evaluation == i32::MIN);
+        let score = search_result.evaluation.clamp(-100_000_000, 100_000_000) / 100;
+        let white_score = if board.turn == Colour::White { score } else { -score };
+
+        // Positions in check or where the best move is a capture aren't quiet, so their scores say little about the evaluation
+        if !board.is_check(&board.turn) && best_move.capture.is_none() && best_move.promotion.is_none() && !is_mate_score {
+            positions.push((board.to_fen(), white_score));
+        }
+
+        if white_score.abs() >= ADJUDICATION_SCORE {
+            adjudication_plies += 1;
+            if adjudication_plies >= ADJUDICATION_PLIES {
+                break if white_score > 0 { 1.0 } else { 0.0 };
+            }
+        } else {
+            adjudication_plies = 0;
+        }
+
+        if best_move.capture.is_some() | (best_move.role == Some(Role::Pawn)) {
+            plies_since_irreversible = 0;
+            history.clear();
+        } else {
+            plies_since_irreversible += 1;
+        }
+        board.play_unsafe(best_move);
+        history.push(board.zobrist_hash());
+        game_plies += 1;
+    };
+
+    return positions.into_iter().map(|(fen, score)| format_line(&fen, score, result)).collect();
+}
+
+fn format_line(fen: &str, score: i32, result: f64) -> String {
+    return format!("{} | {} | {:.1}", fen, score, result);
+}
+
+// The current position has come up twice before since the last capture or pawn move
+fn is_repetition(history: &[u64]) -> bool {
+    let Some(current) = history.last() else {
+        return false;
+    };
+    return history.iter().filter(|hash| *hash == current).count() >= 3;
+}
+
+fn parse_options() -> Options {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = Options {
+        games: 100,
+        nodes: 5000,
+        threads: available_parallelism().map(|threads| threads.get()).unwrap_or(1),
+        output: "datagen.txt".to_string(),
+        random_plies: 8,
+        book: Vec::new(),
+        seed: SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as u64).unwrap_or(1),
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        let Some(value) = args.get(i + 1) else {
+            fail::<()>(USAGE);
+            break;
+        };
+        match args[i].as_str() {
+            "--games" => options.games = value.parse().unwrap_or_else(|_| fail(USAGE)),
+            "--nodes" => options.nodes = value.parse().unwrap_or_else(|_| fail(USAGE)),
+            "--threads" => options.threads = value.parse().unwrap_or_else(|_| fail(USAGE)),
+            "--output" => options.output = value.clone(),
+            "--random-plies" => options.random_plies = value.parse().unwrap_or_else(|_| fail(USAGE)),
+            "--seed" => options.seed = value.parse().unwrap_or_else(|_| fail(USAGE)),
+            "--book" => {
+                // One FEN or EPD position to a line
+                let contents = fs::read_to_string(value).unwrap_or_else(|error| fail(&format!("Could not read {}: {}", value, error)));
+                options.book = contents
+                    .lines()
+                    .map(|line| line.split_whitespace().take(4).collect::<Vec<&str>>())
+                    .filter(|fields| fields.len() == 4)
+                    .map(|fields| fields.join(" "))
+                    .collect();
+            }
+            _ => fail(USAGE),
+        }
+        i += 2;
+    }
+
+    return options;
+}
+
+// Small xorshift generator so each thread gets its own reproducible stream of random numbers
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random { state: seed.max(1) }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
+    }
+
+    fn below(&mut self, limit: usize) -> usize {
+        return (self.next() % limit as u64) as usize;
+    }
+}
+
+fn fail<T>(message: &str) -> T {
+    eprintln!("{}", message);
+    exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chesslibrary::engine::tune::load_dataset;
+
+    #[test]
+    fn lines_load_back_into_the_tuner() {
+        let samples = [
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 30, 0.5),
+            ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", -35, 0.0),
+            ("8/5k2/8/8/3K4/8/4P3/8 w - - 0 60", 250, 1.0),
+        ];
+        let lines: Vec<String> = samples.iter().map(|(fen, score, result)| format_line(fen, *score, *result)).collect();
+
+        let path = std::env::temp_dir().join(format!("chesslibrary_datagen_{}.txt", std::process::id()));
+        fs::write(&path, lines.join("\n")).unwrap();
+        let positions = load_dataset(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(positions.len(), samples.len());
+        for ((fen, _, result), position) in samples.iter().zip(&positions) {
+            // The tuner only needs the position, so the move clocks aren't read back
+            let fields: Vec<&str> = fen.split_whitespace().collect();
+            assert_eq!(position.board.to_fen().split_whitespace().take(4).collect::<Vec<&str>>(), fields[..4]);
+            assert_eq!(position.result, *result);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use chesslibrary::engine::book::{make_book, DEFAULT_MAX_BOOK_DEPTH};
+use std::fs;
+use std::process::exit;
+
+const USAGE: &str = "Usage: makebook <pgn files> [--output <file>] [--max-plies <n>]";
+
+// Builds a Polyglot opening book from the games in one or more PGN files
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut pgn_paths: Vec<String> = Vec::new();
+    let mut output = "book.bin".to_string();
+    let mut max_plies = DEFAULT_MAX_BOOK_DEPTH;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--output" => {
+                output = args.get(i + 1).cloned().unwrap_or_else(|| fail(USAGE));
+                i += 1;
+            }
+            "--max-plies" => {
+                max_plies = args.get(i + 1).and_then(|value| value.parse().ok()).unwrap_or_else(|| fail(USAGE));
+                i += 1;
+            }
+            path => pgn_paths.push(path.to_string()),
+        }
+        i += 1;
+    }
+    if pgn_paths.is_empty() {
+        fail::<()>(USAGE);
+    }
+
+    let mut pgn = String::new();
+    for path in &pgn_paths {
+        pgn.push_str(&fs::read_to_string(path).unwrap_or_else(|error| fail(&format!("Could not read {}: {}", path, error))));
+        pgn.push('\n');
+    }
+
+    let book = make_book(&pgn, max_plies);
+    book.to_file(&output).unwrap_or_else(|error| fail(&error));
+    println!("Wrote {} entries to {}", book.entries.len(), output);
+}
+
+fn fail<T>(message: &str) -> T {
+    eprintln!("{}", message);
+    exit(1);
+}
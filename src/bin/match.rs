@@ -0,0 +1,789 @@
+use chesslibrary::colour::Colour;
+use chesslibrary::engine::syzygy::{can_probe, init_tablebases, probe_wdl, Wdl};
+use chesslibrary::epd::parse_epd_file;
+use chesslibrary::game::Game;
+use chesslibrary::pgn::parse_pgn;
+use chesslibrary::san::from_san;
+use chesslibrary::uci::{from_uci, to_uci};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{exit, Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: match --engine <spec> --engine <spec> [--games <n>] [--tc <seconds>+<increment> | --movetime <ms> | --nodes <n>]
+             [--openings <epd or pgn file>] [--opening-plies <n>] [--concurrency <n>] [--pgn <file>] [--time-margin <ms>]
+             [--resign <cp> <moves>] [--draw <cp> <moves> <from move>] [--syzygy <path>] [--sprt <elo0> <elo1>] [--sprt-alpha <a>] [--sprt-beta <b>]
+An engine spec is name=<name>, cmd=<path> and any number of option.<name>=<value>, which are sent with setoption. Without cmd the
+chesslibrary binary next to this one is used, so two parameter sets of our own engine are --engine name=base --engine name=new option.EvalFile=tuned_params.txt";
+
+// Mate scores are turned into this many centipawns so the adjudication rules can compare them
+const MATE_SCORE: i32 = 100_000;
+
+// How long an engine gets to answer anything that isn't a move before it is taken to have hung
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long a move can take with a node limit, where the clock can't say when an engine has hung
+const NODE_LIMIT_MOVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+struct EngineSpec {
+    name: String,
+    command: String,
+    options: Vec<(String, String)>,
+}
+
+#[derive(Clone, Copy)]
+enum Limit {
+    Clock { base: Duration, increment: Duration },
+    MoveTime(Duration),
+    Nodes(u64),
+}
+
+struct Sprt {
+    elo0: f64,
+    elo1: f64,
+    alpha: f64,
+    beta: f64,
+}
+
+struct Options {
+    engines: Vec<EngineSpec>,
+    games: usize,
+    limit: Limit,
+    openings: Vec<Game>,
+    concurrency: usize,
+    pgn: Option<String>,
+    time_margin: Duration,
+    // Centipawns and the number of moves both engines have to agree for
+    resign: Option<(i32, usize)>,
+    // Centipawns, the number of moves both engines have to agree for and the move number adjudication can start at
+    draw: Option<(i32, usize, u32)>,
+    syzygy: bool,
+    sprt: Option<Sprt>,
+}
+
+// A finished game, with the score from the first engine's point of view
+struct GameResult {
+    index: usize,
+    first_engine_score: f64,
+    description: String,
+    pgn: String,
+}
+
+// Plays two engines against each other over UCI. Each opening is played twice with the colours swapped, and the first engine's Elo
+// against the second is estimated from the pairs. With --sprt the match stops as soon as the result is clear
+fn main() {
+    let options = Arc::new(parse_options());
+    let next_game = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel::<Result<GameResult, String>>();
+
+    // Each thread runs its own pair of engine processes and plays whole games with them
+    for _ in 0..options.concurrency {
+        let options = Arc::clone(&options);
+        let next_game = Arc::clone(&next_game);
+        let stop = Arc::clone(&stop);
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let mut engines: Vec<Option<UciEngine>> = vec![None, None];
+            while !stop.load(Ordering::Relaxed) {
+                let index = next_game.fetch_add(1, Ordering::Relaxed);
+                if index >= options.games {
+                    break;
+                }
+                if sender.send(play_game(&options, &mut engines, index)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut pgn_file = options.pgn.as_ref().map(|path| {
+        OpenOptions::new().create(true).append(true).open(path).unwrap_or_else(|error| fail(&format!("Could not open {}: {}", path, error)))
+    });
+
+    let mut stats = Stats::new();
+    let mut finished = 0;
+    for result in receiver {
+        let result = result.unwrap_or_else(|error| fail(&error));
+        finished += 1;
+        stats.add(result.index, result.first_engine_score);
+
+        if let (Some(file), Some(path)) = (pgn_file.as_mut(), options.pgn.as_ref()) {
+            writeln!(file, "{}", result.pgn).unwrap_or_else(|error| fail(&format!("Could not write {}: {}", path, error)));
+        }
+        println!("Finished game {} ({}), {} of {}", result.index + 1, result.description, finished, options.games);
+        println!("{}", stats.summary(&options));
+
+        if let Some(sprt) = &options.sprt {
+            let llr = stats.llr(sprt);
+            if (llr >= upper_bound(sprt)) | (llr <= lower_bound(sprt)) {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    if let Some(sprt) = &options.sprt {
+        let llr = stats.llr(sprt);
+        let decision = if llr >= upper_bound(sprt) {
+            format!("H1 accepted, {} is at least {} Elo stronger", options.engines[0].name, sprt.elo1)
+        } else if llr <= lower_bound(sprt) {
+            format!("H0 accepted, {} is no more than {} Elo stronger", options.engines[0].name, sprt.elo0)
+        } else {
+            "no decision".to_string()
+        };
+        println!("SPRT: {}", decision);
+    }
+}
+
+// Plays one game. Games come in pairs on the same opening, the first engine being white in the even numbered one
+fn play_game(options: &Options, engines: &mut [Option<UciEngine>], index: usize) -> Result<GameResult, String> {
+    let mut game = options.openings[(index / 2) % options.openings.len()].clone();
+    let white = index % 2;
+
+    for (slot, spec) in engines.iter_mut().zip(&options.engines) {
+        if slot.is_none() {
+            *slot = Some(UciEngine::start(spec)?);
+        }
+        slot.as_mut().unwrap().new_game()?;
+    }
+
+    let mut clocks = match options.limit {
+        Limit::Clock { base, .. } => [base, base],
+        _ => [Duration::ZERO, Duration::ZERO],
+    };
+    // What each engine thought of the position after its move, in centipawns from whites point of view
+    let mut scores: Vec<Option<i32>> = Vec::new();
+    let mut notes: Vec<String> = Vec::new();
+
+    while game.outcome().is_none() {
+        if let Some((winner, note)) = adjudication(options, &game, &scores) {
+            game.adjudicate(winner);
+            notes.push(note);
+            break;
+        }
+
+        let turn = game.board.turn;
+        let side = if turn == Colour::White { 0 } else { 1 };
+        let engine_index = if side == 0 { white } else { 1 - white };
+        let engine = engines[engine_index].as_mut().unwrap();
+
+        let (go, move_timeout) = match options.limit {
+            Limit::Clock { increment, .. } => (
+                format!("go wtime {} btime {} winc {} binc {}", clocks[0].as_millis(), clocks[1].as_millis(), increment.as_millis(), increment.as_millis()),
+                clocks[side] + options.time_margin,
+            ),
+            Limit::MoveTime(move_time) => (format!("go movetime {}", move_time.as_millis()), move_time + options.time_margin),
+            Limit::Nodes(nodes) => (format!("go nodes {}", nodes), NODE_LIMIT_MOVE_TIMEOUT),
+        };
+
+        let reply = engine.go(&game, &go, move_timeout);
+        let (best_move, score, elapsed) = match reply {
+            Ok(reply) => reply,
+            Err(EngineError::TimedOut) => {
+                game.timeout(turn);
+                notes.push(format!("{} ran out of time", engine.name));
+                break;
+            }
+            Err(EngineError::Failed(error)) => {
+                // An engine that crashed or hung is started again for the next game
+                eprintln!("{}", error);
+                engines[engine_index] = None;
+                game.adjudicate(Some(turn.opposite()));
+                notes.push(format!("{} stopped responding", options.engines[engine_index].name));
+                break;
+            }
+        };
+
+        let clock = match options.limit {
+            Limit::Clock { increment, .. } => {
+                if elapsed > clocks[side] + options.time_margin {
+                    game.timeout(turn);
+                    notes.push(format!("{} ran out of time", engine.name));
+                    break;
+                }
+                clocks[side] = clocks[side].saturating_sub(elapsed) + increment;
+                Some(clocks[side])
+            }
+            _ => None,
+        };
+
+        let played = from_uci(&game.board, &best_move).map_err(|error| error.to_string()).and_then(|mv| game.push_with_clock(mv, clock).map_err(|error| error.to_string()));
+        if let Err(error) = played {
+            eprintln!("{} played {}: {}", engine.name, best_move, error);
+            game.adjudicate(Some(turn.opposite()));
+            notes.push(format!("{} played an illegal move", engine.name));
+            break;
+        }
+        scores.push(score.map(|score| if turn == Colour::White { score } else { -score }));
+    }
+
+    let outcome = game.outcome().unwrap();
+    let first_engine_colour = if white == 0 { Colour::White } else { Colour::Black };
+    let first_engine_score = match outcome.winner {
+        Some(winner) if winner == first_engine_colour => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    };
+
+    let mut tags: Vec<(String, String)> = vec![
+        ("Event".to_string(), format!("{} vs {}", options.engines[0].name, options.engines[1].name)),
+        ("Round".to_string(), (index + 1).to_string()),
+        ("White".to_string(), options.engines[white].name.clone()),
+        ("Black".to_string(), options.engines[1 - white].name.clone()),
+    ];
+    if let Limit::Clock { base, increment } = options.limit {
+        tags.push(("TimeControl".to_string(), format!("{}+{}", base.as_secs_f64(), increment.as_secs_f64())));
+    }
+
+    let description = format!(
+        "{} vs {}: {} by {}",
+        options.engines[white].name,
+        options.engines[1 - white].name,
+        outcome.result(),
+        notes.first().cloned().unwrap_or(outcome.termination.name().replace('_', " "))
+    );
+    return Ok(GameResult { index, first_engine_score, description, pgn: game.to_pgn(&tags) });
+}
+
+// Decides a game early from the tablebases, or when both engines have agreed for long enough that it is won or drawn
+fn adjudication(options: &Options, game: &Game, scores: &[Option<i32>]) -> Option<(Option<Colour>, String)> {
+    if options.syzygy && can_probe(&game.board) && let Some(wdl) = probe_wdl(&game.board) {
+        let winner = match wdl {
+            Wdl::Win => Some(game.board.turn),
+            Wdl::Loss => Some(game.board.turn.opposite()),
+            _ => None,
+        };
+        return Some((winner, "tablebase adjudication".to_string()));
+    }
+
+    if let Some((resign_score, moves)) = options.resign && let Some(recent) = last_scores(scores, moves * 2) {
+        if recent.iter().all(|score| *score >= resign_score) {
+            return Some((Some(Colour::White), "resign adjudication".to_string()));
+        }
+        if recent.iter().all(|score| *score <= -resign_score) {
+            return Some((Some(Colour::Black), "resign adjudication".to_string()));
+        }
+    }
+
+    if let Some((draw_score, moves, from_move)) = options.draw && game.fullmove_number() >= from_move
+        && let Some(recent) = last_scores(scores, moves * 2) && recent.iter().all(|score| score.abs() <= draw_score) {
+        return Some((None, "draw adjudication".to_string()));
+    }
+
+    return None;
+}
+
+// The last count scores, as long as there are that many and every engine gave one
+fn last_scores(scores: &[Option<i32>], count: usize) -> Option<Vec<i32>> {
+    if (count == 0) | (scores.len() < count) {
+        return None;
+    }
+    return scores[scores.len() - count..].iter().copied().collect();
+}
+
+enum EngineError {
+    TimedOut,
+    Failed(String),
+}
+
+// An engine process spoken to over UCI. Its output is read on another thread so waiting for a reply can time out
+struct UciEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciEngine {
+    fn start(spec: &EngineSpec) -> Result<UciEngine, String> {
+        let mut child = Command::new(&spec.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| format!("Could not start {} ({}): {}", spec.name, spec.command, error))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (sender, lines) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciEngine { name: spec.name.clone(), child, stdin, lines };
+        engine.send("uci")?;
+        engine.wait_for("uciok")?;
+        for (name, value) in &spec.options {
+            engine.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        engine.send("isready")?;
+        engine.wait_for("readyok")?;
+        return Ok(engine);
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        return writeln!(self.stdin, "{}", command).and_then(|_| self.stdin.flush()).map_err(|error| format!("Could not write to {}: {}", self.name, error));
+    }
+
+    fn wait_for(&mut self, reply: &str) -> Result<(), String> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) if line.trim() == reply => return Ok(()),
+                Ok(_) => (),
+                Err(_) => return Err(format!("{} didn't send {}", self.name, reply)),
+            }
+        }
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        return self.wait_for("readyok");
+    }
+
+    // Asks for a move in the current position of the game. Returns the move, the last score the engine gave in centipawns from its own
+    // point of view, and how long it took. An engine that is still thinking at the timeout is stopped and told it lost on time
+    fn go(&mut self, game: &Game, go: &str, timeout: Duration) -> Result<(String, Option<i32>, Duration), EngineError> {
+        let moves: Vec<String> = game.moves.iter().map(|mv| to_uci(Some(*mv), game.board.chess960)).collect();
+        let position = match moves.is_empty() {
+            true => format!("position fen {}", game.starting_fen()),
+            false => format!("position fen {} moves {}", game.starting_fen(), moves.join(" ")),
+        };
+        self.send(&position).map_err(EngineError::Failed)?;
+        self.send(go).map_err(EngineError::Failed)?;
+
+        let start_time = Instant::now();
+        let mut score: Option<i32> = None;
+        loop {
+            let line = match self.lines.recv_timeout(timeout.saturating_sub(start_time.elapsed())) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    // The move that comes back after the stop is thrown away, so it doesn't get taken as the answer to the next go
+                    self.send("stop").map_err(EngineError::Failed)?;
+                    self.wait_for_best_move().map_err(EngineError::Failed)?;
+                    return Err(EngineError::TimedOut);
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(EngineError::Failed(format!("{} exited", self.name))),
+            };
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.first() {
+                Some(&"info") => score = parse_score(&tokens).or(score),
+                Some(&"bestmove") => {
+                    let best_move = tokens.get(1).unwrap_or(&"0000").to_string();
+                    return Ok((best_move, score, start_time.elapsed()));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn wait_for_best_move(&mut self) -> Result<(), String> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) if line.starts_with("bestmove") => return Ok(()),
+                Ok(_) => (),
+                Err(_) => return Err(format!("{} didn't stop searching", self.name)),
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// The score in an info line, in centipawns. Mates are MATE_SCORE less the number of moves to mate
+fn parse_score(tokens: &[&str]) -> Option<i32> {
+    let position = tokens.iter().position(|token| *token == "score")?;
+    let value: i64 = tokens.get(position + 2)?.parse().ok()?;
+    match *tokens.get(position + 1)? {
+        "cp" => return Some(value.clamp(-MATE_SCORE as i64, MATE_SCORE as i64) as i32),
+        "mate" if value > 0 => return Some(MATE_SCORE - value as i32),
+        "mate" => return Some(-MATE_SCORE - value as i32),
+        _ => return None,
+    }
+}
+
+// Results so far from the first engine's point of view. Paired games are counted together, since the two games of an opening aren't
+// independent of each other, which is what the Elo error and the SPRT are worked out from
+struct Stats {
+    wins: usize,
+    draws: usize,
+    losses: usize,
+    // The first game of each pair still waiting for the second
+    unpaired: HashMap<usize, f64>,
+    // How many pairs scored 0, 0.5, 1, 1.5 and 2 points
+    pairs: [usize; 5],
+}
+
+impl Stats {
+    fn new() -> Stats {
+        return Stats { wins: 0, draws: 0, losses: 0, unpaired: HashMap::new(), pairs: [0; 5] };
+    }
+
+    fn add(&mut self, index: usize, score: f64) {
+        match score {
+            1.0 => self.wins += 1,
+            0.0 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+        match self.unpaired.remove(&(index / 2)) {
+            Some(other) => self.pairs[((score + other) * 2.0) as usize] += 1,
+            None => {
+                self.unpaired.insert(index / 2, score);
+            }
+        }
+    }
+
+    // The mean score per game and its variance over the pairs, or None until there is something to work out a variance from
+    fn mean_and_variance(&self) -> Option<(f64, f64)> {
+        let count: usize = self.pairs.iter().sum();
+        if count == 0 {
+            return None;
+        }
+        let pair_scores = [0.0, 0.25, 0.5, 0.75, 1.0];
+        let mean = pair_scores.iter().zip(&self.pairs).map(|(score, pairs)| score * *pairs as f64).sum::<f64>() / count as f64;
+        let variance = pair_scores.iter().zip(&self.pairs).map(|(score, pairs)| (score - mean).powi(2) * *pairs as f64).sum::<f64>() / count as f64;
+        return Some((mean, variance));
+    }
+
+    // The log likelihood ratio of elo1 against elo0, using the normal approximation to the generalised SPRT
+    fn llr(&self, sprt: &Sprt) -> f64 {
+        let Some((mean, variance)) = self.mean_and_variance() else {
+            return 0.0;
+        };
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let count: usize = self.pairs.iter().sum();
+        let (score0, score1) = (expected_score(sprt.elo0), expected_score(sprt.elo1));
+        return count as f64 * (score1 - score0) * (2.0 * mean - score0 - score1) / (2.0 * variance);
+    }
+
+    fn summary(&self, options: &Options) -> String {
+        let games = self.wins + self.draws + self.losses;
+        let score = (self.wins as f64 + self.draws as f64 / 2.0) / games as f64;
+        let mut summary = format!(
+            "Score of {} vs {}: {} - {} - {} [{:.3}] {}",
+            options.engines[0].name,
+            options.engines[1].name,
+            self.wins,
+            self.losses,
+            self.draws,
+            score,
+            games
+        );
+
+        // The error is a 95% confidence interval
+        if let Some((mean, variance)) = self.mean_and_variance() {
+            let count: usize = self.pairs.iter().sum();
+            let margin = 1.96 * (variance / count as f64).sqrt();
+            let elo = score_to_elo(mean);
+            let error = (score_to_elo(mean + margin) - score_to_elo(mean - margin)) / 2.0;
+            summary.push_str(&format!(", Elo {:.1} +/- {:.1}, pairs {:?}", elo, error, self.pairs));
+        }
+        if let Some(sprt) = &options.sprt {
+            summary.push_str(&format!(", LLR {:.2} ({:.2}, {:.2})", self.llr(sprt), lower_bound(sprt), upper_bound(sprt)));
+        }
+        return summary;
+    }
+}
+
+fn expected_score(elo: f64) -> f64 {
+    return 1.0 / (1.0 + 10f64.powf(-elo / 400.0));
+}
+
+// The Elo difference that gives a score, kept finite for a perfect or zero score
+fn score_to_elo(score: f64) -> f64 {
+    let score = score.clamp(0.001, 0.999);
+    return -400.0 * (1.0 / score - 1.0).log10();
+}
+
+fn lower_bound(sprt: &Sprt) -> f64 {
+    return (sprt.beta / (1.0 - sprt.alpha)).ln();
+}
+
+fn upper_bound(sprt: &Sprt) -> f64 {
+    return ((1.0 - sprt.beta) / sprt.alpha).ln();
+}
+
+fn parse_options() -> Options {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = Options {
+        engines: Vec::new(),
+        games: 100,
+        limit: Limit::Clock { base: Duration::from_secs(10), increment: Duration::from_millis(100) },
+        openings: Vec::new(),
+        concurrency: 1,
+        pgn: None,
+        time_margin: Duration::from_millis(100),
+        resign: None,
+        draw: None,
+        syzygy: false,
+        sprt: None,
+    };
+    let mut openings_path: Option<String> = None;
+    let mut opening_plies = usize::MAX;
+
+    let value = |i: usize| -> &str { args.get(i).map(|value| value.as_str()).unwrap_or_else(|| fail(USAGE)) };
+    let number = |i: usize| -> f64 { value(i).parse().unwrap_or_else(|_| fail(USAGE)) };
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--engine" => {
+                let mut spec = EngineSpec { name: format!("engine{}", options.engines.len() + 1), command: default_engine_command(), options: Vec::new() };
+                while let Some(argument) = args.get(i + 1).filter(|argument| !argument.starts_with("--")) {
+                    let (key, value) = argument.split_once('=').unwrap_or_else(|| fail(USAGE));
+                    match key {
+                        "name" => spec.name = value.to_string(),
+                        "cmd" => spec.command = value.to_string(),
+                        _ => match key.strip_prefix("option.") {
+                            Some(option) => spec.options.push((option.to_string(), value.to_string())),
+                            None => fail(USAGE),
+                        },
+                    }
+                    i += 1;
+                }
+                options.engines.push(spec);
+                i += 1;
+            }
+            "--games" => {
+                options.games = number(i + 1) as usize;
+                i += 2;
+            }
+            "--tc" => {
+                let (base, increment) = value(i + 1).split_once('+').unwrap_or((value(i + 1), "0"));
+                let seconds = |text: &str| -> Duration { text.parse().ok().and_then(|value| Duration::try_from_secs_f64(value).ok()).unwrap_or_else(|| fail(USAGE)) };
+                options.limit = Limit::Clock { base: seconds(base), increment: seconds(increment) };
+                i += 2;
+            }
+            "--movetime" => {
+                options.limit = Limit::MoveTime(Duration::from_millis(number(i + 1) as u64));
+                i += 2;
+            }
+            "--nodes" => {
+                options.limit = Limit::Nodes(number(i + 1) as u64);
+                i += 2;
+            }
+            "--openings" => {
+                openings_path = Some(value(i + 1).to_string());
+                i += 2;
+            }
+            "--opening-plies" => {
+                opening_plies = number(i + 1) as usize;
+                i += 2;
+            }
+            "--concurrency" => {
+                options.concurrency = (number(i + 1) as usize).max(1);
+                i += 2;
+            }
+            "--pgn" => {
+                options.pgn = Some(value(i + 1).to_string());
+                i += 2;
+            }
+            "--time-margin" => {
+                options.time_margin = Duration::from_millis(number(i + 1) as u64);
+                i += 2;
+            }
+            "--resign" => {
+                options.resign = Some((number(i + 1) as i32, number(i + 2) as usize));
+                i += 3;
+            }
+            "--draw" => {
+                options.draw = Some((number(i + 1) as i32, number(i + 2) as usize, number(i + 3) as u32));
+                i += 4;
+            }
+            "--syzygy" => {
+                let count = init_tablebases(value(i + 1));
+                eprintln!("Found {} tablebases", count);
+                options.syzygy = count > 0;
+                i += 2;
+            }
+            "--sprt" => {
+                options.sprt = Some(Sprt { elo0: number(i + 1), elo1: number(i + 2), alpha: 0.05, beta: 0.05 });
+                i += 3;
+            }
+            "--sprt-alpha" | "--sprt-beta" => {
+                let Some(sprt) = options.sprt.as_mut() else {
+                    fail::<()>("--sprt has to come before --sprt-alpha and --sprt-beta");
+                    break;
+                };
+                match args[i].as_str() {
+                    "--sprt-alpha" => sprt.alpha = number(i + 1),
+                    _ => sprt.beta = number(i + 1),
+                }
+                i += 2;
+            }
+            _ => fail(USAGE),
+        }
+    }
+
+    if options.engines.len() != 2 {
+        fail::<()>(USAGE);
+    }
+    // Games are played in pairs, so an odd number is rounded up
+    options.games += options.games % 2;
+
+    options.openings = match &openings_path {
+        Some(path) => load_openings(path, opening_plies).unwrap_or_else(|error| fail(&format!("{}: {}", path, error))),
+        None => vec![Game::starting_position()],
+    };
+    if options.openings.is_empty() {
+        fail::<()>("No openings found");
+    }
+    return options;
+}
+
+// Openings are positions from an EPD file, or the moves of each game in a PGN file up to opening_plies
+fn load_openings(path: &str, opening_plies: usize) -> Result<Vec<Game>, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    if !path.to_lowercase().ends_with(".pgn") {
+        return parse_epd_file(&text)?.iter().map(|record| Game::from_fen(&record.fen)).collect();
+    }
+
+    let mut openings: Vec<Game> = Vec::new();
+    for pgn_game in parse_pgn(&text) {
+        let mut game = match pgn_game.tag("FEN") {
+            Some(fen) => Game::from_fen(fen)?,
+            None => Game::starting_position(),
+        };
+        for san in pgn_game.moves.iter().take(opening_plies) {
+            let mv = from_san(&game.board, san).ok_or_else(|| format!("Illegal move {} in {}", san, game.fen()))?;
+            game.push(mv).map_err(|error| error.to_string())?;
+        }
+        openings.push(game);
+    }
+    return Ok(openings);
+}
+
+// The UCI engine built alongside this binary
+fn default_engine_command() -> String {
+    let engine = std::env::current_exe().ok().and_then(|path| Some(path.parent()?.join("chesslibrary")));
+    return engine.map(|path| path.to_string_lossy().to_string()).unwrap_or("chesslibrary".to_string());
+}
+
+fn fail<T>(message: &str) -> T {
+    eprintln!("{}", message);
+    exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(resign: Option<(i32, usize)>, draw: Option<(i32, usize, u32)>) -> Options {
+        return Options {
+            engines: Vec::new(),
+            games: 2,
+            limit: Limit::Nodes(1000),
+            openings: Vec::new(),
+            concurrency: 1,
+            pgn: None,
+            time_margin: Duration::ZERO,
+            resign,
+            draw,
+            syzygy: false,
+            sprt: None,
+        };
+    }
+
+    fn stats(pairs: [usize; 5]) -> Stats {
+        let mut stats = Stats::new();
+        stats.pairs = pairs;
+        return stats;
+    }
+
+    #[test]
+    fn games_are_paired_by_opening() {
+        let mut stats = Stats::new();
+        stats.add(0, 1.0);
+        stats.add(3, 0.5);
+        stats.add(1, 0.5);
+        assert_eq!(stats.pairs, [0, 0, 0, 1, 0]);
+        assert_eq!((stats.wins, stats.draws, stats.losses), (1, 2, 0));
+        stats.add(2, 0.0);
+        assert_eq!(stats.pairs, [0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn sprt_bounds_and_llr() {
+        let sprt = Sprt { elo0: 0.0, elo1: 10.0, alpha: 0.05, beta: 0.05 };
+        assert!((lower_bound(&sprt) + 2.9444).abs() < 1e-4);
+        assert!((upper_bound(&sprt) - 2.9444).abs() < 1e-4);
+
+        // Nothing to go on yet, and pairs that all scored the same have no variance
+        assert_eq!(Stats::new().llr(&sprt), 0.0);
+        assert_eq!(stats([0, 0, 7, 0, 0]).llr(&sprt), 0.0);
+
+        // Level with elo0 leans towards H0, and a clear lead passes the upper bound
+        assert!((stats([10, 20, 40, 20, 10]).llr(&sprt) + 0.13799).abs() < 1e-4);
+        let ahead = stats([0, 10, 40, 30, 20]).llr(&sprt);
+        assert!((ahead - 3.91349).abs() < 1e-4);
+        assert!(ahead >= upper_bound(&sprt));
+        assert!(stats([0, 20, 80, 60, 40]).llr(&sprt) > ahead);
+    }
+
+    #[test]
+    fn resign_adjudication_needs_every_recent_score() {
+        let options = options(Some((500, 3)), None);
+        let game = Game::starting_position();
+
+        let winning = vec![Some(20), Some(510), Some(600), Some(700), Some(650), Some(800), Some(900)];
+        assert_eq!(adjudication(&options, &game, &winning).map(|(winner, _)| winner), Some(Some(Colour::White)));
+        let losing: Vec<Option<i32>> = winning.iter().map(|score| score.map(|score| -score)).collect();
+        assert_eq!(adjudication(&options, &game, &losing).map(|(winner, _)| winner), Some(Some(Colour::Black)));
+
+        // Too few scores, one below the threshold or a missing score all keep the game going
+        assert!(adjudication(&options, &game, &winning[2..]).is_none());
+        let mut dipped = winning.clone();
+        dipped[4] = Some(499);
+        assert!(adjudication(&options, &game, &dipped).is_none());
+        let mut missing = winning.clone();
+        missing[6] = None;
+        assert!(adjudication(&options, &game, &missing).is_none());
+    }
+
+    #[test]
+    fn draw_adjudication_waits_for_its_move_number() {
+        let options = options(None, Some((10, 2, 40)));
+        let quiet = vec![Some(300), Some(-10), Some(5), Some(0), Some(10)];
+
+        let game = Game::from_fen("8/5k2/8/8/3K4/8/4P3/8 w - - 0 40").unwrap();
+        let (winner, note) = adjudication(&options, &game, &quiet).unwrap();
+        assert_eq!((winner, note.as_str()), (None, "draw adjudication"));
+        let mut swinging = quiet.clone();
+        swinging[3] = Some(-11);
+        assert!(adjudication(&options, &game, &swinging).is_none());
+
+        let early = Game::from_fen("8/5k2/8/8/3K4/8/4P3/8 w - - 0 39").unwrap();
+        assert!(adjudication(&options, &early, &quiet).is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use chesslibrary::engine::endgame::init_endgame_tables;
+use chesslibrary::engine::mate::{mate_search, MateSearchResult};
+use chesslibrary::engine::search::{search, SearchControl};
+use chesslibrary::epd::{parse_epd_file, EpdRecord};
+use chesslibrary::mv::Move;
+use chesslibrary::san::to_san;
+use std::fs;
+use std::process::exit;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: testsuite <epd file> [--time <ms>] [--depth <n>]";
+
+const DEFAULT_TIME_MS: u64 = 1000;
+
+// Runs the engine over a suite of EPD positions like WAC or STS with a fixed time or depth for each one, and reports which it solved.
+// Positions with bm or am are solved by finding a best move and no move to avoid, and positions with only dm by finding a mate that short
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut path: Option<String> = None;
+    let mut time_ms = DEFAULT_TIME_MS;
+    let mut depth: Option<i32> = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--time" => {
+                time_ms = args.get(i + 1).and_then(|value| value.parse().ok()).unwrap_or_else(|| fail(USAGE));
+                i += 1;
+            }
+            "--depth" => {
+                depth = Some(args.get(i + 1).and_then(|value| value.parse().ok()).unwrap_or_else(|| fail(USAGE)));
+                i += 1;
+            }
+            argument if path.is_none() => path = Some(argument.to_string()),
+            _ => fail::<()>(USAGE),
+        }
+        i += 1;
+    }
+    let path = path.unwrap_or_else(|| fail(USAGE));
+
+    let text = fs::read_to_string(&path).unwrap_or_else(|error| fail(&format!("Could not read {}: {}", path, error)));
+    let records = parse_epd_file(&text).unwrap_or_else(|error| fail(&format!("{}: {}", path, error)));
+
+    init_endgame_tables();
+
+    let mut solved = 0;
+    for (index, record) in records.iter().enumerate() {
+        let control = match depth {
+            Some(depth) => SearchControl::with_depth_limit(depth),
+            None => SearchControl::new(Duration::from_millis(time_ms), false),
+        };
+        let (found, is_solved) = run_position(record, &control);
+        if is_solved {
+            solved += 1;
+        }
+
+        let id = record.id().map(|id| id.to_string()).unwrap_or_else(|| format!("#{}", index + 1));
+        println!(
+            "{:>4} {:<16} expected {:<16} found {:<10} {}",
+            index + 1,
+            id,
+            expected(record),
+            found,
+            if is_solved { "solved" } else { "failed" }
+        );
+    }
+
+    println!("Solved {} of {}, failed {}", solved, records.len(), records.len() - solved);
+}
+
+// Searches one position and returns what was found, in SAN, and whether that solves it
+fn run_position(record: &EpdRecord, control: &SearchControl) -> (String, bool) {
+    let board = record.board();
+    let has_moves = record.operands("bm").is_some() | record.operands("am").is_some();
+
+    if let (Some(direct_mate), false) = (record.direct_mate(), has_moves) {
+        match mate_search(&board, direct_mate, control) {
+            MateSearchResult::Mate(pv) => {
+                let found = format!("{} (mate in {})", to_san(&board, pv[0]), pv.len().div_ceil(2));
+                return (found, true);
+            }
+            MateSearchResult::NoMate => return ("no mate".to_string(), false),
+            MateSearchResult::Aborted => return ("aborted".to_string(), false),
+        }
+    }
+
+    let result = search(&board, control, 1);
+    match result.best_move {
+        Some(mv) => return (to_san(&board, mv), record.is_solved_by(mv)),
+        None => return ("none".to_string(), false),
+    }
+}
+
+fn expected(record: &EpdRecord) -> String {
+    let board = record.board();
+    let san_moves = |moves: Vec<Move>| moves.iter().map(|mv| to_san(&board, *mv)).collect::<Vec<String>>().join(" ");
+    let mut expected: Vec<String> = Vec::new();
+    if !record.best_moves().is_empty() {
+        expected.push(format!("bm {}", san_moves(record.best_moves())));
+    }
+    if !record.avoid_moves().is_empty() {
+        expected.push(format!("am {}", san_moves(record.avoid_moves())));
+    }
+    if let Some(direct_mate) = record.direct_mate() {
+        expected.push(format!("dm {}", direct_mate));
+    }
+    return expected.join(", ");
+}
+
+fn fail<T>(message: &str) -> T {
+    eprintln!("{}", message);
+    exit(1);
+}
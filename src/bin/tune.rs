@@ -0,0 +1,65 @@
+use chesslibrary::engine::params::EvalParams;
+use chesslibrary::engine::tune::{fit_k, load_dataset, mean_error, tune, TuneOptions};
+use std::process::exit;
+use std::thread::available_parallelism;
+
+const USAGE: &str = "Usage: tune <dataset> [--params <file>] [--output <file>] [--iterations <n>] [--step <n>] [--only <name,name,...>] [--threads <n>] [--k <k>]";
+
+// Tunes the evaluation parameters against a labelled dataset, writing the result to a parameter file that load_eval_params or the
+// EvalFile UCI option can read
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(dataset_path) = args.first() else {
+        eprintln!("{}", USAGE);
+        exit(1);
+    };
+
+    let mut params = EvalParams::default();
+    let mut k: Option<f64> = None;
+    let mut options = TuneOptions {
+        iterations: 100,
+        step: 100,
+        only: Vec::new(),
+        threads: available_parallelism().map(|threads| threads.get()).unwrap_or(1),
+        output: "tuned_params.txt".to_string(),
+    };
+
+    let mut i = 1;
+    while i < args.len() {
+        let Some(value) = args.get(i + 1) else {
+            eprintln!("{}", USAGE);
+            exit(1);
+        };
+        match args[i].as_str() {
+            "--params" => params = EvalParams::from_file(value).unwrap_or_else(|error| fail(&error)),
+            "--output" => options.output = value.clone(),
+            "--iterations" => options.iterations = value.parse().unwrap_or_else(|_| fail(USAGE)),
+            "--step" => options.step = value.parse().unwrap_or_else(|_| fail(USAGE)),
+            "--only" => options.only = value.split(',').map(|name| name.to_string()).collect(),
+            "--threads" => options.threads = value.parse().unwrap_or_else(|_| fail(USAGE)),
+            "--k" => k = Some(value.parse().unwrap_or_else(|_| fail(USAGE))),
+            _ => fail(USAGE),
+        }
+        i += 2;
+    }
+
+    let positions = load_dataset(dataset_path).unwrap_or_else(|error| fail(&error));
+    println!("Loaded {} positions", positions.len());
+
+    let k = match k {
+        Some(k) => k,
+        None => {
+            let k = fit_k(&positions, &params, options.threads);
+            println!("Fitted K = {:.4} with error {:.8}", k, mean_error(&positions, &params, k, options.threads));
+            k
+        }
+    };
+
+    let error = tune(&positions, &mut params, k, &options).unwrap_or_else(|error| fail(&error));
+    println!("Finished with error {:.8}", error);
+}
+
+fn fail<T>(message: &str) -> T {
+    eprintln!("{}", message);
+    exit(1);
+}
@@ -1,28 +1,34 @@
 use std::fmt;
 use std::ops::{BitAnd, BitAndAssign, BitOr, BitXor, BitOrAssign, Not, Shl, Shr};
 use crate::square::{EIGHTH_RANK, FIFTH_RANK, FILE_A, FILE_B, FILE_C, FILE_D, FILE_E, FILE_F, FILE_G, FILE_H, FIRST_RANK, FOURTH_RANK, SECOND_RANK, SEVENTH_RANK, SIXTH_RANK, THIRD_RANK};
-use arrayvec::ArrayVec;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Bitboard(pub u64);
 
 pub const EMPTY_BITBOARD: Bitboard = Bitboard(0);
 
-impl Bitboard {
+// Iterator over the single square bitboards in a bitboard, from the lowest bit up
+pub struct ComponentBitboards(u64);
 
-    // Splits a bitboard with multiple 1s into a vector of component bitboards containing only one 1 in it. Since there can
-    // only be 10 pieces of each type maximum, the capacity is 10.
-    pub fn get_component_bitboards(&self) -> ArrayVec::<Bitboard,18> {
-        let mut bitboard_vector = ArrayVec::<Bitboard, 18>::new();
-        let mut bitboard_copy = self.clone().0 as u64;
-        let mut lowest_set_bit: u64 = 1;
-        while bitboard_copy != 0 {
-            lowest_set_bit = lowest_set_bit << bitboard_copy.trailing_zeros();
-            bitboard_vector.push(Bitboard(lowest_set_bit as u64));
-            bitboard_copy = bitboard_copy - lowest_set_bit;
-            lowest_set_bit = 1;
+impl Iterator for ComponentBitboards {
+    type Item = Bitboard;
+    
+    fn next(&mut self) -> Option<Bitboard> {
+        if self.0 == 0 {
+            return None;
         }
-        return bitboard_vector;
+        let lowest_set_bit = self.0 & self.0.wrapping_neg();
+        self.0 ^= lowest_set_bit;
+        return Some(Bitboard(lowest_set_bit));
+    }
+}
+
+impl Bitboard {
+
+    // Splits a bitboard with multiple 1s into its component bitboards containing only one 1 in it. They are made one at a time as
+    // they are iterated over, so there is no limit on how many there are, which Horde's 36 white pawns need
+    pub fn get_component_bitboards(&self) -> ComponentBitboards {
+        return ComponentBitboards(self.0);
     }
     
     pub fn count_ones(&self) -> u32 {
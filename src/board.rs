@@ -1,10 +1,15 @@
-use crate::mv::{Move, EMPTY_MOVE};
+use crate::mv::{IllegalMove, Move, EMPTY_MOVE};
 use crate::role::{Role, ByRole};
 use crate::colour::{Colour, ByColour};
-use crate::bitboard::{Bitboard, EMPTY_BITBOARD, bitboard_to_string_move};
+use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
 use crate::movegen::{get_bishop_attacks, get_black_pawn_attacks, get_knight_attacks, get_legal_moves, get_rook_attacks, get_white_pawn_attacks, get_king_attacks};
-use crate::castle::{ByCastleSide, CastleSide};
-use crate::square::Square;
+use crate::castle::{castle_destinations, ByCastleSide, CastleSide};
+use crate::square::{FILE_A, FILE_H};
+use crate::san::from_san;
+use crate::uci::{from_uci, to_uci};
+use crate::variant::{Variant, THREE_CHECKS};
+#[cfg(feature = "nnue")]
+use crate::engine::nnue::Accumulator;
 
 // Order of board
 // ....
@@ -17,16 +22,24 @@ pub struct Board {
     pub colour: ByColour<Bitboard>,
     pub occupied: Bitboard,
     pub turn: Colour,
-    pub castling_rights: ByColour<ByCastleSide<bool>>,
+    pub castling_rights: ByColour<ByCastleSide<Option<Bitboard>>>,
     pub castle_side: ByColour<Option<CastleSide>>,
     pub en_passant_target_square: Bitboard,
     pub last_move: Move,
     pub half_move_count: i32,
+    pub variant: Variant,
+    // Checks each side still has to give to win, which only Three-check uses
+    pub remaining_checks: ByColour<u8>,
+    // Castling is written in UCI as the king taking its own rook, which Chess960 positions need since the king can start next to the
+    // rook or on the square it castles to
+    pub chess960: bool,
+    #[cfg(feature = "nnue")]
+    pub accumulator: Accumulator,
 }
 
 impl Board {
     pub fn starting_position() -> Board {
-        Board {
+        let board = Board {
             role: ByRole {
                 pawn: Bitboard(0x00ff_0000_0000_ff00),
                 knight: Bitboard(0x4200_0000_0000_0042),
@@ -44,13 +57,13 @@ impl Board {
             castling_rights: ByColour {
                 black:
                     ByCastleSide { 
-                        kingside: true,
-                        queenside: true,
+                        kingside: Some(FILE_H),
+                        queenside: Some(FILE_A),
                     },
                 white:
                     ByCastleSide { 
-                        kingside: true,
-                        queenside: true,
+                        kingside: Some(FILE_H),
+                        queenside: Some(FILE_A),
                     }
             },
             castle_side: ByColour {
@@ -60,7 +73,56 @@ impl Board {
             en_passant_target_square: EMPTY_BITBOARD,
             last_move: EMPTY_MOVE,
             half_move_count: 0,
+            variant: Variant::Standard,
+            remaining_checks: ByColour { white: THREE_CHECKS, black: THREE_CHECKS },
+            chess960: false,
+            #[cfg(feature = "nnue")]
+            accumulator: Accumulator::new(),
+        };
+        
+        #[cfg(feature = "nnue")]
+        let board = board.with_fresh_accumulator();
+        
+        return board;
+    }
+
+    // One of the 960 Chess960 start positions, numbered the standard way so 518 is the normal start position. None if the index is
+    // 960 or more
+    pub fn chess960_position(index: u32) -> Option<Board> {
+        if index >= 960 {
+            return None;
+        }
+        
+        let mut back_rank: [Option<char>; 8] = [None; 8];
+        let mut n = index as usize;
+        
+        // Light squared bishop on b, d, f or h, then the dark squared one on a, c, e or g
+        back_rank[(n % 4) * 2 + 1] = Some('b');
+        n /= 4;
+        back_rank[(n % 4) * 2] = Some('b');
+        n /= 4;
+        
+        // The queen and knights go on the empty squares that are left, counting from the A file
+        let empty_files = |back_rank: &[Option<char>; 8]| (0..8).filter(|file| back_rank[*file].is_none()).collect::<Vec<usize>>();
+        let queen_file = empty_files(&back_rank)[n % 6];
+        back_rank[queen_file] = Some('q');
+        n /= 6;
+        
+        let (first_knight, second_knight) = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)][n];
+        let files = empty_files(&back_rank);
+        back_rank[files[first_knight]] = Some('n');
+        back_rank[files[second_knight]] = Some('n');
+        
+        // The king always goes between the two rooks
+        for (file, piece) in empty_files(&back_rank).into_iter().zip(['r', 'k', 'r']) {
+            back_rank[file] = Some(piece);
         }
+        
+        let black: String = back_rank.iter().flatten().collect();
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", black, black.to_ascii_uppercase());
+        let mut board = Board::from_fen(fen);
+        board.chess960 = true;
+        return Some(board);
     }
 
     pub fn empty_board() -> Board {
@@ -82,13 +144,13 @@ impl Board {
             castling_rights: ByColour {
                 black:
                     ByCastleSide { 
-                        kingside: false,
-                        queenside: false
+                        kingside: None,
+                        queenside: None,
                     },
                 white:
                     ByCastleSide { 
-                        kingside: false,
-                        queenside: false,
+                        kingside: None,
+                        queenside: None,
                     }
             },
             castle_side: ByColour { 
@@ -98,43 +160,52 @@ impl Board {
             en_passant_target_square: EMPTY_BITBOARD,
             last_move: EMPTY_MOVE,
             half_move_count: 0,
+            variant: Variant::Standard,
+            remaining_checks: ByColour { white: THREE_CHECKS, black: THREE_CHECKS },
+            chess960: false,
+            #[cfg(feature = "nnue")]
+            accumulator: Accumulator::new(),
         }
     }
     
-    // Makes move on the board
-    pub fn play(&mut self, mv: Move) {
+    // Makes a move on the board if it is legal. Nothing is printed, since stdout is the UCI channel
+    pub fn try_play(&mut self, mv: Move) -> Result<(), IllegalMove> {
+        if !get_legal_moves(self).contains(&mv) {
+            return Err(IllegalMove::new(self, to_uci(Some(mv), self.chess960)));
+        }
+        self.play_unsafe(mv);
+        return Ok(());
+    }
+
+    // Plays a move written in UCI, like "e2e4" or "e7e8q", and returns the move played
+    pub fn play_uci(&mut self, uci: &str) -> Result<Move, IllegalMove> {
+        let Ok(mv) = from_uci(self, uci) else {
+            return Err(IllegalMove::new(self, uci.to_string()));
+        };
+        self.play_unsafe(mv);
+        return Ok(mv);
+    }
 
-        println!("Playing move: {:?} from {} to {}", mv.role, bitboard_to_string_move(mv.from_square), bitboard_to_string_move(mv.to_square));
+    // Plays a move written in standard algebraic notation, like "Nf3" or "O-O", and returns the move played
+    pub fn play_san(&mut self, san: &str) -> Result<Move, IllegalMove> {
+        let Some(mv) = from_san(self, san) else {
+            return Err(IllegalMove::new(self, san.to_string()));
+        };
+        self.play_unsafe(mv);
+        return Ok(mv);
+    }
 
-        let legal_moves = get_legal_moves(self);
-        if legal_moves.contains(&mv) {
-            
-            self.castling_rights(mv);
+    // Makes a move without checking it is legal
+    pub fn play_unsafe(&mut self, mv: Move) {
 
-            if mv.castle == true {
-                self.play_castle(mv);
-            } else if mv.en_passant == true {
-                self.play_en_passant(mv);
-            } else if mv.promotion != None{
-                self.play_promotion(mv);
-            } else {
-                self.play_normal(mv);
-            }
-            
+        // The null move only passes the turn
+        if mv.from_square == EMPTY_BITBOARD {
             self.swap_turn();
-            
-            self.en_passant_target_square = mv.en_passant_target;
-
+            self.en_passant_target_square = EMPTY_BITBOARD;
             self.last_move = mv;
-
             self.half_move_count += 1;
-        } else {
-            eprintln!("Move: {:?}", mv);
-            panic!("Not a legal move!")
+            return;
         }
-    }
-
-    pub fn play_unsafe(&mut self, mv: Move) {
 
         self.castling_rights(mv);
 
@@ -148,7 +219,16 @@ impl Board {
             self.play_normal(mv);
         }
         
+        if (self.variant == Variant::Atomic) & mv.capture.is_some() {
+            self.explode(mv.to_square);
+        }
+        
         self.swap_turn();
+        
+        if (self.variant == Variant::ThreeCheck) && self.is_check(&self.turn) {
+            let checks = self.remaining_checks.get_mut(self.turn.opposite());
+            *checks = checks.saturating_sub(1);
+        }
 
         self.en_passant_target_square = mv.en_passant_target;
         
@@ -158,39 +238,10 @@ impl Board {
     }
     
     pub fn play_normal(&mut self, mv: Move) {
-        
-        // Takes castling rights if king or rook is moved
-        self.take_castling_rights(mv);
-        
         self.clear_square(&mv.to_square);
         self.set_square(&mv.to_square, &mv.role, &mv.colour);
         self.clear_square(&mv.from_square);
     }
-    
-    // Takes castling rights if king or rook is moved or rook is captured
-    pub fn take_castling_rights(&mut self, mv: Move) {
-        if mv.role == Some(Role::Rook) {
-            match mv.from_square {
-                Square::A1 => self.castling_rights.white.queenside = false,
-                Square::H1 => self.castling_rights.white.kingside = false,
-                Square::A8 => self.castling_rights.black.queenside = false,
-                Square::H8 => self.castling_rights.black.kingside = false,
-                _ => ()
-            }
-        } else if mv.role == Some(Role::King) {
-            match mv.from_square {
-                Square::E1 => {
-                    self.castling_rights.white.kingside = false;
-                    self.castling_rights.white.queenside = false;
-                },
-                Square::E8 => {
-                    self.castling_rights.black.kingside = false;
-                    self.castling_rights.black.queenside = false;
-                },
-                _ => ()
-            }
-        }
-    }
 
     pub fn unplay(&mut self, mv: Move) {
         self.clear_square(&mv.to_square);
@@ -209,40 +260,22 @@ impl Board {
         }
     }
     
+    // Castling moves go from the king to the rook it castles with. Both are taken off first, since in Chess960 either can end up on the
+    // others square
     pub fn play_castle(&mut self, mv: Move) {
+        let Some(colour) = mv.colour else {
+            return;
+        };
+        let side = Board::castle_side_of(mv);
+        let (king_destination, rook_destination) = castle_destinations(colour, side);
+        
         self.clear_square(&mv.from_square);
-        self.set_square(&mv.to_square, &mv.role, &mv.colour);
-        match mv.to_square {
-            Square::G1 => {
-                self.castle_side.white = Some(CastleSide::KingSide);
-                self.clear_square(&Square::H1);
-                self.set_square(&Square::F1, &Some(Role::Rook), &mv.colour);
-                self.castling_rights.white.kingside = false;
-                self.castling_rights.white.queenside = false;
-            }
-            Square::C1 => {
-                self.castle_side.white = Some(CastleSide::QueenSide);
-                self.clear_square(&Square::A1);
-                self.set_square(&Square::D1, &Some(Role::Rook), &mv.colour);
-                self.castling_rights.white.kingside = false;
-                self.castling_rights.white.queenside = false;
-            }
-            Square::G8 => {
-                self.castle_side.black = Some(CastleSide::KingSide);
-                self.clear_square(&Square::H8);
-                self.set_square(&Square::F8, &Some(Role::Rook), &mv.colour);
-                self.castling_rights.black.kingside = false;
-                self.castling_rights.black.queenside = false;
-            }
-            Square::C8 => {
-                self.castle_side.black = Some(CastleSide::QueenSide);
-                self.clear_square(&Square::A8);
-                self.set_square(&Square::D8, &Some(Role::Rook), &mv.colour);
-                self.castling_rights.black.kingside = false;
-                self.castling_rights.black.queenside = false;
-            }
-            _ => (),
-        }
+        self.clear_square(&mv.to_square);
+        self.set_square(&king_destination, &Some(Role::King), &mv.colour);
+        self.set_square(&rook_destination, &Some(Role::Rook), &mv.colour);
+        
+        *self.castle_side.get_mut(colour) = Some(side);
+        *self.castling_rights.get_mut(colour) = ByCastleSide { kingside: None, queenside: None };
     }
     
     pub fn play_en_passant(&mut self, mv: Move) {
@@ -279,6 +312,23 @@ impl Board {
     // Determines if the king of specified colour is in check on a given board. This is more efficient than checking all attacks for a given colour as it avoids recomputing
     pub fn is_check(&self, colour_to_check: &Colour) -> bool {
         let king_square: Bitboard;
+        
+        // There is no check in Antichess, and in Atomic kings next to each other can't be checked since taking one blows up the other
+        match self.variant {
+            Variant::Antichess => return false,
+            Variant::Atomic => {
+                let white_king = self.colour.white & self.role.king;
+                if (get_king_attacks(self, &white_king) & self.colour.black & self.role.king) != EMPTY_BITBOARD {
+                    return false;
+                }
+            }
+            _ => (),
+        }
+        // Nor is a side with no king, like white in Horde
+        if (self.colour.get(*colour_to_check) & self.role.king) == EMPTY_BITBOARD {
+            return false;
+        }
+        
         match colour_to_check {
             Colour::White => {
                 king_square = self.colour.white & self.role.king;
@@ -335,6 +385,16 @@ impl Board {
         }
     }
     
+    // Neither side can win: in standard chess bare kings, or a single knight or bishop against a bare king
+    pub fn is_insufficient_material(&self) -> bool {
+        return self.variant.is_insufficient_material(self);
+    }
+    
+    // The colour has too little left to ever mate, whatever the other side has
+    pub fn has_insufficient_material(&self, colour: Colour) -> bool {
+        return self.variant.has_insufficient_material(self, colour);
+    }
+    
     // Not very efficient, just need primitive for testing
     pub fn display_board(&self) {
         let mut set_bit: u64 = 0b1000000000000000000000000000000000000000000000000000000000000000;
@@ -379,4 +439,94 @@ impl Board {
             println!("{:?}", rank);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::perft_test;
+    use crate::uci::get_uci_square_bitboard;
+
+    #[test]
+    fn illegal_moves_leave_the_board_alone() {
+        let mut board = Board::starting_position();
+        let start = board.to_fen();
+        let black_move = from_uci(&Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1".to_string()), "e7e5").unwrap();
+
+        let error = board.try_play(black_move).unwrap_err();
+        assert_eq!((error.mv.as_str(), error.fen.as_str()), ("e7e5", start.as_str()));
+        assert_eq!(board.play_uci("e2e5").unwrap_err().mv, "e2e5");
+        assert_eq!(board.play_san("Nf6").unwrap_err().mv, "Nf6");
+        assert_eq!(board.to_fen(), start);
+    }
+
+    #[test]
+    fn plays_moves_in_every_notation() {
+        let mut board = Board::starting_position();
+        let e4 = board.play_uci("e2e4").unwrap();
+        assert_eq!(e4.to_square, get_uci_square_bitboard("e4"));
+        board.play_san("e5").unwrap();
+        let knight = from_san(&board, "Nf3").unwrap();
+        board.try_play(knight).unwrap();
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 0 2");
+    }
+
+    #[test]
+    fn chess960_perft() {
+        // From the standard Chess960 perft suite, with the castling rights in Shredder-FEN
+        let positions = [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189]),
+            ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18002]),
+            ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", [20, 479, 10471]),
+            ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", [22, 593, 13440]),
+            ("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", [28, 1120, 31058]),
+            ("qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9", [29, 899, 26578]),
+            ("q1bnrkr1/ppppp2p/2n2p2/4b1p1/2NP4/8/PPP1PPPP/QNB1RRKB w ge - 1 9", [30, 860, 24566]),
+            ("qn1rbbkr/ppp2p1p/1n1pp1p1/8/3P4/P6P/1PP1PPPK/QNNRBB1R w hd - 2 9", [28, 811, 23175]),
+            ("qnr1bkrb/pppp2pp/3np3/5p2/8/P2P2P1/NPP1PP1P/QN1RBKRB w GDg - 3 9", [33, 823, 26895]),
+            ("qb1nrkbr/1pppp1p1/1n3p2/p1B4p/8/3P1P1P/PPP1P1P1/QBNNRK1R w HEhe - 0 9", [31, 855, 25620]),
+        ];
+        for (fen, node_counts) in positions {
+            let board = Board::from_fen(fen.to_string());
+            assert!(board.chess960, "{}", fen);
+            for (depth, nodes) in node_counts.iter().enumerate() {
+                assert_eq!(perft_test(&board, depth as i32 + 1), *nodes, "{} at depth {}", fen, depth + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn shredder_fens_round_trip() {
+        // Shredder-FEN names every castling rook by its file, and X-FEN only does when there is another rook further out on that side
+        let fens = [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 0 9"),
+            ("qnr1bkrb/pppp2pp/3np3/5p2/8/P2P2P1/NPP1PP1P/QN1RBKRB w GDg - 3 9", "qnr1bkrb/pppp2pp/3np3/5p2/8/P2P2P1/NPP1PP1P/QN1RBKRB w KQk - 0 9"),
+            ("4k3/8/8/8/8/8/8/RK6 w A - 0 1", "4k3/8/8/8/8/8/8/RK6 w Q - 0 1"),
+            ("4k3/8/8/8/8/8/8/6KR w H - 0 1", "4k3/8/8/8/8/8/8/6KR w K - 0 1"),
+            ("rk2r3/8/8/8/8/8/8/RK2R3 b Eea - 0 1", "rk2r3/8/8/8/8/8/8/RK2R3 b Kkq - 0 1"),
+            ("r1k1r2r/8/8/8/8/8/8/R1K1R2R w EAha - 0 1", "r1k1r2r/8/8/8/8/8/8/R1K1R2R w EQkq - 0 1"),
+        ];
+        for (shredder, x_fen) in fens {
+            let board = Board::from_fen(shredder.to_string());
+            assert!(board.chess960, "{}", shredder);
+            assert_eq!(board.to_fen(), x_fen);
+
+            let written = Board::from_fen(board.to_fen());
+            assert!(written.chess960, "{}", x_fen);
+            assert_eq!(written.to_fen(), x_fen);
+            assert_eq!(format!("{:?}", written.castling_rights), format!("{:?}", board.castling_rights), "{}", shredder);
+        }
+    }
+
+    #[test]
+    fn only_chess960_positions_are_chess960() {
+        assert!(!Board::starting_position().chess960);
+        assert!(!Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".to_string()).chess960);
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/8/1R2K3 w - - 0 1".to_string()).chess960);
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/1R2K3 w Q - 0 1".to_string()).chess960);
+        assert!(Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1".to_string()).chess960);
+        for index in [0, 518, 959] {
+            assert!(Board::chess960_position(index).unwrap().chess960);
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use crate::mv::Move;
-use crate::{bitboard::Bitboard, square::Square};
+use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
 use crate::board::Board;
 use crate::colour::Colour;
 use crate::role::Role;
+use crate::square::{EIGHTH_RANK, FILE_C, FILE_D, FILE_F, FILE_G, FIRST_RANK};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum CastleSide {
@@ -10,66 +11,106 @@ pub enum CastleSide {
     QueenSide
 }
 
+// Castling rights are kept as the file of the rook that can castle, so the same board works for standard chess and Chess960
 #[derive(Debug, Clone, Copy)]
 pub struct ByCastleSide<T> {
     pub kingside: T,
     pub queenside: T,
 }
 
-// The squares in between the rook and king that need to be unchecked to castle
-pub const WHITE_KINGSIDE_CASTLE_CHECK_SQUARES: Bitboard = Bitboard(0b1110);
-pub const WHITE_QUEENSIDE_CASTLE_CHECK_SQUARES: Bitboard = Bitboard(0b111000);
+impl<T> ByCastleSide<T> {
+    pub fn get(&self, side: CastleSide) -> &T {
+        match side {
+            CastleSide::KingSide => return &self.kingside,
+            CastleSide::QueenSide => return &self.queenside,
+        }
+    }
+
+    pub fn get_mut(&mut self, side: CastleSide) -> &mut T {
+        match side {
+            CastleSide::KingSide => return &mut self.kingside,
+            CastleSide::QueenSide => return &mut self.queenside,
+        }
+    }
+}
 
-pub const BLACK_KINGSIDE_CASTLE_CHECK_SQUARES: Bitboard = Bitboard(0b111000000000000000000000000000000000000000000000000000000000);
-pub const BLACK_QUEENSIDE_CASTLE_CHECK_SQUARES: Bitboard = Bitboard(0b11100000000000000000000000000000000000000000000000000000000000);
+pub const CASTLE_SIDES: [CastleSide; 2] = [CastleSide::KingSide, CastleSide::QueenSide];
+
+pub fn back_rank(colour: Colour) -> Bitboard {
+    match colour {
+        Colour::White => return FIRST_RANK,
+        Colour::Black => return EIGHTH_RANK,
+    }
+}
 
-pub const WHITE_KINGSIDE_CASTLE_UNOCCUPIED_SQUARES: Bitboard = Bitboard(0b110);
-pub const WHITE_QUEENSIDE_CASTLE_UNOCCUPIED_SQUARES: Bitboard = Bitboard(0b1110000);
+// Where the king and rook end up after castling, which is the same in Chess960 as in standard chess
+pub fn castle_destinations(colour: Colour, side: CastleSide) -> (Bitboard, Bitboard) {
+    match side {
+        CastleSide::KingSide => return (FILE_G & back_rank(colour), FILE_F & back_rank(colour)),
+        CastleSide::QueenSide => return (FILE_C & back_rank(colour), FILE_D & back_rank(colour)),
+    }
+}
 
-pub const BLACK_KINGSIDE_CASTLE_UNOCCUPIED_SQUARES: Bitboard = Bitboard(0b11000000000000000000000000000000000000000000000000000000000);
-pub const BLACK_QUEENSIDE_CASTLE_UNOCCUPIED_SQUARES: Bitboard = Bitboard(0b111000000000000000000000000000000000000000000000000000000000000);
+// Every square on a rank from one square to another, including both ends
+pub fn squares_between_inclusive(a: Bitboard, b: Bitboard) -> Bitboard {
+    let low = a.trailing_zeros().min(b.trailing_zeros());
+    let high = a.trailing_zeros().max(b.trailing_zeros());
+    return Bitboard((u64::MAX >> (63 - high)) & (u64::MAX << low));
+}
 
 impl Board {
-    
-    // Determines what castling rights to remove from a move
-    pub fn castling_rights(&mut self, mv: Move) {
-        
-        if mv.role == Some(Role::King) {
-            match self.turn {
-                
-                Colour::White => {
-                    self.castling_rights.white.kingside = false;
-                    self.castling_rights.white.queenside = false;
-                }
-            
-                Colour::Black => {
-                    self.castling_rights.black.kingside = false;
-                    self.castling_rights.black.queenside = false;
-                }
-            }
+
+    // The square of the rook a colour can castle with on a side, if it still has the right to
+    pub fn castle_rook_square(&self, colour: Colour, side: CastleSide) -> Option<Bitboard> {
+        return self.castling_rights.get(colour).get(side).map(|file| file & back_rank(colour));
+    }
+
+    pub fn has_castling_rights(&self, colour: Colour) -> bool {
+        let rights = self.castling_rights.get(colour);
+        return rights.kingside.is_some() | rights.queenside.is_some();
+    }
+
+    // Which side a castling move castles on. Castling moves go from the king to its own rook, and the kingside rook is always towards
+    // the H file, which is the low bits
+    pub fn castle_side_of(mv: Move) -> CastleSide {
+        if mv.to_square.trailing_zeros() < mv.from_square.trailing_zeros() {
+            return CastleSide::KingSide;
         }
+        return CastleSide::QueenSide;
+    }
 
-        if mv.role == Some(Role::Rook) {
-            match self.turn {
+    // Squares that have to be empty and not attacked for a colour to castle on a side, or None if it can't castle there. Only the king
+    // and the castling rook can be on the path, and the path the king walks along can't be attacked, including where it starts
+    pub fn castle_path(&self, colour: Colour, side: CastleSide) -> Option<(Bitboard, Bitboard)> {
+        let rook_square = self.castle_rook_square(colour, side)?;
+        let king_square = self.colour.get(colour) & self.role.king & back_rank(colour);
+        if (king_square == EMPTY_BITBOARD) | ((rook_square & self.role.rook & self.colour.get(colour)) == EMPTY_BITBOARD) {
+            return None;
+        }
+        let (king_destination, rook_destination) = castle_destinations(colour, side);
+        let king_path = squares_between_inclusive(king_square, king_destination);
+        let must_be_empty = (king_path | squares_between_inclusive(rook_square, rook_destination)) & !(king_square | rook_square);
+        return Some((must_be_empty, king_path));
+    }
 
-                Colour::White => {
-                    if mv.from_square == Square::A1 {
-                        self.castling_rights.white.queenside = false;
-                    } else if mv.from_square == Square::H1 {
-                        self.castling_rights.white.kingside = false;
-                    }
-                }
-                
-                Colour::Black => {
-                    if mv.from_square == Square::A8 {
-                        self.castling_rights.black.queenside = false;
-                    } else if mv.from_square == Square::H8 {
-                        self.castling_rights.black.kingside = false;
-                    }
+    // Determines what castling rights to remove from a move. Moving the king loses both, and any move from or to a castling rook's
+    // square loses that side, which covers the rook moving and the rook being captured
+    pub fn castling_rights(&mut self, mv: Move) {
+
+        if mv.role == Some(Role::King) && let Some(colour) = mv.colour {
+            let rights = self.castling_rights.get_mut(colour);
+            rights.kingside = None;
+            rights.queenside = None;
+        }
+
+        for colour in [Colour::White, Colour::Black] {
+            for side in CASTLE_SIDES {
+                if let Some(rook_square) = self.castle_rook_square(colour, side)
+                    && ((mv.from_square | mv.to_square) & rook_square) != EMPTY_BITBOARD
+                {
+                    *self.castling_rights.get_mut(colour).get_mut(side) = None;
                 }
             }
         }
-    
-
     }
-}
\ No newline at end of file
+}
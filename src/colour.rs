@@ -12,6 +12,31 @@ pub struct ByColour<T> {
     pub white: T,
     pub black: T,
 }
+
+impl<T> ByColour<T> {
+    pub fn get(&self, colour: Colour) -> &T {
+        match colour {
+            Colour::White => &self.white,
+            Colour::Black => &self.black,
+        }
+    }
+    
+    pub fn get_mut(&mut self, colour: Colour) -> &mut T {
+        match colour {
+            Colour::White => &mut self.white,
+            Colour::Black => &mut self.black,
+        }
+    }
+}
+
+impl Colour {
+    pub fn opposite(&self) -> Colour {
+        match self {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        }
+    }
+}
  
  // Gets the colour of piece at a square
 pub fn get_colour(board: &Board, square: &Bitboard) -> Option<Colour> {
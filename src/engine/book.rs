@@ -0,0 +1,335 @@
+use crate::bitboard::Bitboard;
+use crate::board::Board;
+use crate::colour::Colour;
+use crate::movegen::get_legal_moves;
+use crate::mv::Move;
+use crate::pgn::parse_pgn;
+use crate::role::Role;
+use crate::san::from_san;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The book used by the search, set from the UCI options or Python
+static BOOK: Lazy<RwLock<Option<Book>>> = Lazy::new(|| RwLock::new(None));
+pub static USE_BOOK: AtomicBool = AtomicBool::new(false);
+pub static MAX_BOOK_DEPTH: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_BOOK_DEPTH);
+static BEST_BOOK_MOVE: AtomicBool = AtomicBool::new(false);
+static RANDOM_STATE: Lazy<AtomicU64> = Lazy::new(|| AtomicU64::new(SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as u64).unwrap_or(1).max(1)));
+
+// Plies from the start of the game the book is used for
+pub const DEFAULT_MAX_BOOK_DEPTH: usize = 20;
+
+// Every entry in a Polyglot book is 16 bytes, all big endian
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSelection {
+    // Picks a move at random with a chance in proportion to its weight
+    WeightedRandom,
+    // Always picks the move with the highest weight
+    Best,
+}
+
+// A Polyglot entry. The key is the Polyglot Zobrist hash of the position, which zobrist_hash already gives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+// Entries sorted by key, like they are in the file, so the moves for a position can be found with a binary search
+pub struct Book {
+    pub entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn from_file(path: &str) -> Result<Book, String> {
+        let bytes = fs::read(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        return Book::from_bytes(&bytes).map_err(|error| format!("{} in {}", error, path));
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Book, String> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err("Polyglot book size is not a multiple of 16 bytes".to_string());
+        }
+
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                mv: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect();
+
+        // Books should already be sorted, but sorting an already sorted list is cheap and a badly made book then still works
+        entries.sort_by_key(|entry| entry.key);
+        return Ok(Book { entries });
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.mv.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        return bytes;
+    }
+
+    pub fn to_file(&self, path: &str) -> Result<(), String> {
+        return fs::write(path, self.to_bytes()).map_err(|error| format!("Could not write {}: {}", path, error));
+    }
+
+    // Every entry for the position
+    pub fn entries_for(&self, board: &Board) -> &[BookEntry] {
+        let key = board.zobrist_hash();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        return &self.entries[start..end];
+    }
+
+    // The legal book moves for the position with their weights. Entries that don't match a legal move are left out
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        return self.entries_for(board).iter().filter_map(|entry| Some((decode_move(board, entry.mv)?, entry.weight))).collect();
+    }
+
+    pub fn pick_move(&self, board: &Board, selection: BookSelection) -> Option<Move> {
+        let moves = self.moves(board);
+        match selection {
+            BookSelection::Best => return moves.iter().max_by_key(|(_, weight)| *weight).map(|(mv, _)| *mv),
+            BookSelection::WeightedRandom => {
+                let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+                // Books made without weights are all 0, so every move is as likely as any other
+                if total == 0 {
+                    if moves.is_empty() {
+                        return None;
+                    }
+                    return Some(moves[(next_random() % moves.len() as u64) as usize].0);
+                }
+                let mut choice = next_random() % total;
+                for (mv, weight) in &moves {
+                    if choice < *weight as u64 {
+                        return Some(*mv);
+                    }
+                    choice -= *weight as u64;
+                }
+                return None;
+            }
+        }
+    }
+}
+
+// Polyglot squares are A1 as 0 and H8 as 63, the bitboards have H1 as bit 0 and A8 as bit 63
+fn polyglot_square(file: u16, row: u16) -> Bitboard {
+    return Bitboard(1 << (row * 8 + 7 - file));
+}
+
+fn square_file_row(square: Bitboard) -> (u16, u16) {
+    let index = square.trailing_zeros() as u16;
+    return (7 - index % 8, index / 8);
+}
+
+// Turns a Polyglot move into the legal move it stands for. Castling is stored as the king taking its own rook, like e1h1
+pub fn decode_move(board: &Board, encoded: u16) -> Option<Move> {
+    let to_square = polyglot_square(encoded & 7, (encoded >> 3) & 7);
+    let from_square = polyglot_square((encoded >> 6) & 7, (encoded >> 9) & 7);
+    let promotion = match (encoded >> 12) & 7 {
+        1 => Some(Role::Knight),
+        2 => Some(Role::Bishop),
+        3 => Some(Role::Rook),
+        4 => Some(Role::Queen),
+        _ => None,
+    };
+
+    // Castling moves already go from the king to the rook, the same as Polyglot writes them
+    return get_legal_moves(board).iter().copied().find(|mv| (mv.from_square == from_square) && (mv.to_square == to_square) && (mv.promotion == promotion));
+}
+
+pub fn encode_move(mv: Move) -> u16 {
+    let (to_file, to_row) = square_file_row(mv.to_square);
+    let (from_file, from_row) = square_file_row(mv.from_square);
+    let promotion = match mv.promotion {
+        Some(Role::Knight) => 1,
+        Some(Role::Bishop) => 2,
+        Some(Role::Rook) => 3,
+        Some(Role::Queen) => 4,
+        _ => 0,
+    };
+    return to_file | (to_row << 3) | (from_file << 6) | (from_row << 9) | (promotion << 12);
+}
+
+// Builds a book from the first max_plies plies of every game in the PGN text. Each move scores 2 for a win and 1 for a draw for the side
+// that played it, and moves that only ever lost are left out. Weights are scaled down to fit if any total is too big for 16 bits
+pub fn make_book(pgn: &str, max_plies: usize) -> Book {
+    let mut scores: HashMap<(u64, u16), u64> = HashMap::new();
+
+    for game in parse_pgn(pgn) {
+        let mut board = match game.tag("FEN") {
+            Some(fen) => Board::from_fen(fen.to_string()),
+            None => Board::starting_position(),
+        };
+
+        for san in game.moves.iter().take(max_plies) {
+            let Some(mv) = from_san(&board, san) else {
+                break;
+            };
+            let score = match (game.result.as_str(), board.turn) {
+                ("1-0", Colour::White) | ("0-1", Colour::Black) => 2,
+                ("1/2-1/2", _) => 1,
+                _ => 0,
+            };
+            *scores.entry((board.zobrist_hash(), encode_move(mv))).or_insert(0) += score;
+            board.play_unsafe(mv);
+        }
+    }
+
+    let max_score = scores.values().copied().max().unwrap_or(0);
+    let divisor = max_score.div_ceil(u16::MAX as u64).max(1);
+
+    let mut entries: Vec<BookEntry> = scores
+        .into_iter()
+        .filter(|(_, score)| *score > 0)
+        .map(|((key, mv), score)| BookEntry { key, mv, weight: (score / divisor).max(1) as u16, learn: 0 })
+        .collect();
+    entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)).then(a.mv.cmp(&b.mv)));
+
+    return Book { entries };
+}
+
+// Loads the book the search uses. An empty path or "<empty>" removes it
+pub fn load_book(path: &str) -> Result<(), String> {
+    if path.is_empty() || (path == "<empty>") {
+        *BOOK.write().unwrap() = None;
+        return Ok(());
+    }
+    let book = Book::from_file(path)?;
+    *BOOK.write().unwrap() = Some(book);
+    return Ok(());
+}
+
+pub fn set_book_selection(selection: BookSelection) {
+    BEST_BOOK_MOVE.store(selection == BookSelection::Best, Ordering::Relaxed);
+}
+
+// The move the loaded book gives for the position, if the book is turned on and the game isn't past the max book depth. The depth is
+// the plies played in the whole game, which a board from a FEN takes from its full move number
+pub fn book_move(board: &Board) -> Option<Move> {
+    if !USE_BOOK.load(Ordering::Relaxed) || (board.half_move_count as usize >= MAX_BOOK_DEPTH.load(Ordering::Relaxed)) {
+        return None;
+    }
+    let selection = if BEST_BOOK_MOVE.load(Ordering::Relaxed) { BookSelection::Best } else { BookSelection::WeightedRandom };
+    return BOOK.read().unwrap().as_ref()?.pick_move(board, selection);
+}
+
+// xorshift, shared between threads. Losing an update when two threads pick at once doesn't matter
+fn next_random() -> u64 {
+    let mut state = RANDOM_STATE.load(Ordering::Relaxed);
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    RANDOM_STATE.store(state, Ordering::Relaxed);
+    return state;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_san(board: &mut Board, moves: &[&str]) {
+        for san in moves {
+            let mv = from_san(board, san).unwrap_or_else(|| panic!("{} is not legal", san));
+            board.play_unsafe(mv);
+        }
+    }
+
+    #[test]
+    fn keys_match_the_polyglot_reference() {
+        // The positions and keys given in the Polyglot book format description
+        let lines: [(&[&str], u64); 9] = [
+            (&[], 0x463b96181691fc9c),
+            (&["e4"], 0x823c9b50fd114196),
+            (&["e4", "d5"], 0x0756b94461c50fb0),
+            (&["e4", "d5", "e5"], 0x662fafb965db29d4),
+            (&["e4", "d5", "e5", "f5"], 0x22a48b5a8e47ff78),
+            (&["e4", "d5", "e5", "f5", "Ke2"], 0x652a607ca3f242c1),
+            (&["e4", "d5", "e5", "f5", "Ke2", "Kf7"], 0x00fdd303c946bdd9),
+            (&["a4", "b5", "h4", "b4", "c4"], 0x3c8123ea7b067637),
+            (&["a4", "b5", "h4", "b4", "c4", "bxc3", "Ra3"], 0x5c3f9b829b279560),
+        ];
+        for (moves, key) in lines {
+            let mut board = Board::starting_position();
+            play_san(&mut board, moves);
+            assert_eq!(board.zobrist_hash(), key, "after {:?}", moves);
+        }
+    }
+
+    #[test]
+    fn moves_round_trip_through_the_encoding() {
+        // Castling both ways, promotions with and without a capture, and en passant
+        let fens = [
+            "r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPpP/R3K2R w KQkq - 0 1",
+            "r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPpP/R3K2R b KQkq - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen.to_string());
+            for mv in get_legal_moves(&board) {
+                assert_eq!(decode_move(&board, encode_move(mv)), Some(mv), "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn books_round_trip_through_bytes() {
+        let book = make_book("[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n\n[Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n", 10);
+        let read = Book::from_bytes(&book.to_bytes()).unwrap();
+        assert_eq!(read.entries, book.entries);
+        assert!(Book::from_bytes(&[0; 15]).is_err());
+    }
+
+    #[test]
+    fn made_books_give_their_moves_back() {
+        let pgn = "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n\n\
+                   [Result \"1-0\"]\n\n1. e4 c5 2. Nf3 d6 1-0\n\n\
+                   [Result \"0-1\"]\n\n1. d4 d5 2. c4 e6 0-1\n";
+        let book = make_book(pgn, 10);
+
+        let mut board = Board::starting_position();
+        // e4 won twice and d4 only lost, so d4 is left out
+        assert_eq!(book.moves(&board), vec![(from_san(&board, "e4").unwrap(), 4)]);
+
+        play_san(&mut board, &["d4"]);
+        assert_eq!(book.pick_move(&board, BookSelection::Best), from_san(&board, "d5"));
+
+        // Past max_plies nothing was added
+        let short_book = make_book(pgn, 1);
+        play_san(&mut board, &["d5"]);
+        assert_eq!(short_book.pick_move(&board, BookSelection::Best), None);
+
+        *BOOK.write().unwrap() = Some(book);
+        USE_BOOK.store(true, Ordering::Relaxed);
+        set_book_selection(BookSelection::Best);
+
+        let start = Board::starting_position();
+        assert_eq!(book_move(&start), from_san(&start, "e4"));
+        let mut after_d4 = Board::starting_position();
+        play_san(&mut after_d4, &["d4"]);
+        assert_eq!(book_move(&after_d4), from_san(&after_d4, "d5"));
+
+        // The same position deep into a game is past the max book depth, which comes from the full move number
+        let late = Board::from_fen("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 30".to_string());
+        assert_eq!(late.zobrist_hash(), after_d4.zobrist_hash());
+        assert_eq!(book_move(&late), None);
+        let early = Board::from_fen("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 10".to_string());
+        assert_eq!(book_move(&early), from_san(&early, "d5"));
+    }
+}
@@ -0,0 +1,550 @@
+use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
+use crate::board::Board;
+use crate::colour::Colour;
+use crate::engine::params::EvalParams;
+use crate::engine::pawns::{relative_rank, square_distance};
+use crate::role::Role;
+use crate::square::{FILE_A, FILE_H};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+// Added on top of the material in endgames that are known to be won, so the engine goes for them over anything the normal evaluation
+// likes. Well under the mate scores, which are i32::MAX and i32::MIN
+pub const KNOWN_WIN: i32 = 100000;
+
+// Scale factors are out of this, so a factor of SCALE_FACTOR_NORMAL leaves the evaluation as it is and 0 makes it a draw
+pub const SCALE_FACTOR_NORMAL: i32 = 64;
+
+// Squares the same colour as h1
+const LIGHT_SQUARES: Bitboard = bootstrap_light_squares();
+
+// Evaluates a known endgame from the strong sides point of view
+type EndgameFunction = fn(&Board, &EvalParams, Colour) -> i32;
+
+// The name of an endgame, its evaluation function and the colour of the strong side
+type EndgameEntry = (&'static str, EndgameFunction, Colour);
+
+// Specialised evaluations by material signature, written as the strong sides pieces then the weak sides pieces, each starting with the king
+const ENDGAMES: [(&str, EndgameFunction); 5] = [
+    ("KPK", kpk),
+    ("KBNK", kbnk),
+    ("KRKB", krkb),
+    ("KRKN", krkn),
+    ("KNNK", knnk),
+];
+
+// Maps the material of both sides to the evaluation function and the colour of the strong side. Each endgame is in here twice, once with
+// white as the strong side and once with black
+static ENDGAME_TABLE: Lazy<HashMap<(u32, u32), EndgameEntry>> = Lazy::new(|| {
+    let mut table = HashMap::new();
+    for (code, function) in ENDGAMES {
+        let weak_king = code[1..].find('K').unwrap() + 1;
+        let strong = signature_from_code(&code[..weak_king]);
+        let weak = signature_from_code(&code[weak_king..]);
+        table.insert((strong, weak), (code, function, Colour::White));
+        table.insert((weak, strong), (code, function, Colour::Black));
+    }
+    return table;
+});
+
+// Whether each king pawn versus king position is a win, indexed by kpk_index
+static KPK_BITBASE: Lazy<Vec<bool>> = Lazy::new(generate_kpk_bitbase);
+
+// Builds the endgame tables now instead of the first time an endgame is evaluated, which would otherwise come out of a search's time.
+// The KPK bitbase takes a noticeable fraction of a second
+pub fn init_endgame_tables() {
+    Lazy::force(&ENDGAME_TABLE);
+    Lazy::force(&KPK_BITBASE);
+}
+
+// The material one colour has, packed into 4 bits for each of pawns, knights, bishops, rooks and queens
+pub fn material_signature(board: &Board, colour: Colour) -> u32 {
+    let pieces = *board.colour.get(colour);
+    let mut signature = 0;
+    for (shift, role) in [Role::Pawn, Role::Knight, Role::Bishop, Role::Rook, Role::Queen].iter().enumerate() {
+        let count = (*board.role.get(*role) & pieces).count_ones().min(15);
+        signature |= count << (shift * 4);
+    }
+    return signature;
+}
+
+fn signature_from_code(code: &str) -> u32 {
+    let mut signature = 0;
+    for piece in code.chars() {
+        let shift = match piece {
+            'P' => 0,
+            'N' => 4,
+            'B' => 8,
+            'R' => 12,
+            'Q' => 16,
+            _ => continue,
+        };
+        signature += 1 << shift;
+    }
+    return signature;
+}
+
+// Finds the specialised evaluation for the material on the board, if there is one. Gives the name of the endgame and the evaluation from
+// whites point of view
+pub fn endgame_evaluation(board: &Board, params: &EvalParams) -> Option<(&'static str, i32)> {
+    let white = material_signature(board, Colour::White);
+    let black = material_signature(board, Colour::Black);
+
+    // A lone king against enough material to mate is the same endgame whatever the material is
+    let (evaluation, strong) = if (black == 0) && can_force_mate(board, Colour::White) {
+        (("KXK", kxk(board, params, Colour::White)), Colour::White)
+    } else if (white == 0) && can_force_mate(board, Colour::Black) {
+        (("KXK", kxk(board, params, Colour::Black)), Colour::Black)
+    } else if board.occupied.count_ones() <= 4 {
+        let (code, function, strong) = ENDGAME_TABLE.get(&(white, black))?;
+        ((*code, function(board, params, *strong)), *strong)
+    } else {
+        return None;
+    };
+
+    match strong {
+        Colour::White => return Some(evaluation),
+        Colour::Black => return Some((evaluation.0, -evaluation.1)),
+    }
+}
+
+// Pulls a drawish evaluation towards 0. The side the evaluation favours is taken as the strong side
+pub fn scale_evaluation(board: &Board, params: &EvalParams, evaluation: i32) -> i32 {
+    let strong = if evaluation > 0 { Colour::White } else { Colour::Black };
+    return evaluation * scale_factor(board, params, strong) / SCALE_FACTOR_NORMAL;
+}
+
+// How much of an advantage the strong side can expect to turn into a win, out of SCALE_FACTOR_NORMAL. Material is counted with the
+// middlegame values, so a bishop or a rook is worth exactly its parameter
+pub fn scale_factor(board: &Board, params: &EvalParams, strong: Colour) -> i32 {
+    let weak = strong.opposite();
+    let strong_pieces = *board.colour.get(strong);
+    let weak_pieces = *board.colour.get(weak);
+    let strong_pawns = strong_pieces & board.role.pawn;
+    let bishop_value = params.material_mg.bishop;
+    let rook_value = params.material_mg.rook;
+    let strong_material = non_pawn_material(board, params, strong);
+    let weak_material = non_pawn_material(board, params, weak);
+    let weak_king = weak_pieces & board.role.king;
+
+    // Without pawns the strong side needs to be at least a rook up to have real winning chances, and more than a minor piece up to
+    // have any
+    if strong_pawns == EMPTY_BITBOARD && strong_material - weak_material <= bishop_value {
+        if strong_material < rook_value {
+            return 0;
+        }
+        return 16;
+    }
+
+    // Pawns all on one rook file can't be forced through once the defending king gets in front of them, unless a bishop controls the
+    // promotion square
+    if (strong_pawns != EMPTY_BITBOARD) && (((strong_pawns & !FILE_A) == EMPTY_BITBOARD) | ((strong_pawns & !FILE_H) == EMPTY_BITBOARD)) {
+        let strong_bishops = strong_pieces & board.role.bishop;
+        let only_bishop = (strong_material == 0) | ((strong_material == bishop_value) && (strong_bishops != EMPTY_BITBOARD));
+        let promotion_square = promotion_square(strong, &strong_pawns);
+        let wrong_bishop = (strong_bishops == EMPTY_BITBOARD) || (is_light_square(&strong_bishops) != is_light_square(&promotion_square));
+        if only_bishop && wrong_bishop && (weak_king != EMPTY_BITBOARD) && (square_distance(&weak_king, &promotion_square) <= 1) {
+            return 0;
+        }
+    }
+
+    // Opposite coloured bishops are drawish, and very drawish when they are the only pieces left
+    let strong_bishops = strong_pieces & board.role.bishop;
+    let weak_bishops = weak_pieces & board.role.bishop;
+    if (strong_bishops.count_ones() == 1) && (weak_bishops.count_ones() == 1) && (is_light_square(&strong_bishops) != is_light_square(&weak_bishops)) {
+        if (strong_material == bishop_value) && (weak_material == bishop_value) {
+            return 16;
+        }
+        return 44;
+    }
+
+    return SCALE_FACTOR_NORMAL;
+}
+
+// Value of the knights, bishops, rooks and queens a colour has
+fn non_pawn_material(board: &Board, params: &EvalParams, colour: Colour) -> i32 {
+    let pieces = *board.colour.get(colour);
+    let mut material = 0;
+    for role in [Role::Knight, Role::Bishop, Role::Rook, Role::Queen] {
+        material += (*board.role.get(role) & pieces).count_ones() as i32 * params.material_mg.get(role);
+    }
+    return material;
+}
+
+// A colour has a queen, a rook or bishops on both colours, which is enough to mate a lone king without any special technique
+fn can_force_mate(board: &Board, colour: Colour) -> bool {
+    let pieces = *board.colour.get(colour);
+    let bishops = pieces & board.role.bishop;
+    if (pieces & (board.role.queen | board.role.rook)) != EMPTY_BITBOARD {
+        return true;
+    }
+    if (bishops & LIGHT_SQUARES != EMPTY_BITBOARD) && (bishops & !LIGHT_SQUARES != EMPTY_BITBOARD) {
+        return true;
+    }
+    // King bishop and knight versus king has its own evaluation
+    return false;
+}
+
+// Mating a lone king. The weak king is pushed to the edge and the strong king brought up to help
+fn kxk(board: &Board, params: &EvalParams, strong: Colour) -> i32 {
+    let strong_king = *board.colour.get(strong) & board.role.king;
+    let weak_king = *board.colour.get(strong.opposite()) & board.role.king;
+
+    let mut evaluation = KNOWN_WIN + material(board, params, strong);
+    evaluation += push_to_edge(&weak_king) * 1000;
+    evaluation += push_close(&strong_king, &weak_king) * 500;
+    return evaluation;
+}
+
+// King bishop and knight versus king. The weak king can only be mated in a corner the bishop controls, so it is driven there
+fn kbnk(board: &Board, params: &EvalParams, strong: Colour) -> i32 {
+    let strong_king = *board.colour.get(strong) & board.role.king;
+    let weak_king = *board.colour.get(strong.opposite()) & board.role.king;
+    let bishop = *board.colour.get(strong) & board.role.bishop;
+
+    // a1 and h8 are dark, a8 and h1 are light
+    let corners = match is_light_square(&bishop) {
+        true => [Bitboard(1 << 63), Bitboard(1)],
+        false => [Bitboard(1 << 7), Bitboard(1 << 56)],
+    };
+    let corner_distance = square_distance(&weak_king, &corners[0]).min(square_distance(&weak_king, &corners[1]));
+
+    let mut evaluation = KNOWN_WIN + material(board, params, strong);
+    evaluation += (7 - corner_distance) * 2000;
+    evaluation += push_close(&strong_king, &weak_king) * 500;
+    return evaluation;
+}
+
+// King rook versus king bishop is usually a draw, but the defending king is in more danger near the edge
+fn krkb(board: &Board, _params: &EvalParams, strong: Colour) -> i32 {
+    let weak_king = *board.colour.get(strong.opposite()) & board.role.king;
+    return push_to_edge(&weak_king) * 200;
+}
+
+// King rook versus king knight is usually a draw, but less so when the defending king is near the edge or away from its knight
+fn krkn(board: &Board, _params: &EvalParams, strong: Colour) -> i32 {
+    let weak_king = *board.colour.get(strong.opposite()) & board.role.king;
+    let weak_knight = *board.colour.get(strong.opposite()) & board.role.knight;
+    return push_to_edge(&weak_king) * 200 + square_distance(&weak_king, &weak_knight) * 300;
+}
+
+// Two knights can't force mate
+fn knnk(_board: &Board, _params: &EvalParams, _strong: Colour) -> i32 {
+    return 0;
+}
+
+// King pawn versus king, looked up in the bitbase. Won positions are scored by how far up the pawn is, and everything else is a draw
+fn kpk(board: &Board, params: &EvalParams, strong: Colour) -> i32 {
+    let pawn = *board.colour.get(strong) & board.role.pawn;
+    if !probe_kpk(board, strong) {
+        return 0;
+    }
+    return KNOWN_WIN + params.material_eg.pawn + relative_rank(strong, &pawn) as i32 * 1000;
+}
+
+// Whether the strong side wins a king and pawn versus king position with best play
+pub fn probe_kpk(board: &Board, strong: Colour) -> bool {
+    let strong_king = *board.colour.get(strong) & board.role.king;
+    let weak_king = *board.colour.get(strong.opposite()) & board.role.king;
+    let pawn = *board.colour.get(strong) & board.role.pawn;
+    if (strong_king == EMPTY_BITBOARD) | (weak_king == EMPTY_BITBOARD) | (pawn.count_ones() != 1) {
+        return false;
+    }
+
+    // The bitbase has white as the strong side and the pawn on files a to d, so other positions are flipped and mirrored to match
+    let flip = |square: &Bitboard| -> (i32, i32) {
+        let file = 7 - square.trailing_zeros() % 8;
+        let rank = square.trailing_zeros() / 8;
+        let rank = if strong == Colour::White { rank } else { 7 - rank };
+        let pawn_file = 7 - pawn.trailing_zeros() % 8;
+        let file = if pawn_file > 3 { 7 - file } else { file };
+        return (file, rank);
+    };
+    let (pawn_file, pawn_rank) = flip(&pawn);
+    let (strong_file, strong_rank) = flip(&strong_king);
+    let (weak_file, weak_rank) = flip(&weak_king);
+
+    let strong_to_move = board.turn == strong;
+    let index = kpk_index(strong_to_move, strong_rank * 8 + strong_file, weak_rank * 8 + weak_file, pawn_rank * 8 + pawn_file);
+    return KPK_BITBASE[index];
+}
+
+// Squares in the bitbase are rank * 8 + file from a1, with the pawn on files a to d and ranks 2 to 7
+fn kpk_index(white_to_move: bool, white_king: i32, black_king: i32, pawn: i32) -> usize {
+    let pawn_index = (pawn % 8) * 6 + (pawn / 8 - 1);
+    return (white_to_move as usize) + 2 * (white_king as usize + 64 * (black_king as usize + 64 * pawn_index as usize));
+}
+
+const KPK_SIZE: usize = 2 * 64 * 64 * 24;
+
+#[derive(Clone, Copy, PartialEq)]
+enum KpkResult {
+    Invalid,
+    Unknown,
+    Draw,
+    Win,
+}
+
+// Works out every king pawn versus king position by going backwards from the positions with a known result until nothing changes.
+// White wins by promoting the pawn safely, and black draws by taking the pawn or getting stalemated
+fn generate_kpk_bitbase() -> Vec<bool> {
+    let mut results = vec![KpkResult::Unknown; KPK_SIZE];
+    let mut positions = Vec::with_capacity(KPK_SIZE);
+    for pawn_file in 0..4 {
+        for pawn_rank in 1..7 {
+            for black_king in 0..64 {
+                for white_king in 0..64 {
+                    for white_to_move in [false, true] {
+                        let pawn = pawn_rank * 8 + pawn_file;
+                        let index = kpk_index(white_to_move, white_king, black_king, pawn);
+                        results[index] = initial_kpk_result(white_to_move, white_king, black_king, pawn);
+                        positions.push((index, white_to_move, white_king, black_king, pawn));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &(index, white_to_move, white_king, black_king, pawn) in &positions {
+            if results[index] != KpkResult::Unknown {
+                continue;
+            }
+            let result = match white_to_move {
+                true => white_kpk_result(&results, white_king, black_king, pawn),
+                false => black_kpk_result(&results, white_king, black_king, pawn),
+            };
+            if result != KpkResult::Unknown {
+                results[index] = result;
+                changed = true;
+            }
+        }
+    }
+
+    return results.into_iter().map(|result| result == KpkResult::Win).collect();
+}
+
+fn initial_kpk_result(white_to_move: bool, white_king: i32, black_king: i32, pawn: i32) -> KpkResult {
+    let promotion_square = 56 + pawn % 8;
+    if (distance(white_king, black_king) <= 1) | (white_king == pawn) | (black_king == pawn) {
+        return KpkResult::Invalid;
+    }
+    // Black can't be in check with white to move
+    if white_to_move && pawn_attacks(pawn, black_king) {
+        return KpkResult::Invalid;
+    }
+    // The pawn promotes and the new queen can't be taken
+    let promotes = white_to_move && (pawn / 8 == 6) && (white_king != promotion_square) && (black_king != promotion_square);
+    if promotes && ((distance(black_king, promotion_square) > 1) | (distance(white_king, promotion_square) == 1)) {
+        return KpkResult::Win;
+    }
+    return KpkResult::Unknown;
+}
+
+// White wins if any move wins, and draws once every move is known to draw
+fn white_kpk_result(results: &[KpkResult], white_king: i32, black_king: i32, pawn: i32) -> KpkResult {
+    let mut all_draws = true;
+    let mut successors = Vec::new();
+    for square in king_moves(white_king) {
+        successors.push(kpk_index(false, square, black_king, pawn));
+    }
+    let push = pawn + 8;
+    if (pawn / 8 < 6) && (push != white_king) && (push != black_king) {
+        successors.push(kpk_index(false, white_king, black_king, push));
+        let double_push = pawn + 16;
+        if (pawn / 8 == 1) && (double_push != white_king) && (double_push != black_king) {
+            successors.push(kpk_index(false, white_king, black_king, double_push));
+        }
+    }
+
+    for index in successors {
+        match results[index] {
+            KpkResult::Win => return KpkResult::Win,
+            KpkResult::Unknown => all_draws = false,
+            _ => {}
+        }
+    }
+    return if all_draws { KpkResult::Draw } else { KpkResult::Unknown };
+}
+
+// Black draws if any move draws, and loses once every move is known to lose
+fn black_kpk_result(results: &[KpkResult], white_king: i32, black_king: i32, pawn: i32) -> KpkResult {
+    let mut all_wins = true;
+    let mut has_move = false;
+    for square in king_moves(black_king) {
+        if (distance(square, white_king) <= 1) | pawn_attacks(pawn, square) {
+            continue;
+        }
+        has_move = true;
+        // Taking the pawn, which is safe since the white king isn't next to it
+        if square == pawn {
+            return KpkResult::Draw;
+        }
+        match results[kpk_index(true, white_king, square, pawn)] {
+            KpkResult::Draw => return KpkResult::Draw,
+            KpkResult::Unknown => all_wins = false,
+            _ => {}
+        }
+    }
+
+    if !has_move {
+        // Mate is impossible with a pawn on its own, so this is stalemate
+        if !pawn_attacks(pawn, black_king) {
+            return KpkResult::Draw;
+        }
+        return KpkResult::Win;
+    }
+    return if all_wins { KpkResult::Win } else { KpkResult::Unknown };
+}
+
+fn king_moves(square: i32) -> Vec<i32> {
+    let mut moves = Vec::new();
+    for rank_step in -1..=1 {
+        for file_step in -1..=1 {
+            let (file, rank) = (square % 8 + file_step, square / 8 + rank_step);
+            if (rank_step, file_step) != (0, 0) && (0..8).contains(&file) && (0..8).contains(&rank) {
+                moves.push(rank * 8 + file);
+            }
+        }
+    }
+    return moves;
+}
+
+fn pawn_attacks(pawn: i32, square: i32) -> bool {
+    return (square / 8 == pawn / 8 + 1) && ((square % 8 - pawn % 8).abs() == 1);
+}
+
+fn distance(first: i32, second: i32) -> i32 {
+    return (first % 8 - second % 8).abs().max((first / 8 - second / 8).abs());
+}
+
+// Material for a colour in the endgame
+fn material(board: &Board, params: &EvalParams, colour: Colour) -> i32 {
+    let pieces = *board.colour.get(colour);
+    let mut material = 0;
+    for role in [Role::Pawn, Role::Knight, Role::Bishop, Role::Rook, Role::Queen] {
+        material += (*board.role.get(role) & pieces).count_ones() as i32 * params.material_eg.get(role);
+    }
+    return material;
+}
+
+// 0 in the centre up to 6 in the corners
+fn push_to_edge(square: &Bitboard) -> i32 {
+    let file = 7 - square.trailing_zeros() % 8;
+    let rank = square.trailing_zeros() / 8;
+    return ((2 * file - 7).abs() + (2 * rank - 7).abs()) / 2 - 1;
+}
+
+// 6 with the kings next to each other down to 0 with them on opposite corners
+fn push_close(first: &Bitboard, second: &Bitboard) -> i32 {
+    return 7 - square_distance(first, second);
+}
+
+const fn bootstrap_light_squares() -> Bitboard {
+    let mut squares = 0;
+    let mut index = 0;
+    while index < 64 {
+        let (file, rank) = (7 - index % 8, index / 8);
+        if (file + rank) % 2 == 1 {
+            squares |= 1 << index;
+        }
+        index += 1;
+    }
+    return Bitboard(squares);
+}
+
+// Whether a single square is light. a1 is dark
+fn is_light_square(square: &Bitboard) -> bool {
+    return (*square & LIGHT_SQUARES) != EMPTY_BITBOARD;
+}
+
+// The square the most advanced pawn would promote on
+fn promotion_square(colour: Colour, pawns: &Bitboard) -> Bitboard {
+    let file = 7 - pawns.trailing_zeros() % 8;
+    let rank = if colour == Colour::White { 7 } else { 0 };
+    return Bitboard(1 << (rank * 8 + (7 - file)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kpk_wins(fen: &str, strong: Colour) -> bool {
+        return probe_kpk(&Board::from_fen(fen.to_string()), strong);
+    }
+
+    #[test]
+    fn rook_pawns_are_drawn_with_the_king_in_front() {
+        for turn in ["w", "b"] {
+            assert!(!kpk_wins(&format!("k7/8/8/8/8/8/P7/K7 {} - -", turn), Colour::White));
+            assert!(!kpk_wins(&format!("k7/8/1K6/P7/8/8/8/8 {} - -", turn), Colour::White));
+            assert!(!kpk_wins(&format!("8/8/8/8/7p/6k1/8/7K {} - -", turn), Colour::Black));
+        }
+    }
+
+    #[test]
+    fn kings_on_key_squares_win() {
+        // d6, e6 and f6 are the key squares for a pawn on e4, and reaching one wins whoever is to move
+        for turn in ["w", "b"] {
+            assert!(kpk_wins(&format!("4k3/8/4K3/8/4P3/8/8/8 {} - -", turn), Colour::White));
+            assert!(kpk_wins(&format!("2k5/8/5K2/8/4P3/8/8/8 {} - -", turn), Colour::White));
+            assert!(kpk_wins(&format!("8/8/8/4p3/8/4k3/8/4K3 {} - -", turn), Colour::Black));
+        }
+    }
+
+    #[test]
+    fn the_opposition_depends_on_the_side_to_move() {
+        // With the kings in opposition the side to move has to give way
+        assert!(!kpk_wins("8/4k3/8/4K3/4P3/8/8/8 w - -", Colour::White));
+        assert!(kpk_wins("8/4k3/8/4K3/4P3/8/8/8 b - -", Colour::White));
+        assert!(!kpk_wins("8/8/8/4p3/4k3/8/4K3/8 b - -", Colour::Black));
+        assert!(kpk_wins("8/8/8/4p3/4k3/8/4K3/8 w - -", Colour::Black));
+
+        // The black king is just inside the square of the pawn when it is black to move
+        assert!(!kpk_wins("8/8/8/8/P4k2/8/8/7K b - -", Colour::White));
+        assert!(kpk_wins("8/8/8/8/P4k2/8/8/7K w - -", Colour::White));
+    }
+
+    #[test]
+    fn endgames_are_found_by_their_material() {
+        let params = EvalParams::default();
+        let endgames = [
+            ("8/8/8/4k3/8/8/8/KQ6 w - -", "KXK", 1),
+            ("kq6/8/8/8/8/3K4/8/8 w - -", "KXK", -1),
+            ("8/8/3k4/8/8/8/4KBN1/8 w - -", "KBNK", 1),
+            ("8/4kbn1/8/8/8/3K4/8/8 b - -", "KBNK", -1),
+            ("3k4/8/8/3b4/8/8/4KR2/8 w - -", "KRKB", 1),
+            ("3k4/8/8/3n4/8/8/4KR2/8 w - -", "KRKN", 1),
+            ("8/4kr2/8/8/3N4/8/8/3K4 b - -", "KRKN", -1),
+            ("3k4/8/8/8/8/8/4KNN1/8 w - -", "KNNK", 0),
+            ("4k3/8/4K3/8/4P3/8/8/8 w - -", "KPK", 1),
+            ("8/8/8/4p3/8/4k3/8/4K3 w - -", "KPK", -1),
+            ("k7/8/8/8/8/8/P7/K7 w - -", "KPK", 0),
+        ];
+        for (fen, name, sign) in endgames {
+            let (found, evaluation) = endgame_evaluation(&Board::from_fen(fen.to_string()), &params).unwrap();
+            assert_eq!(found, name, "{}", fen);
+            assert_eq!(evaluation.signum(), sign, "{}", fen);
+        }
+
+        // A lone minor piece has no endgame of its own, and neither does anything with more than four pieces
+        assert!(endgame_evaluation(&Board::from_fen("3k4/8/8/8/8/8/4KB2/8 w - -".to_string()), &params).is_none());
+        assert!(endgame_evaluation(&Board::from_fen("3k4/3p4/8/8/8/8/4KRP1/8 w - -".to_string()), &params).is_none());
+    }
+
+    #[test]
+    fn drawish_material_is_scaled_down() {
+        let params = EvalParams::default();
+        let scale = |fen: &str, strong: Colour| scale_factor(&Board::from_fen(fen.to_string()), &params, strong);
+        assert_eq!(scale("3k4/8/8/8/8/8/4KRP1/8 w - -", Colour::White), SCALE_FACTOR_NORMAL);
+        // A minor piece up without pawns
+        assert_eq!(scale("3k4/8/8/8/8/8/4KB2/8 w - -", Colour::White), 0);
+        // The wrong bishop for a rook pawn
+        assert_eq!(scale("k7/8/8/8/8/8/P7/K1B5 w - -", Colour::White), 0);
+        assert_eq!(scale("k7/8/8/8/8/8/P7/KB6 w - -", Colour::White), SCALE_FACTOR_NORMAL);
+        // Only opposite coloured bishops
+        assert_eq!(scale("3k4/3b4/8/8/8/8/4KBPP/8 w - -", Colour::White), 16);
+    }
+}
@@ -1,36 +1,195 @@
-use crate::movegen::{get_king_attacks, get_bishop_attacks, get_black_pawn_attacks, get_knight_attacks, get_rook_attacks, get_white_pawn_attacks};
-use crate::square::{NEIGHBOUR_FILES};
+use crate::movegen::{get_king_attacks, get_bishop_attacks, get_black_pawn_attacks, get_knight_attacks, get_queen_attacks, get_rook_attacks, get_white_pawn_attacks};
 use crate::{board::Board};
-use crate::colour::Colour;
-use crate::castle::CastleSide;
+use crate::colour::{ByColour, Colour};
 use crate::engine::search::NODE_COUNT;
-use std::sync::atomic::{Ordering};
-use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
-
-// Pawn structure bitboards
-const WHITE_KINGSIDE_PAWN_STRUCTURE: Bitboard = Bitboard(0b10000011100000000);
-const WHITE_QUEENSIDE_PAWN_STRUCTURE: Bitboard = Bitboard(0b100000001110000000000000);
-const BLACK_KINGSIDE_PAWN_STRUCTURE: Bitboard = Bitboard(0b111000000010000000000000000000000000000000000000000);
-const BLACK_QUEENSIDE_PAWN_STRUCTURE: Bitboard = Bitboard(0b11100000100000000000000000000000000000000000000000000000);
-const DFILE_EFILE_PAWN_CENTER_SQUARES: Bitboard = Bitboard(0b0000000000000000000110000001100000011000000110000000000000000000);
-
-const PAWN_MATERIAL_VALUE: i32 = 10000;
-const KNIGHT_MATERIAL_VALUE: i32 = 30500;
-const BISHOP_MATERIAL_VALUE: i32 = 33300;
-const ROOK_MATERIAL_VALUE: i32 = 56300;
-const QUEEN_MATERIAL_VALUE: i32 = 95000;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::bitboard::Bitboard;
+use crate::engine::params::{EvalParams, EVAL_PARAMS, MOBILITY_ROLES};
+use crate::engine::king_safety::king_safety_evaluation;
+use crate::engine::endgame::{endgame_evaluation, scale_evaluation};
+use crate::engine::pawns::{passed_pawn_king_evaluation, probe_pawn_structure};
+use crate::role::{Role, ALL_ROLES};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use serde::Serialize;
+#[cfg(feature = "nnue")]
+use crate::engine::nnue::{current_network, nnue_evaluate};
+
+// Debug switch to go back to the old mobility, where the evaluation was multiplied by the ratio of white to black attacks. Only for
+// comparing against the additive mobility terms
+pub static USE_LEGACY_MOBILITY: AtomicBool = AtomicBool::new(false);
+
+// How much each piece counts towards the game phase. The phase is the total of these for the pieces left, capped at the starting total
+const KNIGHT_PHASE: i32 = 1;
+const BISHOP_PHASE: i32 = 1;
+const ROOK_PHASE: i32 = 2;
+const QUEEN_PHASE: i32 = 4;
+pub const TOTAL_PHASE: i32 = 24;
+
+// A middlegame and an endgame score, blended together by the game phase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const fn new(mg: i32, eg: i32) -> Score {
+        Score { mg, eg }
+    }
+    
+    // Blends the middlegame and endgame scores, where a phase of TOTAL_PHASE is a full middlegame and 0 is a bare endgame
+    pub fn taper(&self, phase: i32) -> i32 {
+        return ((self.mg as i64 * phase as i64 + self.eg as i64 * (TOTAL_PHASE - phase) as i64) / TOTAL_PHASE as i64) as i32;
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+    fn add(self, rhs: Score) -> Score {
+        Score::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+    fn sub(self, rhs: Score) -> Score {
+        Score::new(self.mg - rhs.mg, self.eg - rhs.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Score) {
+        self.mg += rhs.mg;
+        self.eg += rhs.eg;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Score) {
+        self.mg -= rhs.mg;
+        self.eg -= rhs.eg;
+    }
+}
+
+// Every evaluation term for both colours, before they are added together and tapered
+#[derive(Debug, Clone, Copy)]
+pub struct EvalTerms {
+    pub material: ByColour<Score>,
+    pub psqt: ByColour<Score>,
+    pub pawn_structure: ByColour<Score>,
+    pub passed_pawn_kings: ByColour<Score>,
+    pub king_attacks: ByColour<Score>,
+    pub pawn_shield: ByColour<Score>,
+    pub pawn_storm: ByColour<Score>,
+    pub king_files: ByColour<Score>,
+    pub safe_checks: ByColour<Score>,
+    pub castling: ByColour<Score>,
+    pub mobility: ByColour<Score>,
+}
+
+impl EvalTerms {
+    // Each term with its name, in the order they are listed in an evaluation trace
+    pub fn named_terms(&self) -> [(&'static str, ByColour<Score>); 11] {
+        return [
+            ("material", self.material),
+            ("psqt", self.psqt),
+            ("pawn_structure", self.pawn_structure),
+            ("passed_pawn_kings", self.passed_pawn_kings),
+            ("king_attacks", self.king_attacks),
+            ("pawn_shield", self.pawn_shield),
+            ("pawn_storm", self.pawn_storm),
+            ("king_files", self.king_files),
+            ("safe_checks", self.safe_checks),
+            ("castling", self.castling),
+            ("mobility", self.mobility),
+        ];
+    }
+    
+    // Sum of every term from whites point of view
+    pub fn total(&self) -> Score {
+        let mut total = Score::default();
+        for (_, term) in self.named_terms() {
+            total += term.white - term.black;
+        }
+        return total;
+    }
+}
+
+// Works out every evaluation term for both colours
+pub fn evaluation_terms(board: &Board, params: &EvalParams) -> EvalTerms {
+    // Pawn structure comes from the pawn hash table, but how close the kings are to the passed pawns has to be worked out every time
+    let pawn_entry = probe_pawn_structure(board, params);
+    let white_king_safety = king_safety_evaluation(board, params, Colour::White);
+    let black_king_safety = king_safety_evaluation(board, params, Colour::Black);
+    
+    return EvalTerms {
+        material: ByColour { white: material_score(board, params, &Colour::White), black: material_score(board, params, &Colour::Black) },
+        psqt: ByColour { white: psqt_evaluation(board, params, &Colour::White), black: psqt_evaluation(board, params, &Colour::Black) },
+        pawn_structure: pawn_entry.score,
+        passed_pawn_kings: ByColour {
+            white: passed_pawn_king_evaluation(board, params, Colour::White, &pawn_entry.passed_pawns.white),
+            black: passed_pawn_king_evaluation(board, params, Colour::Black, &pawn_entry.passed_pawns.black),
+        },
+        king_attacks: ByColour { white: white_king_safety.king_attacks, black: black_king_safety.king_attacks },
+        pawn_shield: ByColour { white: white_king_safety.pawn_shield, black: black_king_safety.pawn_shield },
+        pawn_storm: ByColour { white: white_king_safety.pawn_storm, black: black_king_safety.pawn_storm },
+        king_files: ByColour { white: white_king_safety.open_files, black: black_king_safety.open_files },
+        safe_checks: ByColour { white: white_king_safety.safe_checks, black: black_king_safety.safe_checks },
+        castling: ByColour { white: castling_evaluation(board, params, Colour::White), black: castling_evaluation(board, params, Colour::Black) },
+        mobility: ByColour { white: mobility_evaluation(board, params, &Colour::White), black: mobility_evaluation(board, params, &Colour::Black) },
+    };
+}
+
+// The hand crafted evaluation from whites point of view with the given parameters. Known endgames use their own evaluation, and
+// everything else is the tapered terms pulled towards a draw by the scale factor. The tuner fits this, so it tunes the function the
+// engine plays with
+pub fn evaluate_with_params(board: &Board, params: &EvalParams) -> i32 {
+    if let Some((_, evaluation)) = endgame_evaluation(board, params) {
+        return evaluation;
+    }
+    return scale_evaluation(board, params, evaluation_terms(board, params).total().taper(game_phase(board)));
+}
 
 // Provides a positive i32 if the colour provided is doing better than the other colour, and a negative value if the colour is doing worse
 pub fn evaluate(board: &Board, colour: &Colour) -> i32 {
     
     NODE_COUNT.fetch_add(1, Ordering::Relaxed);
     
-    let mut evaluation: i32 = 0;
+    let params = EVAL_PARAMS.read().unwrap();
+    
+    // Known endgames have their own evaluations, which are used whether or not a network is loaded
+    if let Some((_, evaluation)) = endgame_evaluation(board, &params) {
+        match colour {
+            Colour::White => return evaluation,
+            Colour::Black => return -evaluation,
+        }
+    }
+    
+    // The network takes over the rest of the evaluation once one is loaded
+    #[cfg(feature = "nnue")]
+    if let Some(network) = current_network() {
+        let evaluation = nnue_evaluate(board, &network);
+        if *colour == board.turn {
+            return evaluation;
+        } else {
+            return -evaluation;
+        }
+    }
+    
+    if !USE_LEGACY_MOBILITY.load(Ordering::Relaxed) {
+        let evaluation = evaluate_with_params(board, &params);
+        match colour {
+            Colour::White => return evaluation,
+            Colour::Black => return -evaluation,
+        }
+    }
+    
+    let phase = game_phase(board);
+    let terms = evaluation_terms(board, &params);
+    let mut evaluation: i32 = scale_evaluation(board, &params, terms.total().taper(phase));
     
-    // Material evaluation
-    evaluation += material_evaluation(board, &Colour::White) - material_evaluation(board, &Colour::Black);
-    evaluation += pawn_evaluation(board, &(board.colour.white & board.role.pawn)) - pawn_evaluation(board, &(board.colour.black & board.role.pawn));
-    evaluation += castling_evaluation(board, &Colour::White) - castling_evaluation(board, &Colour::Black);
+    // The additive mobility terms are replaced by the old multiplier
+    evaluation -= (terms.mobility.white - terms.mobility.black).taper(phase);
     
     //Testing additional 100 to help with mobility
     let mut adjusted_mobility_evaluation: f32 = evaluation as f32;
@@ -55,126 +214,109 @@ pub fn evaluate(board: &Board, colour: &Colour) -> i32 {
     }
 }
 
-// Gets overall material evaluation for a colour of 
-pub fn material_evaluation(board: &Board, colour: &Colour) -> i32 {
-    let mut material_evaluation: i32 = 0;
-    let pawns: Bitboard;
-    let knights: Bitboard;
-    let bishops: Bitboard;
-    let rooks: Bitboard;
-    let queens: Bitboard;
+// Counts the game phase from the non pawn material left. TOTAL_PHASE at the start down to 0 with only kings and pawns
+pub fn game_phase(board: &Board) -> i32 {
+    let mut phase: i32 = 0;
+    phase += board.role.knight.count_ones() as i32 * KNIGHT_PHASE;
+    phase += board.role.bishop.count_ones() as i32 * BISHOP_PHASE;
+    phase += board.role.rook.count_ones() as i32 * ROOK_PHASE;
+    phase += board.role.queen.count_ones() as i32 * QUEEN_PHASE;
+    return phase.min(TOTAL_PHASE);
+}
 
-    match colour {
-        Colour::White => {
-            pawns = board.role.pawn & board.colour.white;
-            knights = board.role.knight & board.colour.white;
-            bishops = board.role.bishop & board.colour.white;
-            rooks = board.role.rook & board.colour.white;
-            queens = board.role.queen & board.colour.white;
-        }
-        Colour::Black => {
-            pawns = board.role.pawn & board.colour.black;
-            knights = board.role.knight & board.colour.black;
-            bishops = board.role.bishop & board.colour.black;
-            rooks = board.role.rook & board.colour.black;
-            queens = board.role.queen & board.colour.black;
-        }
-    }
+// Gets the middlegame and endgame material values for a colour
+pub fn material_score(board: &Board, params: &EvalParams, colour: &Colour) -> Score {
+    let mut score = Score::default();
+    let pieces = match colour {
+        Colour::White => board.colour.white,
+        Colour::Black => board.colour.black,
+    };
     
-    material_evaluation += pawns.count_ones() as i32 * PAWN_MATERIAL_VALUE;
-    material_evaluation += knights.count_ones() as i32 * KNIGHT_MATERIAL_VALUE;
-    material_evaluation += bishops.count_ones() as i32 * BISHOP_MATERIAL_VALUE;
-    material_evaluation += rooks.count_ones() as i32 * ROOK_MATERIAL_VALUE;
-    material_evaluation += queens.count_ones() as i32 * QUEEN_MATERIAL_VALUE;
+    for role in ALL_ROLES {
+        let count = (*board.role.get(role) & pieces).count_ones() as i32;
+        score += Score::new(count * params.material_mg.get(role), count * params.material_eg.get(role));
+    }
     
-    return material_evaluation;
+    return score;
 }
 
-// evaluation for pawns
-pub fn pawn_evaluation(board: &Board, pawns: &Bitboard) -> i32 {
-    let mut pawn_evaluation: i32 = 0;
+// Gets the middlegame and endgame piece-square values for a colour
+pub fn psqt_evaluation(board: &Board, params: &EvalParams, colour: &Colour) -> Score {
+    let mut score = Score::default();
+    let pieces = match colour {
+        Colour::White => board.colour.white,
+        Colour::Black => board.colour.black,
+    };
     
-    pawn_evaluation += (DFILE_EFILE_PAWN_CENTER_SQUARES & pawns).count_ones() as i32 * PAWN_MATERIAL_VALUE/100;
-
-    // Half a pawn penalty for doubled pawns and quarter pawn penalty for isolated pawns
-    for (file, neighbours) in NEIGHBOUR_FILES {
-        if (file & pawns).count_ones() > 1 {
-            pawn_evaluation -= PAWN_MATERIAL_VALUE/2
-        }
-        if (neighbours & pawns) == EMPTY_BITBOARD {
-            pawn_evaluation -= PAWN_MATERIAL_VALUE/4;
-        }
-        // Passed pawn bonus
-        if ((board.role.pawn ^ pawns) & file).count_ones() == 0 {
-            pawn_evaluation += PAWN_MATERIAL_VALUE/8;
+    for role in ALL_ROLES {
+        let role_bitboard = *board.role.get(role) & pieces;
+        for square in role_bitboard.get_component_bitboards() {
+            let index = psqt_index(&square, colour);
+            score += Score::new(params.psqt_mg.get(role)[index], params.psqt_eg.get(role)[index]);
         }
     }
-
-    return pawn_evaluation;
+    
+    return score;
 }
 
-pub fn castling_evaluation(board: &Board, colour: &Colour) -> i32 {
-    let mut pawn_evaluation: i32 = 0;
-    let pawns: Bitboard;
+// Index into a piece-square table for a square. The tables start at A8, so white reads them from the top and black from the bottom
+pub fn psqt_index(square: &Bitboard, colour: &Colour) -> usize {
+    let index = 63 - square.trailing_zeros() as usize;
     match colour {
-        Colour::White => {
-            pawns = board.colour.white & board.role.pawn;
-            if board.castle_side.white == Some(CastleSide::KingSide) {
-                if (pawns & WHITE_KINGSIDE_PAWN_STRUCTURE).count_ones() > 2 {
-                    pawn_evaluation += PAWN_MATERIAL_VALUE/4;
-                }
-                pawn_evaluation += PAWN_MATERIAL_VALUE/4;
-            } else if board.castle_side.white == Some(CastleSide::QueenSide) {
-                if (pawns & WHITE_QUEENSIDE_PAWN_STRUCTURE).count_ones() > 2 {
-                    pawn_evaluation += PAWN_MATERIAL_VALUE/4;
-                }
-                pawn_evaluation += PAWN_MATERIAL_VALUE/4;
-            } else {
-                if board.castling_rights.white.kingside == true {
-                    if (pawns & WHITE_KINGSIDE_PAWN_STRUCTURE).count_ones() > 2 {
-                        pawn_evaluation += PAWN_MATERIAL_VALUE/8;
-                    }
-                    pawn_evaluation += PAWN_MATERIAL_VALUE/8;
-                }
-                if board.castling_rights.white.queenside == true {
-                    if (pawns & WHITE_QUEENSIDE_PAWN_STRUCTURE).count_ones() > 2 {
-                        pawn_evaluation += PAWN_MATERIAL_VALUE/8;
-                    }
-                    pawn_evaluation += PAWN_MATERIAL_VALUE/8;
-                }
-            }
-        }
-        Colour::Black => {
-            pawns = board.colour.black & board.role.pawn;
-            if board.castle_side.black == Some(CastleSide::KingSide) {
-                if (pawns & BLACK_KINGSIDE_PAWN_STRUCTURE).count_ones() > 2 {
-                    pawn_evaluation += PAWN_MATERIAL_VALUE/4;
-                }
-                pawn_evaluation += PAWN_MATERIAL_VALUE/4;
-            } else if board.castle_side.black == Some(CastleSide::QueenSide) {
-                if (pawns & BLACK_QUEENSIDE_PAWN_STRUCTURE).count_ones() > 2 {
-                    pawn_evaluation += PAWN_MATERIAL_VALUE/4;
-                }
-                pawn_evaluation += PAWN_MATERIAL_VALUE/4;
-            } else {
-                if board.castling_rights.black.kingside == true {
-                    if (pawns & BLACK_KINGSIDE_PAWN_STRUCTURE).count_ones() > 2 {
-                        pawn_evaluation += PAWN_MATERIAL_VALUE/8;
-                    }
-                    pawn_evaluation += PAWN_MATERIAL_VALUE/8;
-                }
-                if board.castling_rights.black.queenside == true {
-                    if (pawns & BLACK_QUEENSIDE_PAWN_STRUCTURE).count_ones() > 2 {
-                        pawn_evaluation += PAWN_MATERIAL_VALUE/8;
-                    }
-                    pawn_evaluation += PAWN_MATERIAL_VALUE/8;
-                }
-            }
+        Colour::White => return index,
+        Colour::Black => return index ^ 56,
+    }
+}
+
+// Bonus for having castled, or for keeping the right to castle. Whether the castled king is actually safe is up to the king safety terms
+pub fn castling_evaluation(board: &Board, params: &EvalParams, colour: Colour) -> Score {
+    if board.castle_side.get(colour).is_some() {
+        return params.castled;
+    }
+    
+    let mut score = Score::default();
+    let castling_rights = board.castling_rights.get(colour);
+    if castling_rights.kingside.is_some() {
+        score += params.castling_right;
+    }
+    if castling_rights.queenside.is_some() {
+        score += params.castling_right;
+    }
+    return score;
+}
+
+// Gets the mobility bonus for a colours knights, bishops, rooks and queens. Only safe squares count, which are ones not occupied by
+// the colours own pieces and not attacked by enemy pawns
+pub fn mobility_evaluation(board: &Board, params: &EvalParams, colour: &Colour) -> Score {
+    let mut score = Score::default();
+    
+    let (pieces, enemy_pawn_attacks) = match colour {
+        Colour::White => (board.colour.white, get_black_pawn_attacks(board, &(board.colour.black & board.role.pawn))),
+        Colour::Black => (board.colour.black, get_white_pawn_attacks(board, &(board.colour.white & board.role.pawn))),
+    };
+    let safe_squares = !(pieces | enemy_pawn_attacks);
+    
+    for role in MOBILITY_ROLES {
+        let mg_bonus = params.mobility_mg.get(role);
+        let eg_bonus = params.mobility_eg.get(role);
+        
+        for piece in (*board.role.get(role) & pieces).get_component_bitboards() {
+            let attacks = match role {
+                Role::Knight => get_knight_attacks(board, &piece),
+                Role::Bishop => get_bishop_attacks(board, &piece),
+                Role::Rook => get_rook_attacks(board, &piece),
+                _ => get_queen_attacks(board, &piece),
+            };
+            let safe_square_count = (attacks & safe_squares).count_ones() as usize;
+            score += Score::new(mg_bonus[safe_square_count.min(mg_bonus.len() - 1)], eg_bonus[safe_square_count.min(eg_bonus.len() - 1)]);
         }
     }
-    return pawn_evaluation
+    
+    return score;
 }
 
+// Old mobility measure, kept for USE_LEGACY_MOBILITY. Ratio of one colours attacks to the others, which divides by zero if the other
+// colour has no attacks
 pub fn calculate_attack_mobility(board: &Board, colour: &Colour) -> f32 {
     let mut white_attack_count: f32 = 0.0;
     
@@ -195,4 +337,22 @@ pub fn calculate_attack_mobility(board: &Board, colour: &Colour) -> f32 {
         Colour::White => return white_attack_count/black_attack_count,
         Colour::Black => return black_attack_count/white_attack_count
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mobility_counts_safe_squares() {
+        // The knight on a1 can go to b3 and c2, but the pawn on d3 covers c2. The rook on h1 has the h file and g1 and f1, with its own
+        // king on e1 not counting. Black has nothing that gets a mobility bonus
+        let board = Board::from_fen("4k3/8/8/8/8/3p4/8/N3K2R w - -".to_string());
+        let params = EvalParams::default();
+
+        let knight = Score::new(params.mobility_mg.knight[1], params.mobility_eg.knight[1]);
+        let rook = Score::new(params.mobility_mg.rook[9], params.mobility_eg.rook[9]);
+        assert_eq!(mobility_evaluation(&board, &params, &Colour::White), knight + rook);
+        assert_eq!(mobility_evaluation(&board, &params, &Colour::Black), Score::default());
+    }
+}
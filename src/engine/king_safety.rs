@@ -0,0 +1,179 @@
+use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
+use crate::board::Board;
+use crate::colour::Colour;
+use crate::engine::eval::Score;
+use crate::engine::params::EvalParams;
+use crate::engine::pawns::relative_rank;
+use crate::magic::{bishop_attacks, rook_attacks};
+use crate::movegen::{get_bishop_attacks, get_black_attacks, get_king_attacks, get_knight_attacks, get_queen_attacks, get_rook_attacks, get_white_attacks};
+use crate::role::Role;
+use crate::square::FILE_H;
+
+// Pieces that count as attacking the king zone and can give safe checks
+const KING_ATTACKER_ROLES: [Role; 4] = [Role::Knight, Role::Bishop, Role::Rook, Role::Queen];
+
+// The most attack units counted, so the quadratic danger term can't grow without limit
+const MAX_ATTACK_UNITS: i32 = 50;
+
+// The king safety evaluation for a colour, split up so the terms can be looked at on their own. All of them are from the point of view
+// of the colour whose king it is, so attacks on it are negative
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KingSafety {
+    pub king_attacks: Score,
+    pub pawn_shield: Score,
+    pub pawn_storm: Score,
+    pub open_files: Score,
+    pub safe_checks: Score,
+}
+
+impl KingSafety {
+    pub fn total(&self) -> Score {
+        return self.king_attacks + self.pawn_shield + self.pawn_storm + self.open_files + self.safe_checks;
+    }
+}
+
+// Squares around the king plus the ones a rank further towards the enemy, which is where attacks on a castled king come from
+pub fn king_zone(board: &Board, king: &Bitboard, colour: Colour) -> Bitboard {
+    let zone = get_king_attacks(board, king) | *king;
+    match colour {
+        Colour::White => return zone | (zone << 8),
+        Colour::Black => return zone | (zone >> 8),
+    }
+}
+
+// Evaluates how safe a colours king is
+pub fn king_safety_evaluation(board: &Board, params: &EvalParams, colour: Colour) -> KingSafety {
+    let mut safety = KingSafety::default();
+
+    let king = *board.colour.get(colour) & board.role.king;
+    if king == EMPTY_BITBOARD {
+        return safety;
+    }
+
+    let own_pieces = *board.colour.get(colour);
+    let enemy_pieces = *board.colour.get(colour.opposite());
+    let own_pawns = own_pieces & board.role.pawn;
+    let enemy_pawns = enemy_pieces & board.role.pawn;
+
+    // Attacks on the king zone. Each attacker adds units for every zone square it hits, weighted by how dangerous the piece is, and
+    // the penalty grows with the square of the units once at least two pieces join in
+    let zone = king_zone(board, &king, colour);
+    let mut attacker_count = 0;
+    let mut attack_units = 0;
+    for role in KING_ATTACKER_ROLES {
+        for piece in (*board.role.get(role) & enemy_pieces).get_component_bitboards() {
+            let attacks = attacks_for_role(board, role, &piece);
+            let zone_attacks = (attacks & zone).count_ones() as i32;
+            if zone_attacks > 0 {
+                attacker_count += 1;
+                attack_units += zone_attacks * params.king_attack_weight.get(role);
+            }
+        }
+    }
+    if attacker_count >= 2 {
+        let attack_units = attack_units.min(MAX_ATTACK_UNITS);
+        safety.king_attacks = Score::new(-attack_units * attack_units * params.king_danger.mg, -attack_units * attack_units * params.king_danger.eg);
+    }
+
+    // Pawn shield, pawn storm and open files, looking at the kings file and the ones either side of it. Kings on the edge use the
+    // three files nearest the edge
+    let king_rank = relative_rank(colour, &king);
+    let centre_file = (king.trailing_zeros() % 8).clamp(1, 6);
+    for file_index in (centre_file - 1)..=(centre_file + 1) {
+        let file = Bitboard(FILE_H.0 << file_index);
+        let own_file_pawns = own_pawns & file;
+        let enemy_file_pawns = enemy_pawns & file;
+
+        let shield_distance = closest_pawn_distance_in_front(&own_file_pawns, colour, king_rank);
+        safety.pawn_shield.mg += params.pawn_shield_mg[shield_distance];
+
+        let storm_distance = closest_pawn_distance_in_front(&enemy_file_pawns, colour, king_rank);
+        safety.pawn_storm.mg += params.pawn_storm_mg[storm_distance];
+
+        if own_file_pawns == EMPTY_BITBOARD {
+            if enemy_file_pawns == EMPTY_BITBOARD {
+                safety.open_files += params.king_open_file;
+            } else {
+                safety.open_files += params.king_semi_open_file;
+            }
+        }
+    }
+
+    // Safe checks are squares an enemy piece can check from next move that are empty or hold one of our pieces and that none of our
+    // pieces defend
+    let defended = match colour {
+        Colour::White => get_white_attacks(board),
+        Colour::Black => get_black_attacks(board),
+    };
+    let knight_checks = get_knight_attacks(board, &king);
+    let bishop_checks = bishop_attacks(&king, &board.occupied);
+    let rook_checks = rook_attacks(&king, &board.occupied);
+    for role in KING_ATTACKER_ROLES {
+        let checking_squares = match role {
+            Role::Knight => knight_checks,
+            Role::Bishop => bishop_checks,
+            Role::Rook => rook_checks,
+            _ => bishop_checks | rook_checks,
+        };
+        let enemy_attacks = attacks_for_role(board, role, &(*board.role.get(role) & enemy_pieces));
+        if (checking_squares & enemy_attacks & !enemy_pieces & !defended) != EMPTY_BITBOARD {
+            safety.safe_checks -= Score::new(*params.safe_check_mg.get(role), *params.safe_check_eg.get(role));
+        }
+    }
+
+    return safety;
+}
+
+// How many ranks in front of the king the nearest pawn on a file is, or 0 when there isn't one in front of it
+fn closest_pawn_distance_in_front(file_pawns: &Bitboard, colour: Colour, king_rank: usize) -> usize {
+    let mut closest_distance = 0;
+    for pawn in file_pawns.get_component_bitboards() {
+        let rank = relative_rank(colour, &pawn);
+        if (rank > king_rank) && ((closest_distance == 0) || (rank - king_rank < closest_distance)) {
+            closest_distance = rank - king_rank;
+        }
+    }
+    return closest_distance;
+}
+
+fn attacks_for_role(board: &Board, role: Role, pieces: &Bitboard) -> Bitboard {
+    match role {
+        Role::Knight => return get_knight_attacks(board, pieces),
+        Role::Bishop => return get_bishop_attacks(board, pieces),
+        Role::Rook => return get_rook_attacks(board, pieces),
+        Role::Queen => return get_queen_attacks(board, pieces),
+        _ => return get_king_attacks(board, pieces),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci::get_uci_square_bitboard;
+
+    fn squares(names: &str) -> Bitboard {
+        return names.split_whitespace().fold(EMPTY_BITBOARD, |squares, name| squares | get_uci_square_bitboard(name));
+    }
+
+    #[test]
+    fn zones_reach_a_rank_towards_the_enemy() {
+        let board = Board::empty_board();
+        assert_eq!(king_zone(&board, &squares("g1"), Colour::White), squares("f1 g1 h1 f2 g2 h2 f3 g3 h3"));
+        assert_eq!(king_zone(&board, &squares("g8"), Colour::Black), squares("f8 g8 h8 f7 g7 h7 f6 g6 h6"));
+        assert_eq!(king_zone(&board, &squares("a1"), Colour::White), squares("a1 b1 a2 b2 a3 b3"));
+    }
+
+    #[test]
+    fn zone_attacks_are_counted_per_square() {
+        // The knight hits f2 and h2 and the queen hits f2, g3, h3 and h2, with the pawns stopping both of its lines
+        let params = EvalParams::default();
+        let board = Board::from_fen("6k1/8/8/8/6nq/8/5PPP/6K1 w - -".to_string());
+        let attack_units = 2 * params.king_attack_weight.knight + 4 * params.king_attack_weight.queen;
+        let danger = Score::new(-attack_units * attack_units * params.king_danger.mg, -attack_units * attack_units * params.king_danger.eg);
+        assert_eq!(king_safety_evaluation(&board, &params, Colour::White).king_attacks, danger);
+
+        // A lone attacker isn't counted at all
+        let board = Board::from_fen("6k1/8/8/8/7q/8/5PPP/6K1 w - -".to_string());
+        assert_eq!(king_safety_evaluation(&board, &params, Colour::White).king_attacks, Score::default());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::board::Board;
+use crate::engine::search::{SearchControl, NODE_COUNT};
+use crate::movegen::get_legal_moves;
+use crate::mv::Move;
+use std::sync::atomic::Ordering;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MateSearchResult {
+    // The shortest forced mate, as a line of moves ending in checkmate. The mate is in (length + 1) / 2 moves
+    Mate(Vec<Move>),
+    // There is no forced mate within the number of moves searched
+    NoMate,
+    // The search was stopped or ran out of time before it could prove or refute a mate
+    Aborted,
+}
+
+impl MateSearchResult {
+    pub fn mate_in(&self) -> Option<i32> {
+        match self {
+            MateSearchResult::Mate(pv) => Some((pv.len() as i32 + 1) / 2),
+            _ => None,
+        }
+    }
+}
+
+// Proves or refutes a mate in max_moves for the side to move. Searches mate in 1, then mate in 2 and so on, so a mate found is always
+// the shortest one. Every attacking move is tried, checks first, except on the final move where only checks can mate
+pub fn mate_search(board: &Board, max_moves: i32, control: &SearchControl) -> MateSearchResult {
+    
+    for moves in 1..=max_moves {
+        let mut pv: Vec<Move> = Vec::new();
+        let mated = attacker_mates(board, moves, control, &mut pv);
+        
+        // Running out of time can only stop a mate being proven, never prove a false one, so a mate found right at the deadline stands
+        if mated {
+            return MateSearchResult::Mate(pv);
+        }
+        
+        if control.time_up() {
+            return MateSearchResult::Aborted;
+        }
+    }
+    
+    return MateSearchResult::NoMate;
+}
+
+// Determines if the side to move can force mate within moves_left moves. Fills pv with the mating line if it can
+fn attacker_mates(board: &Board, moves_left: i32, control: &SearchControl, pv: &mut Vec<Move>) -> bool {
+    
+    NODE_COUNT.fetch_add(1, Ordering::Relaxed);
+    
+    if control.time_up() {
+        return false;
+    }
+    
+    let mut checks: Vec<(Move, Board)> = Vec::new();
+    let mut quiet_moves: Vec<(Move, Board)> = Vec::new();
+    for mv in get_legal_moves(board) {
+        let mut new_board = board.clone();
+        new_board.play_unsafe(mv);
+        
+        if new_board.is_check(&new_board.turn) {
+            checks.push((mv, new_board));
+        } else if moves_left > 1 {
+            quiet_moves.push((mv, new_board));
+        }
+    }
+    
+    let mut child_pv: Vec<Move> = Vec::new();
+    for (mv, new_board) in checks.iter().chain(quiet_moves.iter()) {
+        child_pv.clear();
+        if defender_loses(new_board, moves_left, control, &mut child_pv) {
+            pv.clear();
+            pv.push(*mv);
+            pv.extend(child_pv.iter());
+            return true;
+        }
+    }
+    
+    return false;
+}
+
+// Determines if every move for the side to move loses to a mate within moves_left moves of the attacker, counting the move that was
+// just played. Fills pv with one of the losing lines if so
+fn defender_loses(board: &Board, moves_left: i32, control: &SearchControl, pv: &mut Vec<Move>) -> bool {
+    
+    NODE_COUNT.fetch_add(1, Ordering::Relaxed);
+    
+    // pv is shared between the attacker's moves, so a line left from a refuted one mustn't carry over
+    pv.clear();
+    
+    let legal_moves = get_legal_moves(board);
+    
+    // Checkmate, or stalemate which is not a mate
+    if legal_moves.is_empty() {
+        return board.is_check(&board.turn);
+    }
+    
+    if moves_left <= 1 {
+        return false;
+    }
+    
+    let mut child_pv: Vec<Move> = Vec::new();
+    for mv in legal_moves {
+        let mut new_board = board.clone();
+        new_board.play_unsafe(mv);
+        
+        if !attacker_mates(&new_board, moves_left - 1, control, &mut child_pv) {
+            return false;
+        }
+        
+        if pv.is_empty() {
+            pv.push(mv);
+            pv.extend(child_pv.iter());
+        }
+    }
+    
+    return true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    // Replays a mating line from the position and checks every move is legal and the last one mates
+    fn assert_mating_line(fen: &str, pv: &[Move]) {
+        let mut board = Board::from_fen(fen.to_string());
+        for mv in pv {
+            assert!(get_legal_moves(&board).contains(mv), "{} plays an illegal move", fen);
+            board.play_unsafe(*mv);
+        }
+        assert!(get_legal_moves(&board).is_empty() && board.is_check(&board.turn), "{} does not end in checkmate", fen);
+    }
+    
+    #[test]
+    fn mating_lines_end_in_checkmate() {
+        // Positions where a line from a refuted attacking move used to be returned as the mate
+        let positions = [
+            "5k2/8/5pp1/3Q3p/7P/8/5PP1/4R1K1 w - - 0 7",
+            "7k/7p/3R4/5p2/6p1/8/5PPP/2Q3K1 w - - 0 1",
+            "4k3/5ppp/8/8/8/6Q1/5PPP/3R2K1 w - - 0 1",
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+        ];
+        for fen in positions {
+            let board = Board::from_fen(fen.to_string());
+            let control = SearchControl::new(Duration::MAX, false);
+            match mate_search(&board, 3, &control) {
+                MateSearchResult::Mate(pv) => assert_mating_line(fen, &pv),
+                result => panic!("{} found {:?} instead of a mate", fen, result),
+            }
+        }
+    }
+    
+    #[test]
+    fn finds_the_shortest_mate() {
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string());
+        let control = SearchControl::new(Duration::MAX, false);
+        assert_eq!(mate_search(&board, 3, &control).mate_in(), Some(1));
+    }
+    
+    #[test]
+    fn no_mate_in_a_drawn_position() {
+        let board = Board::from_fen("8/8/8/4k3/8/8/8/4K3 w - - 0 1".to_string());
+        let control = SearchControl::new(Duration::MAX, false);
+        assert_eq!(mate_search(&board, 2, &control), MateSearchResult::NoMate);
+    }
+}
@@ -1,2 +1,13 @@
 pub mod search;
-pub mod eval;
\ No newline at end of file
+pub mod eval;
+pub mod mate;
+pub mod params;
+pub mod pawns;
+pub mod king_safety;
+pub mod endgame;
+pub mod syzygy;
+pub mod book;
+pub mod trace;
+pub mod tune;
+#[cfg(feature = "nnue")]
+pub mod nnue;
@@ -0,0 +1,423 @@
+// NNUE evaluation, built with the "nnue" cargo feature.
+//
+// The network is (768 -> N) x 2 -> 1. Every piece on the board is one of 768 input features (colour, role and square), seen from both
+// sides. Each side has an accumulator of N int16 values, which is the feature biases plus the feature weights of every piece on the
+// board. Pieces are seen relative to the side whose accumulator it is: "own" pieces are the first 384 features and the board is flipped
+// vertically for black, so one set of weights serves both sides. The accumulators live on the Board and are updated incrementally by
+// set_square and clear_square, so making a move only adds and subtracts the weights of the pieces that changed. The search copies the
+// board to make a move, so unmaking one is just dropping the copy.
+//
+// The output is the side to move's accumulator followed by the other side's, each clipped to 0..=QA, dotted with 2N int8 output weights,
+// plus the int32 output bias. That is scaled by scale / (QA * QB) to give centipawns for the side to move.
+//
+// Network file format, all little endian:
+//
+//   offset  size        contents
+//   0       4           magic "CLNN"
+//   4       4           u32 format version, currently 1
+//   8       4           u32 hidden size N, a multiple of 16 no larger than MAX_HIDDEN_SIZE
+//   12      4           i32 output scale
+//   16      768*N*2     i16 feature weights, all N weights of feature 0 first, then feature 1 and so on
+//           N*2         i16 feature biases
+//           2N          i8 output weights, N for the side to move's accumulator then N for the other side's
+//           4           i32 output bias
+//
+// Feature index = own_or_enemy * 384 + role * 64 + square, where own is 0, roles are in Role order starting with pawns, and squares
+// count from A1 = 0 to H8 = 63 from white's side, flipped vertically (square ^ 56) for black's accumulator.
+
+use crate::bitboard::Bitboard;
+use crate::board::Board;
+use crate::colour::{ByColour, Colour};
+use crate::role::{Role, ALL_ROLES};
+
+use std::fs;
+use std::sync::{Arc, RwLock};
+
+pub const MAX_HIDDEN_SIZE: usize = 512;
+pub const FEATURE_COUNT: usize = 768;
+// Quantisation of the accumulator and output weights
+pub const QA: i32 = 255;
+pub const QB: i32 = 64;
+
+const MAGIC: &[u8; 4] = b"CLNN";
+const VERSION: u32 = 1;
+
+// The network evaluate uses, or None for the hand written evaluation. Boards hold their own reference to the network their accumulators
+// were built for, so a network is freed once it has been replaced and the last board using it is dropped
+static NETWORK: RwLock<Option<Arc<Network>>> = RwLock::new(None);
+
+pub struct Network {
+    pub hidden_size: usize,
+    pub scale: i32,
+    pub feature_weights: Vec<i16>,
+    pub feature_biases: Vec<i16>,
+    pub output_weights: Vec<i8>,
+    pub output_bias: i32,
+}
+
+// Both sides accumulators, along with the network they were built for so boards made before a network was loaded can be spotted
+#[derive(Clone)]
+pub struct Accumulator {
+    pub values: ByColour<[i16; MAX_HIDDEN_SIZE]>,
+    network: Option<Arc<Network>>,
+}
+
+impl std::fmt::Debug for Accumulator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Accumulator {{ network: {:?} }}", self.network.as_ref().map(Arc::as_ptr))
+    }
+}
+
+impl Default for Accumulator {
+    fn default() -> Accumulator {
+        return Accumulator::new();
+    }
+}
+
+impl Network {
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, String> {
+        let mut reader = Reader { bytes, position: 0 };
+
+        if reader.take(4)? != MAGIC {
+            return Err("Not a network file, the magic bytes are wrong".to_string());
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("Unsupported network format version {}", version));
+        }
+        let hidden_size = reader.u32()? as usize;
+        if (hidden_size == 0) | (hidden_size > MAX_HIDDEN_SIZE) | !hidden_size.is_multiple_of(16) {
+            return Err(format!("Hidden size {} must be a multiple of 16 up to {}", hidden_size, MAX_HIDDEN_SIZE));
+        }
+        let scale = reader.i32()?;
+
+        let mut feature_weights = Vec::with_capacity(FEATURE_COUNT * hidden_size);
+        for _ in 0..FEATURE_COUNT * hidden_size {
+            feature_weights.push(reader.i16()?);
+        }
+        let mut feature_biases = Vec::with_capacity(hidden_size);
+        for _ in 0..hidden_size {
+            feature_biases.push(reader.i16()?);
+        }
+        let output_weights: Vec<i8> = reader.take(2 * hidden_size)?.iter().map(|byte| *byte as i8).collect();
+        let output_bias = reader.i32()?;
+
+        if reader.position != bytes.len() {
+            return Err(format!("Network file has {} bytes left over", bytes.len() - reader.position));
+        }
+
+        return Ok(Network { hidden_size, scale, feature_weights, feature_biases, output_weights, output_bias });
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.hidden_size as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.scale.to_le_bytes());
+        for weight in self.feature_weights.iter().chain(self.feature_biases.iter()) {
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+        bytes.extend(self.output_weights.iter().map(|weight| *weight as u8));
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        return bytes;
+    }
+
+    pub fn from_file(path: &str) -> Result<Network, String> {
+        let bytes = fs::read(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        return Network::from_bytes(&bytes);
+    }
+
+    pub fn to_file(&self, path: &str) -> Result<(), String> {
+        return fs::write(path, self.to_bytes()).map_err(|error| format!("Could not write {}: {}", path, error));
+    }
+
+    fn feature_weights(&self, feature: usize) -> &[i16] {
+        return &self.feature_weights[feature * self.hidden_size..(feature + 1) * self.hidden_size];
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if self.position + length > self.bytes.len() {
+            return Err("Network file is too short".to_string());
+        }
+        let taken = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        return Ok(taken);
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        return Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        return Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()));
+    }
+}
+
+// Loads a network for evaluate to use, replacing the one before it
+pub fn load_network(path: &str) -> Result<(), String> {
+    let network = Network::from_file(path)?;
+    *NETWORK.write().unwrap() = Some(Arc::new(network));
+    return Ok(());
+}
+
+// Goes back to the hand written evaluation
+pub fn unload_network() {
+    *NETWORK.write().unwrap() = None;
+}
+
+pub fn current_network() -> Option<Arc<Network>> {
+    return NETWORK.read().unwrap().clone();
+}
+
+fn feature_index(perspective: Colour, role: Role, colour: Colour, square: &Bitboard) -> usize {
+    let square_index = (square.trailing_zeros() ^ 7) as usize;
+    let (side, square_index) = match perspective {
+        Colour::White => (if colour == Colour::White { 0 } else { 1 }, square_index),
+        Colour::Black => (if colour == Colour::Black { 0 } else { 1 }, square_index ^ 56),
+    };
+    return side * 384 + role as usize * 64 + square_index;
+}
+
+impl Accumulator {
+
+    // Accumulators for an empty board with the current network, or marked as not built when there is no network
+    pub fn new() -> Accumulator {
+        return Accumulator::for_network(current_network());
+    }
+
+    // Accumulators for an empty board with a given network
+    pub fn for_network(network: Option<Arc<Network>>) -> Accumulator {
+        let mut accumulator = Accumulator { values: ByColour { white: [0; MAX_HIDDEN_SIZE], black: [0; MAX_HIDDEN_SIZE] }, network: None };
+        if let Some(network) = network {
+            accumulator.values.white[..network.hidden_size].copy_from_slice(&network.feature_biases);
+            accumulator.values.black[..network.hidden_size].copy_from_slice(&network.feature_biases);
+            accumulator.network = Some(network);
+        }
+        return accumulator;
+    }
+
+    // Builds the accumulators from scratch for every piece on the board
+    pub fn from_board(board: &Board) -> Accumulator {
+        return Accumulator::from_board_for_network(board, current_network());
+    }
+
+    pub fn from_board_for_network(board: &Board, network: Option<Arc<Network>>) -> Accumulator {
+        let mut accumulator = Accumulator::for_network(network);
+        for colour in [Colour::White, Colour::Black] {
+            for role in ALL_ROLES {
+                for square in (*board.role.get(role) & *board.colour.get(colour)).get_component_bitboards() {
+                    accumulator.add_piece(role, colour, &square);
+                }
+            }
+        }
+        return accumulator;
+    }
+
+    pub fn is_built_for(&self, network: &Network) -> bool {
+        return self.network.as_ref().is_some_and(|own| std::ptr::eq(Arc::as_ptr(own), network));
+    }
+
+    pub fn add_piece(&mut self, role: Role, colour: Colour, square: &Bitboard) {
+        let Some(network) = &self.network else {
+            return;
+        };
+        for perspective in [Colour::White, Colour::Black] {
+            let weights = network.feature_weights(feature_index(perspective, role, colour, square));
+            for (value, weight) in self.values.get_mut(perspective).iter_mut().zip(weights) {
+                *value = value.wrapping_add(*weight);
+            }
+        }
+    }
+
+    pub fn remove_piece(&mut self, role: Role, colour: Colour, square: &Bitboard) {
+        let Some(network) = &self.network else {
+            return;
+        };
+        for perspective in [Colour::White, Colour::Black] {
+            let weights = network.feature_weights(feature_index(perspective, role, colour, square));
+            for (value, weight) in self.values.get_mut(perspective).iter_mut().zip(weights) {
+                *value = value.wrapping_sub(*weight);
+            }
+        }
+    }
+}
+
+impl Board {
+    // Copy of the board with its accumulators rebuilt for the current network
+    pub fn with_fresh_accumulator(&self) -> Board {
+        let mut board = self.clone();
+        board.accumulator = Accumulator::from_board(self);
+        return board;
+    }
+}
+
+// Evaluates the board with the network, in the engines units from the point of view of the side to move. Uses the boards own
+// accumulators when they were built for this network, and builds new ones otherwise
+pub fn nnue_evaluate(board: &Board, network: &Network) -> i32 {
+    let rebuilt;
+    let accumulator = if board.accumulator.is_built_for(network) {
+        &board.accumulator
+    } else {
+        rebuilt = Accumulator::from_board(board);
+        &rebuilt
+    };
+
+    let hidden_size = network.hidden_size;
+    let own = &accumulator.values.get(board.turn)[..hidden_size];
+    let enemy = &accumulator.values.get(board.turn.opposite())[..hidden_size];
+
+    let output = output_layer(own, &network.output_weights[..hidden_size]) + output_layer(enemy, &network.output_weights[hidden_size..]) + network.output_bias;
+    let centipawns = output as i64 * network.scale as i64 / (QA * QB) as i64;
+    return (centipawns * 100).clamp(-(i32::MAX as i64 - 1), i32::MAX as i64 - 1) as i32;
+}
+
+// Clipped ReLU of the accumulator dotted with the output weights
+fn output_layer(accumulator: &[i16], weights: &[i8]) -> i32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { output_layer_avx2(accumulator, weights) };
+        }
+    }
+    return output_layer_scalar(accumulator, weights);
+}
+
+pub fn output_layer_scalar(accumulator: &[i16], weights: &[i8]) -> i32 {
+    let mut sum: i32 = 0;
+    for (value, weight) in accumulator.iter().zip(weights) {
+        sum += (*value as i32).clamp(0, QA) * *weight as i32;
+    }
+    return sum;
+}
+
+// Sixteen values at a time. The clipped values fit in an i16 and the products of pairs are summed into i32 lanes by madd
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn output_layer_avx2(accumulator: &[i16], weights: &[i8]) -> i32 {
+    use std::arch::x86_64::*;
+
+    let zero = _mm256_setzero_si256();
+    let max = _mm256_set1_epi16(QA as i16);
+    let mut sum = _mm256_setzero_si256();
+
+    for (values, weights) in accumulator.chunks_exact(16).zip(weights.chunks_exact(16)) {
+        let values = unsafe { _mm256_loadu_si256(values.as_ptr() as *const __m256i) };
+        let weights = _mm256_cvtepi8_epi16(unsafe { _mm_loadu_si128(weights.as_ptr() as *const __m128i) });
+        let clipped = _mm256_min_epi16(_mm256_max_epi16(values, zero), max);
+        sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, weights));
+    }
+
+    let high = _mm256_extracti128_si256(sum, 1);
+    let sum = _mm_add_epi32(_mm256_castsi256_si128(sum), high);
+    let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b01_00_11_10));
+    let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b10_11_00_01));
+    return _mm_cvtsi128_si32(sum);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::get_legal_moves;
+    
+    // Deterministic xorshift so the tests don't need a network file or a random number crate
+    struct TestRng(u64);
+    
+    impl TestRng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            return self.0;
+        }
+        
+        fn range(&mut self, low: i32, high: i32) -> i32 {
+            return low + (self.next() % (high - low + 1) as u64) as i32;
+        }
+    }
+    
+    fn random_network(hidden_size: usize, rng: &mut TestRng) -> Network {
+        return Network {
+            hidden_size,
+            scale: 400,
+            feature_weights: (0..FEATURE_COUNT * hidden_size).map(|_| rng.range(-64, 64) as i16).collect(),
+            feature_biases: (0..hidden_size).map(|_| rng.range(-64, 64) as i16).collect(),
+            output_weights: (0..2 * hidden_size).map(|_| rng.range(-127, 127) as i8).collect(),
+            output_bias: rng.range(-1000, 1000),
+        };
+    }
+    
+    fn same_values(accumulator: &Accumulator, other: &Accumulator) -> bool {
+        return (accumulator.values.white == other.values.white) & (accumulator.values.black == other.values.black);
+    }
+    
+    // Plays every move to the given depth, checking the incrementally updated accumulators against ones rebuilt from scratch. The parent
+    // board is checked again after each move, since unmaking a move is dropping the copy it was played on
+    fn check_accumulators(board: &Board, network: &Arc<Network>, depth: i32) {
+        for mv in get_legal_moves(board) {
+            let mut new_board = board.clone();
+            new_board.play_unsafe(mv);
+            let rebuilt = Accumulator::from_board_for_network(&new_board, Some(network.clone()));
+            assert!(same_values(&new_board.accumulator, &rebuilt), "Accumulators differ after {:?} from {}", mv, board.to_fen());
+            if depth > 1 {
+                check_accumulators(&new_board, network, depth - 1);
+            }
+        }
+        let rebuilt = Accumulator::from_board_for_network(board, Some(network.clone()));
+        assert!(same_values(&board.accumulator, &rebuilt), "Accumulators changed after unmaking a move from {}", board.to_fen());
+    }
+    
+    #[test]
+    fn incremental_accumulators_match_a_rebuild() {
+        let network = Arc::new(random_network(32, &mut TestRng(0x9e3779b97f4a7c15)));
+        // Castling on both sides, en passant, promotions with and without a capture, and a Chess960 position
+        let positions = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+        for fen in positions {
+            let mut board = Board::from_fen(fen.to_string());
+            board.accumulator = Accumulator::from_board_for_network(&board, Some(network.clone()));
+            check_accumulators(&board, &network, 2);
+        }
+    }
+    
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx2_output_layer_matches_scalar() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let mut rng = TestRng(0x2545f4914f6cdd1d);
+        for hidden_size in (16..=MAX_HIDDEN_SIZE).step_by(16) {
+            // Values well outside 0..=QA so the clipping is tested too
+            let accumulator: Vec<i16> = (0..hidden_size).map(|_| rng.range(-2000, 2000) as i16).collect();
+            let weights: Vec<i8> = (0..hidden_size).map(|_| rng.range(-128, 127) as i8).collect();
+            let scalar = output_layer_scalar(&accumulator, &weights);
+            let avx2 = unsafe { output_layer_avx2(&accumulator, &weights) };
+            assert_eq!(scalar, avx2, "Output layers differ with hidden size {}", hidden_size);
+        }
+    }
+    
+    #[test]
+    fn network_round_trips_through_bytes() {
+        let network = random_network(16, &mut TestRng(0xdeadbeefcafef00d));
+        let bytes = network.to_bytes();
+        assert_eq!(Network::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+    }
+}
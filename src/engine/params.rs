@@ -0,0 +1,518 @@
+use crate::role::{ByRole, Role, ALL_ROLES};
+use crate::engine::eval::Score;
+use crate::engine::pawns::invalidate_pawn_hash;
+use once_cell::sync::Lazy;
+use std::fs;
+use std::sync::RwLock;
+
+// The parameters evaluate uses. Starts as the defaults below and is replaced by load_eval_params
+pub static EVAL_PARAMS: Lazy<RwLock<EvalParams>> = Lazy::new(|| RwLock::new(EvalParams::default()));
+
+// Middlegame piece values are the original hand picked ones, the endgame values scale those the way PeSTO's do
+const MATERIAL_MG: ByRole<i32> = ByRole { pawn: 10000, knight: 30500, bishop: 33300, rook: 56300, queen: 95000, king: 0 };
+const MATERIAL_EG: ByRole<i32> = ByRole { pawn: 11500, knight: 25400, bishop: 27100, rook: 60400, queen: 86700, king: 0 };
+
+// Credit to PeSTO for these tables, scaled to a pawn being 10000. They are from whites point of view with A8 first, so they read like
+// the board does from whites side. Black uses the same tables flipped vertically
+const MG_PAWN_TABLE: [i32; 64] = [
+        0,     0,     0,     0,     0,     0,     0,     0,
+     9800, 13400,  6100,  9500,  6800, 12600,  3400, -1100,
+     -600,   700,  2600,  3100,  6500,  5600,  2500, -2000,
+    -1400,  1300,   600,  2100,  2300,  1200,  1700, -2300,
+    -2700,  -200,  -500,  1200,  1700,   600,  1000, -2500,
+    -2600,  -400,  -400, -1000,   300,   300,  3300, -1200,
+    -3500,  -100, -2000, -2300, -1500,  2400,  3800, -2200,
+        0,     0,     0,     0,     0,     0,     0,     0,
+];
+
+const MG_KNIGHT_TABLE: [i32; 64] = [
+    -16700,  -8900,  -3400,  -4900,   6100,  -9700,  -1500, -10700,
+     -7300,  -4100,   7200,   3600,   2300,   6200,    700,  -1700,
+     -4700,   6000,   3700,   6500,   8400,  12900,   7300,   4400,
+      -900,   1700,   1900,   5300,   3700,   6900,   1800,   2200,
+     -1300,    400,   1600,   1300,   2800,   1900,   2100,   -800,
+     -2300,   -900,   1200,   1000,   1900,   1700,   2500,  -1600,
+     -2900,  -5300,  -1200,   -300,   -100,   1800,  -1400,  -1900,
+    -10500,  -2100,  -5800,  -3300,  -1700,  -2800,  -1900,  -2300,
+];
+
+const MG_BISHOP_TABLE: [i32; 64] = [
+    -2900,   400, -8200, -3700, -2500, -4200,   700,  -800,
+    -2600,  1600, -1800, -1300,  3000,  5900,  1800, -4700,
+    -1600,  3700,  4300,  4000,  3500,  5000,  3700,  -200,
+     -400,   500,  1900,  5000,  3700,  3700,   700,  -200,
+     -600,  1300,  1300,  2600,  3400,  1200,  1000,   400,
+        0,  1500,  1500,  1500,  1400,  2700,  1800,  1000,
+      400,  1500,  1600,     0,   700,  2100,  3300,   100,
+    -3300,  -300, -1400, -2100, -1300, -1200, -3900, -2100,
+];
+
+const MG_ROOK_TABLE: [i32; 64] = [
+     3200,  4200,  3200,  5100,  6300,   900,  3100,  4300,
+     2700,  3200,  5800,  6200,  8000,  6700,  2600,  4400,
+     -500,  1900,  2600,  3600,  1700,  4500,  6100,  1600,
+    -2400, -1100,   700,  2600,  2400,  3500,  -800, -2000,
+    -3600, -2600, -1200,  -100,   900,  -700,   600, -2300,
+    -4500, -2500, -1600, -1700,   300,     0,  -500, -3300,
+    -4400, -1600, -2000,  -900,  -100,  1100,  -600, -7100,
+    -1900, -1300,   100,  1700,  1600,   700, -3700, -2600,
+];
+
+const MG_QUEEN_TABLE: [i32; 64] = [
+    -2800,     0,  2900,  1200,  5900,  4400,  4300,  4500,
+    -2400, -3900,  -500,   100, -1600,  5700,  2800,  5400,
+    -1300, -1700,   700,   800,  2900,  5600,  4700,  5700,
+    -2700, -2700, -1600, -1600,  -100,  1700,  -200,   100,
+     -900, -2600,  -900, -1000,  -200,  -400,   300,  -300,
+    -1400,   200, -1100,  -200,  -500,   200,  1400,   500,
+    -3500,  -800,  1100,   200,   800,  1500,  -300,   100,
+     -100, -1800,  -900,  1000, -1500, -2500, -3100, -5000,
+];
+
+const MG_KING_TABLE: [i32; 64] = [
+    -6500,  2300,  1600, -1500, -5600, -3400,   200,  1300,
+     2900,  -100, -2000,  -700,  -800,  -400, -3800, -2900,
+     -900,  2400,   200, -1600, -2000,   600,  2200, -2200,
+    -1700, -2000, -1200, -2700, -3000, -2500, -1400, -3600,
+    -4900,  -100, -2700, -3900, -4600, -4400, -3300, -5100,
+    -1400, -1400, -2200, -4600, -4400, -3000, -1500, -2700,
+      100,   700,  -800, -6400, -4300, -1600,   900,   800,
+    -1500,  3600,  1200, -5400,   800, -2800,  2400,  1400,
+];
+
+const EG_PAWN_TABLE: [i32; 64] = [
+        0,     0,     0,     0,     0,     0,     0,     0,
+    17800, 17300, 15800, 13400, 14700, 13200, 16500, 18700,
+     9400, 10000,  8500,  6700,  5600,  5300,  8200,  8400,
+     3200,  2400,  1300,   500,  -200,   400,  1700,  1700,
+     1300,   900,  -300,  -700,  -700,  -800,   300,  -100,
+      400,   700,  -600,   100,     0,  -500,  -100,  -800,
+     1300,   800,   800,  1000,  1300,     0,   200,  -700,
+        0,     0,     0,     0,     0,     0,     0,     0,
+];
+
+const EG_KNIGHT_TABLE: [i32; 64] = [
+    -5800, -3800, -1300, -2800, -3100, -2700, -6300, -9900,
+    -2500,  -800, -2500,  -200,  -900, -2500, -2400, -5200,
+    -2400, -2000,  1000,   900,  -100,  -900, -1900, -4100,
+    -1700,   300,  2200,  2200,  2200,  1100,   800, -1800,
+    -1800,  -600,  1600,  2500,  1600,  1700,   400, -1800,
+    -2300,  -300,  -100,  1500,  1000,  -300, -2000, -2200,
+    -4200, -2000, -1000,  -500,  -200, -2000, -2300, -4400,
+    -2900, -5100, -2300, -1500, -2200, -1800, -5000, -6400,
+];
+
+const EG_BISHOP_TABLE: [i32; 64] = [
+    -1400, -2100, -1100,  -800,  -700,  -900, -1700, -2400,
+     -800,  -400,   700, -1200,  -300, -1300,  -400, -1400,
+      200,  -800,     0,  -100,  -200,   600,     0,   400,
+     -300,   900,  1200,   900,  1400,  1000,   300,   200,
+     -600,   300,  1300,  1900,   700,  1000,  -300,  -900,
+    -1200,  -300,   800,  1000,  1300,   300,  -700, -1500,
+    -1400, -1800,  -700,  -100,   400,  -900, -1500, -2700,
+    -2300,  -900, -2300,  -500,  -900, -1600,  -500, -1700,
+];
+
+const EG_ROOK_TABLE: [i32; 64] = [
+     1300,  1000,  1800,  1500,  1200,  1200,   800,   500,
+     1100,  1300,  1300,  1100,  -300,   300,   800,   300,
+      700,   700,   700,   500,   400,  -300,  -500,  -300,
+      400,   300,  1300,   100,   200,   100,  -100,   200,
+      300,   500,   800,   400,  -500,  -600,  -800, -1100,
+     -400,     0,  -500,  -100,  -700, -1200,  -800, -1600,
+     -600,  -600,     0,   200,  -900,  -900, -1100,  -300,
+     -900,   200,   300,  -100,  -500, -1300,   400, -2000,
+];
+
+const EG_QUEEN_TABLE: [i32; 64] = [
+     -900,  2200,  2200,  2700,  2700,  1900,  1000,  2000,
+    -1700,  2000,  3200,  4100,  5800,  2500,  3000,     0,
+    -2000,   600,   900,  4900,  4700,  3500,  1900,   900,
+      300,  2200,  2400,  4500,  5700,  4000,  5700,  3600,
+    -1800,  2800,  1900,  4700,  3100,  3400,  3900,  2300,
+    -1600, -2700,  1500,   600,   900,  1700,  1000,   500,
+    -2200, -2300, -3000, -1600, -1600, -2300, -3600, -3200,
+    -3300, -2800, -2200, -4300,  -500, -3200, -2000, -4100,
+];
+
+const EG_KING_TABLE: [i32; 64] = [
+    -7400, -3500, -1800, -1800, -1100,  1500,   400, -1700,
+    -1200,  1700,  1400,  1700,  1700,  3800,  2300,  1100,
+     1000,  1700,  2300,  1500,  2000,  4500,  4400,  1300,
+     -800,  2200,  2400,  2700,  2600,  3300,  2600,   300,
+    -1800,  -400,  2100,  2400,  2700,  2300,   900, -1100,
+    -1900,  -300,  1100,  2100,  2300,  1600,   700,  -900,
+    -2700, -1100,   400,  1300,  1400,   400,  -500, -1700,
+    -5300, -3400, -2100, -1100, -2800, -1400, -2400, -4300,
+];
+
+// Credit to Stockfish for the shape of these mobility bonuses, halved in size. Indexed by the number of safe squares a piece attacks
+const MG_KNIGHT_MOBILITY: [i32; 9] = [
+    -3100, -2650, -600, -200, 150, 650, 1100, 1400, 1650,
+];
+const EG_KNIGHT_MOBILITY: [i32; 9] = [
+    -4050, -2800, -1550, -800, 250, 550, 850, 1000, 1250,
+];
+const MG_BISHOP_MOBILITY: [i32; 14] = [
+    -2400, -1000, 800, 1300, 1900, 2550, 2750, 3150, 3150, 3400, 4050, 4050, 4550, 4900,
+];
+const EG_BISHOP_MOBILITY: [i32; 14] = [
+    -2950, -1150, -150, 650, 1200, 2100, 2700, 2850, 3250, 3650, 3900, 4300, 4400, 4850,
+];
+const MG_ROOK_MOBILITY: [i32; 15] = [
+    -3000, -1000, 100, 150, 150, 550, 1100, 1550, 2000, 2000, 2050, 2400, 2850, 2850,
+    3100,
+];
+const EG_ROOK_MOBILITY: [i32; 15] = [
+    -3900, -850, 1150, 1950, 3500, 4950, 5150, 6050, 6700, 6950, 7900, 8200, 8400, 8450,
+    8600,
+];
+const MG_QUEEN_MOBILITY: [i32; 28] = [
+    -1500, -600, -400, -450, 1000, 1150, 1150, 1750, 1900, 2650, 3200, 3250, 3250, 3300,
+    3350, 3350, 3600, 3600, 3850, 3950, 4650, 5400, 5400, 5400, 5500, 5700, 5700, 5800,
+];
+const EG_QUEEN_MOBILITY: [i32; 28] = [
+    -2400, -1500, -350, 950, 2000, 2750, 2950, 3750, 3900, 4800, 4800, 5000, 6050, 6350,
+    6550, 6650, 6800, 7050, 7350, 7500, 7550, 8400, 8400, 8550, 9100, 9100, 9600, 10950,
+];
+
+// Pieces that get a mobility bonus
+pub const MOBILITY_ROLES: [Role; 4] = [Role::Knight, Role::Bishop, Role::Rook, Role::Queen];
+
+// Pawn structure terms. The tables are indexed by how far up the board the pawn is from its own side, so 1 is a pawn that has not
+// moved and 6 is one about to promote
+const PASSED_PAWN_MG: [i32; 8] = [0, 0, 500, 1000, 3000, 5500, 9000, 0];
+const PASSED_PAWN_EG: [i32; 8] = [0, 1000, 1500, 2500, 4500, 8000, 13000, 0];
+const CANDIDATE_PAWN_MG: [i32; 8] = [0, 200, 300, 600, 1000, 1500, 0, 0];
+const CANDIDATE_PAWN_EG: [i32; 8] = [0, 400, 600, 1200, 2000, 3000, 0, 0];
+const CONNECTED_PAWN_MG: [i32; 8] = [0, 350, 400, 600, 1450, 2400, 4300, 0];
+const CONNECTED_PAWN_EG: [i32; 8] = [0, 200, 300, 500, 1000, 1800, 3000, 0];
+const DOUBLED_PAWN: Score = Score::new(-1000, -2500);
+const ISOLATED_PAWN: Score = Score::new(-500, -1500);
+const BACKWARD_PAWN: Score = Score::new(-900, -2400);
+
+// Endgame bonus for each square of distance between a king and the square in front of a passed pawn. Being close to it matters more
+// the further the pawn has got, so these grow with the rank
+const PASSED_PAWN_OWN_KING_DISTANCE: [i32; 8] = [0, 0, 0, -100, -200, -400, -600, 0];
+const PASSED_PAWN_ENEMY_KING_DISTANCE: [i32; 8] = [0, 0, 0, 250, 500, 1000, 1500, 0];
+
+// King safety terms. Each piece attacking the king zone adds its weight for every zone square it hits, and once two or more pieces
+// attack, the penalty is king_danger times the square of the total
+const KING_ATTACK_WEIGHT: ByRole<i32> = ByRole { pawn: 0, knight: 2, bishop: 2, rook: 3, queen: 5, king: 0 };
+const KING_DANGER: Score = Score::new(35, 5);
+
+// Indexed by how many ranks in front of the king the nearest pawn on each of the three files around it is, with 0 meaning no pawn
+const PAWN_SHIELD_MG: [i32; 8] = [-1500, 1500, 1000, 300, 0, 0, 0, 0];
+const PAWN_STORM_MG: [i32; 8] = [0, -500, -2500, -1200, -500, -200, 0, 0];
+const KING_OPEN_FILE: Score = Score::new(-2000, -300);
+const KING_SEMI_OPEN_FILE: Score = Score::new(-1000, -100);
+
+// Bonus for the attacker for each type of piece that has a safe square to check from
+const SAFE_CHECK_MG: ByRole<i32> = ByRole { pawn: 0, knight: 1000, bishop: 700, rook: 1100, queen: 800, king: 0 };
+const SAFE_CHECK_EG: ByRole<i32> = ByRole { pawn: 0, knight: 200, bishop: 200, rook: 300, queen: 300, king: 0 };
+
+// Bonus for having castled, or for each side a colour can still castle on
+const CASTLED: Score = Score::new(2500, 0);
+const CASTLING_RIGHT: Score = Score::new(1250, 0);
+
+// Everything evaluate can be tuned by. Each parameter has a name so it can be written to and read from a parameter file
+#[derive(Debug, Clone)]
+pub struct EvalParams {
+    pub material_mg: ByRole<i32>,
+    pub material_eg: ByRole<i32>,
+    pub psqt_mg: ByRole<[i32; 64]>,
+    pub psqt_eg: ByRole<[i32; 64]>,
+    pub mobility_mg: ByRole<Vec<i32>>,
+    pub mobility_eg: ByRole<Vec<i32>>,
+    pub passed_pawn_mg: [i32; 8],
+    pub passed_pawn_eg: [i32; 8],
+    pub passed_pawn_own_king_distance: [i32; 8],
+    pub passed_pawn_enemy_king_distance: [i32; 8],
+    pub candidate_pawn_mg: [i32; 8],
+    pub candidate_pawn_eg: [i32; 8],
+    pub connected_pawn_mg: [i32; 8],
+    pub connected_pawn_eg: [i32; 8],
+    pub doubled_pawn: Score,
+    pub isolated_pawn: Score,
+    pub backward_pawn: Score,
+    pub king_attack_weight: ByRole<i32>,
+    pub king_danger: Score,
+    pub pawn_shield_mg: [i32; 8],
+    pub pawn_storm_mg: [i32; 8],
+    pub king_open_file: Score,
+    pub king_semi_open_file: Score,
+    pub safe_check_mg: ByRole<i32>,
+    pub safe_check_eg: ByRole<i32>,
+    pub castled: Score,
+    pub castling_right: Score,
+}
+
+impl Default for EvalParams {
+    fn default() -> EvalParams {
+        EvalParams {
+            material_mg: MATERIAL_MG,
+            material_eg: MATERIAL_EG,
+            psqt_mg: ByRole {
+                pawn: MG_PAWN_TABLE,
+                knight: MG_KNIGHT_TABLE,
+                bishop: MG_BISHOP_TABLE,
+                rook: MG_ROOK_TABLE,
+                queen: MG_QUEEN_TABLE,
+                king: MG_KING_TABLE,
+            },
+            psqt_eg: ByRole {
+                pawn: EG_PAWN_TABLE,
+                knight: EG_KNIGHT_TABLE,
+                bishop: EG_BISHOP_TABLE,
+                rook: EG_ROOK_TABLE,
+                queen: EG_QUEEN_TABLE,
+                king: EG_KING_TABLE,
+            },
+            mobility_mg: ByRole {
+                pawn: Vec::new(),
+                knight: MG_KNIGHT_MOBILITY.to_vec(),
+                bishop: MG_BISHOP_MOBILITY.to_vec(),
+                rook: MG_ROOK_MOBILITY.to_vec(),
+                queen: MG_QUEEN_MOBILITY.to_vec(),
+                king: Vec::new(),
+            },
+            mobility_eg: ByRole {
+                pawn: Vec::new(),
+                knight: EG_KNIGHT_MOBILITY.to_vec(),
+                bishop: EG_BISHOP_MOBILITY.to_vec(),
+                rook: EG_ROOK_MOBILITY.to_vec(),
+                queen: EG_QUEEN_MOBILITY.to_vec(),
+                king: Vec::new(),
+            },
+            passed_pawn_mg: PASSED_PAWN_MG,
+            passed_pawn_eg: PASSED_PAWN_EG,
+            passed_pawn_own_king_distance: PASSED_PAWN_OWN_KING_DISTANCE,
+            passed_pawn_enemy_king_distance: PASSED_PAWN_ENEMY_KING_DISTANCE,
+            candidate_pawn_mg: CANDIDATE_PAWN_MG,
+            candidate_pawn_eg: CANDIDATE_PAWN_EG,
+            connected_pawn_mg: CONNECTED_PAWN_MG,
+            connected_pawn_eg: CONNECTED_PAWN_EG,
+            doubled_pawn: DOUBLED_PAWN,
+            isolated_pawn: ISOLATED_PAWN,
+            backward_pawn: BACKWARD_PAWN,
+            king_attack_weight: KING_ATTACK_WEIGHT,
+            king_danger: KING_DANGER,
+            pawn_shield_mg: PAWN_SHIELD_MG,
+            pawn_storm_mg: PAWN_STORM_MG,
+            king_open_file: KING_OPEN_FILE,
+            king_semi_open_file: KING_SEMI_OPEN_FILE,
+            safe_check_mg: SAFE_CHECK_MG,
+            safe_check_eg: SAFE_CHECK_EG,
+            castled: CASTLED,
+            castling_right: CASTLING_RIGHT,
+        }
+    }
+}
+
+// Every parameter as its name and references to its values, as &mut or & depending on whether mut is given. This is the one place a new
+// parameter has to be listed for it to be read and written by parameter files
+macro_rules! eval_param_fields {
+    ($params:expr $(, $mutability:tt)?) => {{
+        let mut fields = Vec::new();
+        
+        fields.push(("material_mg".to_string(), by_role_values!(&$($mutability)? $params.material_mg)));
+        fields.push(("material_eg".to_string(), by_role_values!(&$($mutability)? $params.material_eg)));
+        
+        let ByRole { pawn, knight, bishop, rook, queen, king } = &$($mutability)? $params.psqt_mg;
+        for (role, table) in ALL_ROLES.iter().zip([pawn, knight, bishop, rook, queen, king]) {
+            fields.push((format!("psqt_mg_{}", role.name()), table.into_iter().collect()));
+        }
+        let ByRole { pawn, knight, bishop, rook, queen, king } = &$($mutability)? $params.psqt_eg;
+        for (role, table) in ALL_ROLES.iter().zip([pawn, knight, bishop, rook, queen, king]) {
+            fields.push((format!("psqt_eg_{}", role.name()), table.into_iter().collect()));
+        }
+        
+        let ByRole { knight, bishop, rook, queen, .. } = &$($mutability)? $params.mobility_mg;
+        for (role, table) in MOBILITY_ROLES.iter().zip([knight, bishop, rook, queen]) {
+            fields.push((format!("mobility_mg_{}", role.name()), table.into_iter().collect()));
+        }
+        let ByRole { knight, bishop, rook, queen, .. } = &$($mutability)? $params.mobility_eg;
+        for (role, table) in MOBILITY_ROLES.iter().zip([knight, bishop, rook, queen]) {
+            fields.push((format!("mobility_eg_{}", role.name()), table.into_iter().collect()));
+        }
+        
+        fields.push(("passed_pawn_mg".to_string(), (&$($mutability)? $params.passed_pawn_mg).into_iter().collect()));
+        fields.push(("passed_pawn_eg".to_string(), (&$($mutability)? $params.passed_pawn_eg).into_iter().collect()));
+        fields.push(("passed_pawn_own_king_distance".to_string(), (&$($mutability)? $params.passed_pawn_own_king_distance).into_iter().collect()));
+        fields.push(("passed_pawn_enemy_king_distance".to_string(), (&$($mutability)? $params.passed_pawn_enemy_king_distance).into_iter().collect()));
+        fields.push(("candidate_pawn_mg".to_string(), (&$($mutability)? $params.candidate_pawn_mg).into_iter().collect()));
+        fields.push(("candidate_pawn_eg".to_string(), (&$($mutability)? $params.candidate_pawn_eg).into_iter().collect()));
+        fields.push(("connected_pawn_mg".to_string(), (&$($mutability)? $params.connected_pawn_mg).into_iter().collect()));
+        fields.push(("connected_pawn_eg".to_string(), (&$($mutability)? $params.connected_pawn_eg).into_iter().collect()));
+        fields.push(("doubled_pawn".to_string(), score_values!(&$($mutability)? $params.doubled_pawn)));
+        fields.push(("isolated_pawn".to_string(), score_values!(&$($mutability)? $params.isolated_pawn)));
+        fields.push(("backward_pawn".to_string(), score_values!(&$($mutability)? $params.backward_pawn)));
+        
+        fields.push(("king_attack_weight".to_string(), by_role_values!(&$($mutability)? $params.king_attack_weight)));
+        fields.push(("king_danger".to_string(), score_values!(&$($mutability)? $params.king_danger)));
+        fields.push(("pawn_shield_mg".to_string(), (&$($mutability)? $params.pawn_shield_mg).into_iter().collect()));
+        fields.push(("pawn_storm_mg".to_string(), (&$($mutability)? $params.pawn_storm_mg).into_iter().collect()));
+        fields.push(("king_open_file".to_string(), score_values!(&$($mutability)? $params.king_open_file)));
+        fields.push(("king_semi_open_file".to_string(), score_values!(&$($mutability)? $params.king_semi_open_file)));
+        fields.push(("safe_check_mg".to_string(), by_role_values!(&$($mutability)? $params.safe_check_mg)));
+        fields.push(("safe_check_eg".to_string(), by_role_values!(&$($mutability)? $params.safe_check_eg)));
+        fields.push(("castled".to_string(), score_values!(&$($mutability)? $params.castled)));
+        fields.push(("castling_right".to_string(), score_values!(&$($mutability)? $params.castling_right)));
+        
+                fields
+    }};
+}
+
+macro_rules! by_role_values {
+    ($values:expr) => {{
+        let ByRole { pawn, knight, bishop, rook, queen, king } = $values;
+        vec![pawn, knight, bishop, rook, queen, king]
+    }};
+}
+
+macro_rules! score_values {
+    ($score:expr) => {{
+        let Score { mg, eg } = $score;
+        vec![mg, eg]
+    }};
+}
+
+impl EvalParams {
+    
+    // Every parameter as its name and values, in the order they are written to a parameter file
+    pub fn named_values(&self) -> Vec<(String, Vec<i32>)> {
+        return self.fields().into_iter().map(|(name, values)| (name, values.into_iter().copied().collect())).collect();
+    }
+    
+    // Sets a parameter by the name used in named_values
+    pub fn set_named_values(&mut self, name: &str, values: &[i32]) -> Result<(), String> {
+        for (field_name, field) in self.fields_mut() {
+            if field_name == name {
+                check_length(name, values, field.len())?;
+                for (field_value, value) in field.into_iter().zip(values) {
+                    *field_value = *value;
+                }
+                return Ok(());
+            }
+        }
+        return Err(format!("Unknown evaluation parameter {}", name));
+    }
+    
+    // One value of a parameter, by the parameters name and the values position in it
+    pub fn value(&self, name: &str, index: usize) -> Option<i32> {
+        let (_, values) = self.fields().into_iter().find(|(field_name, _)| field_name == name)?;
+        return values.into_iter().nth(index).copied();
+    }
+    
+    pub fn value_mut(&mut self, name: &str, index: usize) -> Option<&mut i32> {
+        let (_, values) = self.fields_mut().into_iter().find(|(field_name, _)| field_name == name)?;
+        return values.into_iter().nth(index);
+    }
+    
+    // Every parameter as its name and its values, read only or to be changed, in the order eval_param_fields lists them
+    pub fn fields(&self) -> Vec<(String, Vec<&i32>)> {
+        return eval_param_fields!(self);
+    }
+    
+    pub fn fields_mut(&mut self) -> Vec<(String, Vec<&mut i32>)> {
+        return eval_param_fields!(self, mut);
+    }
+    
+    // Reads a parameter file. Each parameter is its name followed by its values, separated by any whitespace, and anything after a # is
+    // a comment. Parameters missing from the file keep their default values
+    pub fn from_file(path: &str) -> Result<EvalParams, String> {
+        let contents = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        
+        let mut params = EvalParams::default();
+        let mut name: Option<&str> = None;
+        let mut values: Vec<i32> = Vec::new();
+        
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            for token in line.split_whitespace() {
+                if let Ok(value) = token.parse::<i32>() {
+                    values.push(value);
+                } else {
+                    if let Some(name) = name {
+                        params.set_named_values(name, &values)?;
+                    }
+                    name = Some(token);
+                    values.clear();
+                }
+            }
+        }
+        
+        if let Some(name) = name {
+            params.set_named_values(name, &values)?;
+        }
+        
+        return Ok(params);
+    }
+    
+    // Writes every parameter in the format from_file reads, eight values to a line so tables keep the shape of the board
+    pub fn to_file(&self, path: &str) -> Result<(), String> {
+        let mut contents = String::from("# ChessLibrary evaluation parameters. A pawn is worth 10000\n");
+        
+        for (name, values) in self.named_values() {
+            contents.push('\n');
+            contents.push_str(&name);
+            contents.push('\n');
+            for row in values.chunks(8) {
+                let row: Vec<String> = row.iter().map(|value| format!("{:>6}", value)).collect();
+                contents.push_str(&row.join(" "));
+                contents.push('\n');
+            }
+        }
+        
+        return fs::write(path, contents).map_err(|error| format!("Could not write {}: {}", path, error));
+    }
+}
+
+fn check_length(name: &str, values: &[i32], expected_length: usize) -> Result<(), String> {
+    if values.len() != expected_length {
+        return Err(format!("Evaluation parameter {} needs {} values but has {}", name, expected_length, values.len()));
+    }
+    return Ok(());
+}
+
+// Replaces the parameters used by evaluate with the ones in a parameter file
+pub fn load_eval_params(path: &str) -> Result<(), String> {
+    let params = EvalParams::from_file(path)?;
+    *EVAL_PARAMS.write().unwrap() = params;
+    invalidate_pawn_hash();
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_and_mutable_fields_agree() {
+        let mut params = EvalParams::default();
+        let names: Vec<(String, usize)> = params.fields().into_iter().map(|(name, values)| (name, values.len())).collect();
+        let mutable_names: Vec<(String, usize)> = params.fields_mut().into_iter().map(|(name, values)| (name, values.len())).collect();
+        assert_eq!(names, mutable_names);
+
+        *params.value_mut("psqt_eg_king", 63).unwrap() = 1234;
+        assert_eq!(params.value("psqt_eg_king", 63), Some(1234));
+        assert_eq!(params.psqt_eg.king[63], 1234);
+        assert_eq!(params.value("psqt_eg_king", 64), None);
+        assert_eq!(params.value("no_such_parameter", 0), None);
+    }
+
+    #[test]
+    fn parameter_files_round_trip() {
+        let mut params = EvalParams::default();
+        params.set_named_values("castled", &[1, 2]).unwrap();
+        assert!(params.set_named_values("castled", &[1]).is_err());
+
+        let path = std::env::temp_dir().join(format!("chesslibrary_params_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        params.to_file(path).unwrap();
+        let read = EvalParams::from_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(read.named_values(), params.named_values());
+        assert_eq!((read.castled.mg, read.castled.eg), (1, 2));
+    }
+}
@@ -0,0 +1,314 @@
+use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
+use crate::board::Board;
+use crate::colour::{ByColour, Colour};
+use crate::engine::eval::Score;
+use crate::engine::params::EvalParams;
+use crate::movegen::{get_black_pawn_attacks, get_white_pawn_attacks};
+use crate::square::{FILE_A, FILE_H};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Number of entries in each threads pawn hash table. Must be a power of two
+const PAWN_HASH_ENTRIES: usize = 1 << 14;
+
+// Bumped whenever the evaluation parameters change, so pawn hash tables made with the old parameters get cleared
+pub static PAWN_HASH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static PAWN_HASH_TABLE: RefCell<PawnHashTable> = RefCell::new(PawnHashTable::new());
+}
+
+// Masks for each square, indexed by trailing zeros
+const FILES: [Bitboard; 64] = bootstrap_files();
+const ADJACENT_FILES: [Bitboard; 64] = bootstrap_adjacent_files();
+const RANKS_AHEAD: ByColour<[Bitboard; 64]> = ByColour { white: bootstrap_ranks_ahead(true), black: bootstrap_ranks_ahead(false) };
+
+// The result of evaluating a pawn structure. Only depends on where the pawns are, so it can be cached by the pawn hash
+#[derive(Debug, Clone, Copy)]
+pub struct PawnEntry {
+    pub key: u64,
+    pub score: ByColour<Score>,
+    pub passed_pawns: ByColour<Bitboard>,
+}
+
+struct PawnHashTable {
+    entries: Vec<Option<PawnEntry>>,
+    generation: u64,
+}
+
+impl PawnHashTable {
+    fn new() -> PawnHashTable {
+        PawnHashTable { entries: vec![None; PAWN_HASH_ENTRIES], generation: PAWN_HASH_GENERATION.load(Ordering::Relaxed) }
+    }
+}
+
+// Clears every threads pawn hash table the next time it is used. Has to be called whenever the evaluation parameters change
+pub fn invalidate_pawn_hash() {
+    PAWN_HASH_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+// Squares on the same file as the square, further up the board for the colour. A pawn with an own pawn here is doubled, and one
+// with an enemy pawn here is blocked
+pub fn front_span(colour: Colour, square: &Bitboard) -> Bitboard {
+    let index = square.trailing_zeros() as usize;
+    return RANKS_AHEAD.get(colour)[index] & FILES[index];
+}
+
+// Squares on the adjacent files further up the board for the colour, which are the squares a pawn could attack as it advances
+pub fn pawn_attack_span(colour: Colour, square: &Bitboard) -> Bitboard {
+    let index = square.trailing_zeros() as usize;
+    return RANKS_AHEAD.get(colour)[index] & ADJACENT_FILES[index];
+}
+
+// Squares an enemy pawn would have to be on to stop a pawn from being passed
+pub fn passed_pawn_span(colour: Colour, square: &Bitboard) -> Bitboard {
+    return front_span(colour, square) | pawn_attack_span(colour, square);
+}
+
+// How far up the board a square is for a colour, from 0 on the colours back rank to 7 on the promotion rank
+pub fn relative_rank(colour: Colour, square: &Bitboard) -> usize {
+    let rank = square.trailing_zeros() as usize / 8;
+    match colour {
+        Colour::White => return rank,
+        Colour::Black => return 7 - rank,
+    }
+}
+
+// Number of king moves between two squares
+pub fn square_distance(first: &Bitboard, second: &Bitboard) -> i32 {
+    let first_index = first.trailing_zeros();
+    let second_index = second.trailing_zeros();
+    let rank_distance = (first_index / 8 - second_index / 8).abs();
+    let file_distance = (first_index % 8 - second_index % 8).abs();
+    return rank_distance.max(file_distance);
+}
+
+// Gets the pawn structure evaluation, from the pawn hash table if this structure has been seen before
+pub fn probe_pawn_structure(board: &Board, params: &EvalParams) -> PawnEntry {
+    let key = board.pawn_hash();
+
+    return PAWN_HASH_TABLE.with(|table| {
+        let mut table = table.borrow_mut();
+
+        let generation = PAWN_HASH_GENERATION.load(Ordering::Relaxed);
+        if table.generation != generation {
+            *table = PawnHashTable::new();
+        }
+
+        let index = key as usize & (PAWN_HASH_ENTRIES - 1);
+        if let Some(entry) = table.entries[index] && entry.key == key {
+            return entry;
+        }
+
+        let (white_score, white_passed_pawns) = pawn_structure_evaluation(board, params, Colour::White);
+        let (black_score, black_passed_pawns) = pawn_structure_evaluation(board, params, Colour::Black);
+        let entry = PawnEntry {
+            key,
+            score: ByColour { white: white_score, black: black_score },
+            passed_pawns: ByColour { white: white_passed_pawns, black: black_passed_pawns },
+        };
+        table.entries[index] = Some(entry);
+        return entry;
+    });
+}
+
+// Evaluates a colours pawn structure, returning the score and the colours passed pawns
+pub fn pawn_structure_evaluation(board: &Board, params: &EvalParams, colour: Colour) -> (Score, Bitboard) {
+    let mut score = Score::default();
+    let mut passed_pawns = EMPTY_BITBOARD;
+
+    let own_pawns = *board.colour.get(colour) & board.role.pawn;
+    let enemy_pawns = *board.colour.get(colour.opposite()) & board.role.pawn;
+    let (own_pawn_attacks, enemy_pawn_attacks) = match colour {
+        Colour::White => (get_white_pawn_attacks(board, &own_pawns), get_black_pawn_attacks(board, &enemy_pawns)),
+        Colour::Black => (get_black_pawn_attacks(board, &own_pawns), get_white_pawn_attacks(board, &enemy_pawns)),
+    };
+
+    for pawn in own_pawns.get_component_bitboards() {
+        let index = pawn.trailing_zeros() as usize;
+        let rank = relative_rank(colour, &pawn);
+        let stop_square = match colour {
+            Colour::White => pawn << 8,
+            Colour::Black => pawn >> 8,
+        };
+
+        let adjacent_pawns = own_pawns & ADJACENT_FILES[index];
+        // Own pawns on the adjacent files that are level with or behind this one, so could still support it
+        let helpers = adjacent_pawns & !RANKS_AHEAD.get(colour)[index];
+        let phalanx = (helpers & !RANKS_AHEAD.get(colour.opposite())[index]) != EMPTY_BITBOARD;
+        let supported = (own_pawn_attacks & pawn) != EMPTY_BITBOARD;
+
+        let passed = (enemy_pawns & passed_pawn_span(colour, &pawn)) == EMPTY_BITBOARD;
+        let isolated = adjacent_pawns == EMPTY_BITBOARD;
+        let doubled = (own_pawns & front_span(colour, &pawn)) != EMPTY_BITBOARD;
+        let backward = !isolated && (helpers == EMPTY_BITBOARD) && (enemy_pawn_attacks & stop_square) != EMPTY_BITBOARD;
+        let connected = phalanx || supported;
+
+        if passed && !doubled {
+            passed_pawns |= pawn;
+            score += Score::new(params.passed_pawn_mg[rank], params.passed_pawn_eg[rank]);
+        } else if !passed && (enemy_pawns & front_span(colour, &pawn)) == EMPTY_BITBOARD {
+            // Candidate passed pawns are on an open file and have at least as many own pawns able to help them through as enemy pawns
+            // guarding their path
+            let sentries = enemy_pawns & pawn_attack_span(colour, &pawn);
+            if sentries.count_ones() <= helpers.count_ones() {
+                score += Score::new(params.candidate_pawn_mg[rank], params.candidate_pawn_eg[rank]);
+            }
+        }
+
+        if isolated {
+            score += params.isolated_pawn;
+        } else if backward {
+            score += params.backward_pawn;
+        }
+        if doubled {
+            score += params.doubled_pawn;
+        }
+        if connected {
+            score += Score::new(params.connected_pawn_mg[rank], params.connected_pawn_eg[rank]);
+        }
+    }
+
+    return (score, passed_pawns);
+}
+
+// Endgame bonus for passed pawns whose square in front is close to the own king and far from the enemy king. Depends on where the
+// kings are, so it is not part of the cached pawn structure evaluation
+pub fn passed_pawn_king_evaluation(board: &Board, params: &EvalParams, colour: Colour, passed_pawns: &Bitboard) -> Score {
+    let mut score = Score::default();
+
+    let own_king = *board.colour.get(colour) & board.role.king;
+    let enemy_king = *board.colour.get(colour.opposite()) & board.role.king;
+    if (own_king == EMPTY_BITBOARD) | (enemy_king == EMPTY_BITBOARD) {
+        return score;
+    }
+
+    for pawn in passed_pawns.get_component_bitboards() {
+        let rank = relative_rank(colour, &pawn);
+        let stop_square = match colour {
+            Colour::White => pawn << 8,
+            Colour::Black => pawn >> 8,
+        };
+        score.eg += square_distance(&own_king, &stop_square) * params.passed_pawn_own_king_distance[rank];
+        score.eg += square_distance(&enemy_king, &stop_square) * params.passed_pawn_enemy_king_distance[rank];
+    }
+
+    return score;
+}
+
+const fn bootstrap_files() -> [Bitboard; 64] {
+    let mut table = [Bitboard(0); 64];
+    let mut square = 0;
+
+    while square < 64 {
+        table[square] = Bitboard(FILE_H.0 << (square % 8));
+        square += 1;
+    }
+
+    return table;
+}
+
+const fn bootstrap_adjacent_files() -> [Bitboard; 64] {
+    let mut table = [Bitboard(0); 64];
+    let mut square = 0;
+
+    while square < 64 {
+        let file = FILE_H.0 << (square % 8);
+        table[square] = Bitboard(((file & !FILE_A.0) << 1) | ((file & !FILE_H.0) >> 1));
+        square += 1;
+    }
+
+    return table;
+}
+
+// Every rank strictly in front of each square, from the point of view of white when white is true and black otherwise
+const fn bootstrap_ranks_ahead(white: bool) -> [Bitboard; 64] {
+    let mut table = [Bitboard(0); 64];
+    let mut square = 0;
+
+    while square < 64 {
+        let rank = square / 8;
+        if white {
+            table[square] = Bitboard(if rank == 7 { 0 } else { u64::MAX << ((rank + 1) * 8) });
+        } else {
+            table[square] = Bitboard(if rank == 0 { 0 } else { u64::MAX >> ((8 - rank) * 8) });
+        }
+        square += 1;
+    }
+
+    return table;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci::get_uci_square_bitboard;
+
+    fn squares(names: &str) -> Bitboard {
+        return names.split_whitespace().fold(EMPTY_BITBOARD, |squares, name| squares | get_uci_square_bitboard(name));
+    }
+
+    // Evaluates a white pawn structure and the same structure flipped for black, which have to agree
+    fn evaluate_both_ways(white_fen: &str, black_fen: &str) -> (Score, Bitboard, Bitboard) {
+        let params = EvalParams::default();
+        let (white_score, white_passed_pawns) = pawn_structure_evaluation(&Board::from_fen(white_fen.to_string()), &params, Colour::White);
+        let (black_score, black_passed_pawns) = pawn_structure_evaluation(&Board::from_fen(black_fen.to_string()), &params, Colour::Black);
+        assert_eq!(white_score, black_score);
+        return (white_score, white_passed_pawns, black_passed_pawns);
+    }
+
+    #[test]
+    fn spans_stay_on_the_board_at_the_edges() {
+        assert_eq!(front_span(Colour::White, &squares("a2")), squares("a3 a4 a5 a6 a7 a8"));
+        assert_eq!(front_span(Colour::Black, &squares("h7")), squares("h6 h5 h4 h3 h2 h1"));
+        assert_eq!(front_span(Colour::White, &squares("h8")), EMPTY_BITBOARD);
+        assert_eq!(pawn_attack_span(Colour::White, &squares("a6")), squares("b7 b8"));
+        assert_eq!(pawn_attack_span(Colour::Black, &squares("a3")), squares("b2 b1"));
+        assert_eq!(passed_pawn_span(Colour::White, &squares("h7")), squares("h8 g8"));
+        assert_eq!(passed_pawn_span(Colour::White, &squares("a7")), squares("a8 b8"));
+        assert_eq!(passed_pawn_span(Colour::Black, &squares("a2")), squares("a1 b1"));
+        assert_eq!(passed_pawn_span(Colour::Black, &squares("h2")), squares("h1 g1"));
+    }
+
+    #[test]
+    fn lone_pawns_are_isolated_and_passed() {
+        let params = EvalParams::default();
+        let (score, white_passed_pawns, black_passed_pawns) = evaluate_both_ways("4k3/8/8/8/8/8/P7/4K3 w - -", "4k3/p7/8/8/8/8/8/4K3 b - -");
+        assert_eq!(score, params.isolated_pawn + Score::new(params.passed_pawn_mg[1], params.passed_pawn_eg[1]));
+        assert_eq!(white_passed_pawns, squares("a2"));
+        assert_eq!(black_passed_pawns, squares("a7"));
+    }
+
+    #[test]
+    fn only_the_front_doubled_pawn_is_passed() {
+        let params = EvalParams::default();
+        let (score, white_passed_pawns, black_passed_pawns) = evaluate_both_ways("4k3/8/8/8/8/P7/P7/4K3 w - -", "4k3/p7/p7/8/8/8/8/4K3 b - -");
+        assert_eq!(score, params.isolated_pawn + params.isolated_pawn + params.doubled_pawn + Score::new(params.passed_pawn_mg[2], params.passed_pawn_eg[2]));
+        assert_eq!(white_passed_pawns, squares("a3"));
+        assert_eq!(black_passed_pawns, squares("a6"));
+    }
+
+    #[test]
+    fn pawns_left_behind_are_backward() {
+        // The c pawn has no pawn beside or behind it and its stop square is covered by the enemy d pawn, so it is backward. The b pawn
+        // is passed and not backward, as the c pawn could still come up to support it
+        let params = EvalParams::default();
+        let (score, white_passed_pawns, black_passed_pawns) = evaluate_both_ways("4k3/8/8/8/1P1p4/8/2P5/4K3 w - -", "4k3/2p5/8/1p1P4/8/8/8/4K3 b - -");
+        assert_eq!(score, params.backward_pawn + Score::new(params.passed_pawn_mg[3], params.passed_pawn_eg[3]));
+        assert_eq!(white_passed_pawns, squares("b4"));
+        assert_eq!(black_passed_pawns, squares("b5"));
+    }
+
+    #[test]
+    fn supported_pawns_on_open_files_are_candidates() {
+        // The d pawn has one sentry on e6 and one helper on c3, which also supports it. The c pawn is passed
+        let params = EvalParams::default();
+        let (score, white_passed_pawns, black_passed_pawns) = evaluate_both_ways("4k3/8/4p3/8/3P4/2P5/8/4K3 w - -", "4k3/8/2p5/3p4/8/4P3/8/4K3 b - -");
+        let candidate = Score::new(params.candidate_pawn_mg[3], params.candidate_pawn_eg[3]);
+        let connected = Score::new(params.connected_pawn_mg[3], params.connected_pawn_eg[3]);
+        let passed = Score::new(params.passed_pawn_mg[2], params.passed_pawn_eg[2]);
+        assert_eq!(score, candidate + connected + passed);
+        assert_eq!(white_passed_pawns, squares("c3"));
+        assert_eq!(black_passed_pawns, squares("c6"));
+    }
+}
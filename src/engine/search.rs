@@ -1,101 +1,213 @@
 use crate::colour::Colour;
 use crate::board::{Board};
 use crate::movegen::get_legal_moves;
-use crate::engine::eval::{calculate_attack_mobility, evaluate};
+use crate::engine::eval::evaluate;
+use crate::engine::syzygy::{largest_table, tablebase_evaluation, tablebase_root_moves};
 use crate::mv::Move;
-use crate::uci::{from_uci, to_uci};
+use crate::role::Role;
 use std::cmp;
 use std::cmp::Reverse;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicUsize, Ordering};
 use once_cell::sync::Lazy;
 use arrayvec::ArrayVec;
 use std::time::{Duration, Instant};
 
-use pyo3::prelude::*;
-use pyo3::wrap_pyfunction;
-
 pub static NODE_COUNT: Lazy<AtomicUsize> = Lazy::new(|| AtomicUsize::new(0));
 
-#[pyfunction]
-pub fn pick_move(board_starting_position: String, bot_time: (u64, u64), bot_colour: String, move_list: String) -> PyResult<(String, i32)> {
-    
-    NODE_COUNT.store(0, Ordering::Relaxed);
+const MAX_SEARCH_DEPTH: i32 = 50;
+
+// Tablebases aren't probed closer to the leaves than this, where reading the files costs more than the search it saves
+const TB_PROBE_DEPTH: i32 = 1;
+
+// Longest principal variation kept. Has to be larger than the max search depth
+pub const MAX_PV_LENGTH: usize = 64;
+
+// A single move uses at most one over this of the time left on the clock
+const MAX_TIME_FRACTION: u64 = 3;
+
+// Shared between a running search and whoever started it, so the search can be stopped or a ponder turned into a real search
+pub struct SearchControl {
+    pub start_time: Instant,
+    pub max_search_time_ms: AtomicU64,
+    pub stop: AtomicBool,
+    pub pondering: AtomicBool,
+    // Nodes searched so far, and the most the search may use. Counted per search so searches on different threads don't share a limit
+    pub nodes: AtomicU64,
+    pub max_nodes: AtomicU64,
+    // The deepest iteration the search will start
+    pub max_depth: AtomicI32,
+}
+
+impl SearchControl {
+    pub fn new(max_search_time: Duration, pondering: bool) -> SearchControl {
+        SearchControl {
+            start_time: Instant::now(),
+            max_search_time_ms: AtomicU64::new(max_search_time.as_millis().min(u64::MAX as u128) as u64),
+            stop: AtomicBool::new(false),
+            pondering: AtomicBool::new(pondering),
+            nodes: AtomicU64::new(0),
+            max_nodes: AtomicU64::new(u64::MAX),
+            max_depth: AtomicI32::new(MAX_SEARCH_DEPTH),
+        }
+    }
     
-    let start_time = Instant::now();
+    // A search with no time limit that stops after searching a fixed number of nodes
+    pub fn with_node_limit(max_nodes: u64) -> SearchControl {
+        let control = SearchControl::new(Duration::MAX, false);
+        control.max_nodes.store(max_nodes, Ordering::Relaxed);
+        return control;
+    }
     
-    let bot_colour = match bot_colour.as_str() {
-        "white" => Colour::White,
-        "black" => Colour::Black,
-        _ => return Ok(("Invalid colour.".to_string(), 0)),
-    };
+    // A search with no time limit that stops once it has finished a depth
+    pub fn with_depth_limit(max_depth: i32) -> SearchControl {
+        let control = SearchControl::new(Duration::MAX, false);
+        control.max_depth.store(max_depth, Ordering::Relaxed);
+        return control;
+    }
     
-    let opponent_colour;
-    if bot_colour == Colour::White {
-        opponent_colour = Colour::Black;
-    } else {
-        opponent_colour = Colour::White;
+    // A search never runs out of time while pondering, only when it's stopped or out of nodes
+    pub fn time_up(&self) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        if self.nodes.load(Ordering::Relaxed) >= self.max_nodes.load(Ordering::Relaxed) {
+            return true;
+        }
+        if self.pondering.load(Ordering::Relaxed) {
+            return false;
+        }
+        return self.start_time.elapsed() > Duration::from_millis(self.max_search_time_ms.load(Ordering::Relaxed));
     }
     
-    let mut board: Board;
+    // Turns a ponder into a normal search. The time is still measured from when the ponder started, so the time already spent pondering counts
+    pub fn ponderhit(&self, max_search_time: Option<Duration>) {
+        if let Some(max_search_time) = max_search_time {
+            self.max_search_time_ms.store(max_search_time.as_millis() as u64, Ordering::Relaxed);
+        }
+        self.pondering.store(false, Ordering::Relaxed);
+    }
     
-    if board_starting_position == String::from("startpos") {
-        board = Board::starting_position();   
-    } else {
-        board = Board::from_fen(board_starting_position);
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
     }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub evaluation: i32,
+    pub pv: ArrayVec<Move, MAX_PV_LENGTH>,
+    pub depth: i32,
+    pub lines: Vec<PvLine>,
+}
 
-    for mv in move_list.split_whitespace() {
-        board.play(from_uci(&board, mv));
+// One of the best root moves found with its own evaluation and principal variation. The first move of the pv is the root move
+#[derive(Debug, Clone)]
+pub struct PvLine {
+    pub evaluation: i32,
+    pub pv: ArrayVec<Move, MAX_PV_LENGTH>,
+}
+
+impl SearchResult {
+    // The reply the search expects from the opponent, which is what gets pondered on
+    pub fn ponder_move(&self) -> Option<Move> {
+        return self.pv.get(1).copied();
     }
+}
+
+
+// Iterative deepening search from the side to move until the search control says time is up. Every root move is searched with a full
+// window so its evaluation is exact, which lets the best multi_pv of them be reported as separate lines
+pub fn search(board: &Board, control: &SearchControl, multi_pv: usize) -> SearchResult {
     
-    let max_search_time: Duration = search_time(bot_time);
+    // Boards made before a network was loaded need their NNUE accumulators rebuilt, otherwise every evaluation rebuilds them
+    #[cfg(feature = "nnue")]
+    let board = &board.with_fresh_accumulator();
+    
+    let bot_colour = board.turn;
+    
+    let opponent_colour = bot_colour.opposite();
     
     let mut ordered_legal_moves = get_legal_moves(&board);
+    
+    // In tablebase positions only the moves that keep the best result are searched, and winning moves have to make progress towards the
+    // next capture or pawn move
+    if let Some(tablebase_moves) = tablebase_root_moves(board) {
+        ordered_legal_moves.retain(|mv| tablebase_moves.contains(mv));
+    }
     let total_legal_moves: i32 = ordered_legal_moves.len() as i32;
     
-    let mut overall_best_mv: Option<Move> = None;
-    let mut overall_best_mv_evaluation: i32 = i32::MIN;
+    // Played if time runs out before the first depth is finished, so there is always a move to play. Nothing is known about it, so it is
+    // given an even evaluation rather than looking like a forced loss
+    let fallback_move = ordered_legal_moves.first().copied();
+    
+    let mut result = SearchResult {
+        best_move: None,
+        evaluation: i32::MIN,
+        pv: ArrayVec::new(),
+        depth: 0,
+        lines: Vec::new(),
+    };
     
     let mut current_depth = 1;
     
-    while (start_time.elapsed() < max_search_time) & (current_depth < 50) {
+    while !control.time_up() & (current_depth < MAX_SEARCH_DEPTH) & (current_depth <= control.max_depth.load(Ordering::Relaxed)) {
         
         let mut local_best_mv: Option<Move> = None;
         let mut local_best_mv_evaluation: i32 = i32::MIN;
+        let mut local_best_pv: ArrayVec<Move, MAX_PV_LENGTH> = ArrayVec::new();
         
         let mut move_evaluation: ArrayVec<(Move, i32), 218> = ArrayVec::<(Move, i32), 218>::new();
+        let mut root_lines: Vec<PvLine> = Vec::new();
         for mv in ordered_legal_moves {
 
             let mut current_board = board.clone();
             current_board.play_unsafe(mv);
             
+            let mut child_pv: ArrayVec<Move, MAX_PV_LENGTH> = ArrayVec::new();
+            
             let eval;
             if current_board.is_stalemate(&opponent_colour) {
                 eval = -1000;
             } else {
-                eval = minmax(&current_board, current_depth - 1, false, i32::MIN, i32::MAX, &bot_colour, start_time, max_search_time);
+                eval = minmax(&current_board, current_depth - 1, i32::MIN, i32::MAX, &bot_colour, control, &mut child_pv);
             }
 
             if eval > local_best_mv_evaluation {
                 local_best_mv = Some(mv);
                 local_best_mv_evaluation = eval;
+                update_pv(&mut local_best_pv, mv, &child_pv);
             }
 
             move_evaluation.push((mv, eval));
+            
+            let mut line = PvLine {
+                evaluation: eval,
+                pv: ArrayVec::new(),
+            };
+            update_pv(&mut line.pv, mv, &child_pv);
+            root_lines.push(line);
         }
         
-        if (start_time.elapsed() < max_search_time) & (current_depth < 50) {
+        if !control.time_up() & (current_depth < MAX_SEARCH_DEPTH) {
 
-            overall_best_mv = local_best_mv;
-            overall_best_mv_evaluation = local_best_mv_evaluation;
+            result.best_move = local_best_mv;
+            result.evaluation = local_best_mv_evaluation;
+            result.pv = local_best_pv;
+            result.depth = current_depth;
+            
+            // Stable sort so equal evaluations keep the search order, which keeps the first line the same as the best move
+            root_lines.sort_by_key(|line| Reverse(line.evaluation));
+            root_lines.truncate(cmp::max(multi_pv, 1));
+            result.lines = root_lines;
             
-            if overall_best_mv_evaluation == i32::MAX {
+            if result.evaluation == i32::MAX {
                 break;
             }
             
             // Late move reduction
             if current_depth >= 3 {
-                ordered_legal_moves = late_move_reduction(order_moves_by_evaluation(move_evaluation), total_legal_moves, current_depth);
+                ordered_legal_moves = late_move_reduction(order_moves_by_evaluation(move_evaluation), total_legal_moves, current_depth, multi_pv);
             } else {
                 ordered_legal_moves = order_moves_by_evaluation(move_evaluation);
             }
@@ -107,22 +219,56 @@ pub fn pick_move(board_starting_position: String, bot_time: (u64, u64), bot_colo
         }
     }
     
-    eprintln!("Move picked: {:#?} with evaluation {}. Nodes searched: {} in {:?} at depth {}", overall_best_mv, overall_best_mv_evaluation, NODE_COUNT.load(std::sync::atomic::Ordering::Relaxed), start_time.elapsed(), current_depth);
-    eprintln!("Current mobility for white/black: {:?}", calculate_attack_mobility(&board, &Colour::White));
+    if result.best_move.is_none() && let Some(mv) = fallback_move {
+        result.best_move = Some(mv);
+        result.evaluation = 0;
+        result.pv.push(mv);
+        result.lines = vec![PvLine { evaluation: result.evaluation, pv: result.pv.clone() }];
+    }
+    
+    return result;
+}
 
-    return Ok((to_uci(overall_best_mv), overall_best_mv_evaluation));
+// Replaces a principal variation with a move followed by the principal variation of the position after it
+fn update_pv(pv: &mut ArrayVec<Move, MAX_PV_LENGTH>, mv: Move, child_pv: &ArrayVec<Move, MAX_PV_LENGTH>) {
+    pv.clear();
+    pv.push(mv);
+    for child_mv in child_pv.iter().take(MAX_PV_LENGTH - 1) {
+        pv.push(*child_mv);
+    }
 }
 
-fn minmax(current_board: &Board, depth: i32, is_bots_move: bool, mut alpha: i32, mut beta: i32, bot_colour: &Colour, start_time: Instant, max_time: Duration) -> i32 {
+fn minmax(current_board: &Board, depth: i32, mut alpha: i32, mut beta: i32, bot_colour: &Colour, control: &SearchControl, pv: &mut ArrayVec<Move, MAX_PV_LENGTH>) -> i32 {
+    
+    pv.clear();
+    
+    // The bot's move whenever it is its turn, since the null move also hands the turn over
+    let is_bots_move = current_board.turn == *bot_colour;
     
-    if start_time.elapsed() > max_time {
+    control.nodes.fetch_add(1, Ordering::Relaxed);
+    if control.time_up() {
         return 0;
     }
 
+    // The tablebases know the result of positions with few enough pieces, so there is no need to search them. They are only probed
+    // right after a capture or pawn move, which is how a position first comes into the tables and the only time the fifty move counter
+    // is 0, which the result they give assumes
+    let last_move = current_board.last_move;
+    let zeroing = last_move.capture.is_some() | (last_move.role == Some(Role::Pawn));
+    let few_enough_pieces = current_board.occupied.count_ones() as usize <= largest_table();
+    if zeroing && few_enough_pieces && (depth >= TB_PROBE_DEPTH) && let Some(evaluation) = tablebase_evaluation(current_board) {
+        match bot_colour {
+            Colour::White => return evaluation,
+            Colour::Black => return -evaluation,
+        }
+    }
+
     if depth == 0 {
-        return quiesce(current_board, bot_colour, is_bots_move, alpha, beta, start_time, max_time);
+        return quiesce(current_board, bot_colour, is_bots_move, alpha, beta, control);
     }
     
+    let mut child_pv: ArrayVec<Move, MAX_PV_LENGTH> = ArrayVec::new();
+    
     if is_bots_move {
 
         if current_board.is_checkmate(bot_colour) {
@@ -135,7 +281,7 @@ fn minmax(current_board: &Board, depth: i32, is_bots_move: bool, mut alpha: i32,
             let mut null_move_board: Board = current_board.clone();
             null_move_board.swap_turn();
             
-            let eval = minmax( &null_move_board, depth - 2, false, alpha, beta, bot_colour, start_time, max_time);
+            let eval = minmax(&null_move_board, depth - 2, alpha, beta, bot_colour, control, &mut child_pv);
             if eval >= beta {
                 return beta;
             }
@@ -148,7 +294,10 @@ fn minmax(current_board: &Board, depth: i32, is_bots_move: bool, mut alpha: i32,
             let mut new_board = current_board.clone();
             new_board.play_unsafe(mv);
 
-            let eval = minmax(&new_board, depth - 1, false, alpha, beta, bot_colour, start_time, max_time);
+            let eval = minmax(&new_board, depth - 1, alpha, beta, bot_colour, control, &mut child_pv);
+            if (eval > max_eval) | pv.is_empty() {
+                update_pv(pv, mv, &child_pv);
+            }
             max_eval = cmp::max(max_eval, eval);
 
             alpha = cmp::max(alpha, eval);
@@ -171,7 +320,10 @@ fn minmax(current_board: &Board, depth: i32, is_bots_move: bool, mut alpha: i32,
             let mut new_board = current_board.clone();
             new_board.play_unsafe(mv);
             
-            let eval = minmax(&new_board, depth - 1, true, alpha, beta, bot_colour, start_time, max_time);
+            let eval = minmax(&new_board, depth - 1, alpha, beta, bot_colour, control, &mut child_pv);
+            if (eval < min_eval) | pv.is_empty() {
+                update_pv(pv, mv, &child_pv);
+            }
             min_eval = cmp::min(min_eval, eval);
             beta = cmp::min(beta, eval);
 
@@ -185,9 +337,10 @@ fn minmax(current_board: &Board, depth: i32, is_bots_move: bool, mut alpha: i32,
 }
 
 // Quiescence search to only evaluate positions with no tactical move to prevent bad trades when max depth is reached
-fn quiesce(current_board: &Board, bot_colour: &Colour, is_bots_move: bool, mut alpha: i32, mut beta: i32, start_time: Instant, max_time: Duration) -> i32 {
+fn quiesce(current_board: &Board, bot_colour: &Colour, is_bots_move: bool, mut alpha: i32, mut beta: i32, control: &SearchControl) -> i32 {
 
-    if start_time.elapsed() > max_time {
+    control.nodes.fetch_add(1, Ordering::Relaxed);
+    if control.time_up() {
         return 0;
     }
 
@@ -207,7 +360,7 @@ fn quiesce(current_board: &Board, bot_colour: &Colour, is_bots_move: bool, mut a
             let mut new_board = current_board.clone();
             new_board.play_unsafe(*mv);
 
-            let score = quiesce(&new_board, bot_colour, false, alpha, beta, start_time, max_time);
+            let score = quiesce(&new_board, bot_colour, false, alpha, beta, control);
             best_value = cmp::max(best_value, score);
 
             alpha = cmp::max(alpha,best_value);
@@ -229,7 +382,7 @@ fn quiesce(current_board: &Board, bot_colour: &Colour, is_bots_move: bool, mut a
             let mut new_board = current_board.clone();
             new_board.play_unsafe(*mv);
 
-            let score = quiesce(&new_board, bot_colour, true, alpha, beta, start_time, max_time);
+            let score = quiesce(&new_board, bot_colour, true, alpha, beta, control);
             best_value = cmp::min(best_value, score);
 
             beta = cmp::min(beta,best_value);
@@ -242,10 +395,10 @@ fn quiesce(current_board: &Board, bot_colour: &Colour, is_bots_move: bool, mut a
     }
 }
 
-// Removes lower ranked moves from further searches. Only to be applied after a certain depth
-fn late_move_reduction(mut moves: ArrayVec<Move, 218>, total_legal_moves: i32, current_depth: i32) -> ArrayVec<Move, 218> {
+// Removes lower ranked moves from further searches. Only to be applied after a certain depth. Never removes the multi_pv best moves
+fn late_move_reduction(mut moves: ArrayVec<Move, 218>, total_legal_moves: i32, current_depth: i32, multi_pv: usize) -> ArrayVec<Move, 218> {
     
-    let reduce_to: usize = (total_legal_moves - (current_depth.pow(3)).isqrt()) as usize;
+    let reduce_to: usize = cmp::max((total_legal_moves - (current_depth.pow(3)).isqrt()) as usize, multi_pv);
     
     if reduce_to > 0 {
         moves.truncate(reduce_to);
@@ -262,27 +415,58 @@ fn order_moves_by_evaluation(mut moves: ArrayVec<(Move, i32), 218>) -> ArrayVec<
     return moves.into_iter().map(|(k,_)| k.clone()).collect();
 }
 
-// Determines how long to search for
-fn search_time((base, increment): (u64, u64)) -> Duration {
-
-    // Lichess determines the time classification based off of the formula (base + 40 * increment). Ultrabullet <= 29s, bullet <= 179, blitz <= 479, rapid <= 1499, classical >= 1500
-    let lichess_time_control = base + (40 * increment);
-    if lichess_time_control <= 29 {
-        return Duration::from_millis(250);
-    } else if lichess_time_control <= 179 {
-        return Duration::from_millis(base * 10 + increment * 1500);
-    } else if lichess_time_control <= 479 {
-        return Duration::from_millis(base * 10 + increment * 1500);
-    } else if lichess_time_control <= 1499 {
-        return Duration::from_millis(base * 10 + increment * 1500);
+// Determines how long to search for from the time left and increment in seconds
+pub fn search_time((base, increment): (u64, u64)) -> Duration {
+    return search_time_ms(base * 1000, increment * 1000);
+}
+
+// Determines how long to search for from the time left and increment in milliseconds. A move never takes more than a third of the time
+// left, so a short clock with a long increment can't flag
+pub fn search_time_ms(time_ms: u64, increment_ms: u64) -> Duration {
+
+    // Lichess determines the time classification based off of the formula (base + 40 * increment), with ultrabullet under 30 seconds
+    let lichess_time_control_ms = time_ms + (40 * increment_ms);
+    let budget_ms = if lichess_time_control_ms < 30_000 {
+        250
     } else {
-        return Duration::from_millis(base * 10 + increment * 1500);
-    }
+        (time_ms / 100) + (increment_ms * 3 / 2)
+    };
+    return Duration::from_millis(budget_ms.min(time_ms / MAX_TIME_FRACTION));
 }
 
-// Python module definition
-#[pymodule]
-fn chesslibrary(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(pick_move, m)?)?;
-    Ok(())
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_fit_in_the_time_left() {
+        assert_eq!(search_time_ms(1000, 2000), Duration::from_millis(333));
+        assert_eq!(search_time_ms(600, 0), Duration::from_millis(200));
+        assert_eq!(search_time_ms(20_000, 0), Duration::from_millis(250));
+        assert_eq!(search_time_ms(300_000, 2000), Duration::from_millis(6000));
+        assert_eq!(search_time((300, 2)), search_time_ms(300_000, 2000));
+    }
+    #[test]
+    fn multi_pv_gives_distinct_lines_best_first() {
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3".to_string());
+        let result = search(&board, &SearchControl::with_depth_limit(2), 4);
+        assert_eq!(result.lines.len(), 4);
+        for (i, line) in result.lines.iter().enumerate() {
+            assert!(!line.pv.is_empty());
+            assert!(result.lines[..i].iter().all(|other| other.pv[0] != line.pv[0]), "{:?} comes up twice", line.pv[0]);
+            if i > 0 {
+                assert!(result.lines[i - 1].evaluation >= line.evaluation);
+            }
+        }
+        assert_eq!(result.best_move, Some(result.lines[0].pv[0]));
+        assert_eq!(result.evaluation, result.lines[0].evaluation);
+    }
+
+    #[test]
+    fn multi_pv_is_limited_by_the_legal_moves() {
+        // Only two king moves
+        let board = Board::from_fen("7k/8/8/8/8/8/1r6/K7 w - - 0 1".to_string());
+        let result = search(&board, &SearchControl::with_depth_limit(2), 5);
+        assert_eq!(result.lines.len(), 1);
+    }
+}
@@ -0,0 +1,1393 @@
+use crate::board::Board;
+use crate::colour::Colour;
+use crate::movegen::get_legal_moves;
+use crate::mv::Move;
+use crate::role::{Role, ALL_ROLES};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+// Reads Syzygy endgame tablebases. WDL files (.rtbw) give the result of a position with best play and DTZ files (.rtbz) give the number
+// of plies until the next capture or pawn move that keeps that result. The layout of the files and the way positions are turned into
+// indices follow the probing code the tables were released with. Files are read into memory the first time they are probed
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+const MAX_PIECES: usize = 7;
+
+// Evaluation given to a position the tablebases say is won. Far above any normal evaluation but below mate, which is i32::MAX
+pub const TB_WIN: i32 = 1_000_000_000;
+
+// Flags stored for each table in a file
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+// Every table found, by material such as "KRPvKR" with the pieces in king, queen, rook, bishop, knight, pawn order
+static TABLEBASES: Lazy<RwLock<HashMap<String, Arc<Table>>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+// Most pieces in any table found, or 0 with no tables
+static LARGEST_TABLE: AtomicUsize = AtomicUsize::new(0);
+
+static INDEX_TABLES: Lazy<IndexTables> = Lazy::new(IndexTables::new);
+
+// Result of a position from the side to moves point of view. Cursed wins and blessed losses are wins and losses that the fifty move rule
+// turns into draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            ..=-2 => return Wdl::Loss,
+            -1 => return Wdl::BlessedLoss,
+            0 => return Wdl::Draw,
+            1 => return Wdl::CursedWin,
+            _ => return Wdl::Win,
+        }
+    }
+
+    pub fn opposite(&self) -> Wdl {
+        return Wdl::from_value(-(*self as i32));
+    }
+}
+
+// A table found on disk. What is known from the file name is worked out straight away and the files are only read once needed
+struct Table {
+    wdl_path: PathBuf,
+    dtz_path: Option<PathBuf>,
+    piece_count: usize,
+    has_pawns: bool,
+    // Some colour has exactly one of a piece other than the king
+    has_unique_pieces: bool,
+    // Pawns of the leading colour, which is the one with fewer pawns, then the other colours
+    pawn_count: [usize; 2],
+    // Both sides have the same pieces, so only white to move is stored
+    symmetric: bool,
+    wdl: OnceLock<Option<TableFile>>,
+    dtz: OnceLock<Option<TableFile>>,
+}
+
+// The contents of a .rtbw or .rtbz file with the positions of everything needed to decompress it
+struct TableFile {
+    bytes: Vec<u8>,
+    // By side to move then by file of the leading pawn, or just the first file without pawns
+    pairs: [[PairsData; 4]; 2],
+    // Start of the DTZ value maps
+    map: usize,
+}
+
+// Everything needed to find and decompress the value of a position in one table. Offsets are into the files bytes
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    max_sym_len: u8,
+    min_sym_len: u8,
+    num_blocks: usize,
+    block_size: usize,
+    span: usize,
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    block_length_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    group_len: [usize; MAX_PIECES + 1],
+    map_idx: [usize; 4],
+}
+
+// Tables used to turn piece placements into indices. Squares here are a1 = 0 up to h8 = 63
+struct IndexTables {
+    map_pawns: [usize; 64],
+    map_b1h1h7: [usize; 64],
+    map_a1d1d4: [usize; 64],
+    map_kk: [[usize; 64]; 10],
+    binomial: [[u64; 64]; MAX_PIECES],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+}
+
+impl IndexTables {
+    fn new() -> IndexTables {
+        let mut tables = IndexTables {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+        };
+
+        // Squares below the a1-h8 diagonal to 0..27
+        let mut code = 0;
+        for square in 0..64 {
+            if off_a1h8(square) < 0 {
+                tables.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        // Squares in the a1-d1-d4 triangle to 0..9, with the ones on the diagonal last
+        let mut diagonal = Vec::new();
+        code = 0;
+        for square in 0..28 {
+            if (off_a1h8(square) < 0) && (file_of(square) <= 3) {
+                tables.map_a1d1d4[square] = code;
+                code += 1;
+            } else if (off_a1h8(square) == 0) && (file_of(square) <= 3) {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            tables.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // The 462 legal ways to place two kings with the first in the a1-d1-d4 triangle. With the first king on the diagonal the second
+        // can't be above it, and positions with both on the diagonal come last
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for first in 0..28 {
+                if (tables.map_a1d1d4[first] != idx) || ((idx == 0) && (first != 1)) {
+                    continue;
+                }
+                for second in 0..64 {
+                    if (distance(first, second) <= 1) || ((off_a1h8(first) == 0) && (off_a1h8(second) > 0)) {
+                        continue;
+                    } else if (off_a1h8(first) == 0) && (off_a1h8(second) == 0) {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        tables.map_kk[idx][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            tables.map_kk[idx][second] = code;
+            code += 1;
+        }
+
+        // Ways to choose k things from n
+        tables.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                tables.binomial[k][n] = (if k > 0 { tables.binomial[k - 1][n - 1] } else { 0 }) + (if k < n { tables.binomial[k][n - 1] } else { 0 });
+            }
+        }
+
+        // Pawn squares a2 to h7 to 0..47, where the pawn with the highest value is the leading one, nearest the edge and lowest down
+        let mut available_squares = 48;
+        for lead_pawns in 1..MAX_PIECES {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        tables.map_pawns[square] = available_squares - 1;
+                        tables.map_pawns[square ^ 7] = available_squares - 2;
+                        available_squares -= 2;
+                    }
+                    tables.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += tables.binomial[lead_pawns - 1][tables.map_pawns[square]];
+                }
+                tables.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        return tables;
+    }
+}
+
+fn file_of(square: usize) -> usize {
+    return square & 7;
+}
+
+fn rank_of(square: usize) -> usize {
+    return square >> 3;
+}
+
+fn off_a1h8(square: usize) -> i32 {
+    return rank_of(square) as i32 - file_of(square) as i32;
+}
+
+fn distance(first: usize, second: usize) -> usize {
+    return file_of(first).abs_diff(file_of(second)).max(rank_of(first).abs_diff(rank_of(second)));
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> usize {
+    return u16::from_le_bytes([bytes[offset], bytes[offset + 1]]) as usize;
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> usize {
+    return u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]) as usize;
+}
+
+// Compressed data is big endian. Reading past the end of the file gives zeros, which only happens when refilling the bit buffer after
+// the last symbol of the last block
+fn read_u32_be(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = 0;
+    for i in 0..4 {
+        value = (value << 8) | *bytes.get(offset + i).unwrap_or(&0) as u64;
+    }
+    return value;
+}
+
+// Finds every table in the directories, separated like the PATH environment variable. Replaces any tables found before, and an empty
+// path or "<empty>" just clears them. Returns the number of tables found
+pub fn init_tablebases(paths: &str) -> usize {
+    let mut wdl_paths: HashMap<String, PathBuf> = HashMap::new();
+    let mut dtz_paths: HashMap<String, PathBuf> = HashMap::new();
+
+    if !paths.is_empty() && (paths != "<empty>") {
+        for directory in env::split_paths(paths) {
+            let Ok(entries) = fs::read_dir(&directory) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let (Some(name), Some(extension)) = (path.file_stem().and_then(|name| name.to_str()), path.extension().and_then(|extension| extension.to_str())) else {
+                    continue;
+                };
+                match extension {
+                    "rtbw" => wdl_paths.insert(name.to_string(), path.clone()),
+                    "rtbz" => dtz_paths.insert(name.to_string(), path.clone()),
+                    _ => None,
+                };
+            }
+        }
+    }
+
+    let mut tables: HashMap<String, Arc<Table>> = HashMap::new();
+    let mut largest_table = 0;
+    for (name, wdl_path) in wdl_paths {
+        let Some(table) = Table::new(&name, wdl_path, dtz_paths.get(&name).cloned()) else {
+            continue;
+        };
+        largest_table = largest_table.max(table.piece_count);
+        tables.insert(name, Arc::new(table));
+    }
+
+    let count = tables.len();
+    *TABLEBASES.write().unwrap() = tables;
+    LARGEST_TABLE.store(largest_table, Ordering::Relaxed);
+    return count;
+}
+
+// Most pieces in a position that can be probed, or 0 with no tables loaded
+pub fn largest_table() -> usize {
+    return LARGEST_TABLE.load(Ordering::Relaxed);
+}
+
+// Tables only cover positions without castling rights and with few enough pieces
+pub fn can_probe(board: &Board) -> bool {
+    let castling = board.has_castling_rights(Colour::White) | board.has_castling_rights(Colour::Black);
+    return !castling && (board.occupied.count_ones() as usize <= largest_table());
+}
+
+impl Table {
+    // Works out the table layout from a name like "KRPvKR". White is the side before the v
+    fn new(name: &str, wdl_path: PathBuf, dtz_path: Option<PathBuf>) -> Option<Table> {
+        let (white, black) = name.split_once('v')?;
+        if !white.starts_with('K') || !black.starts_with('K') || !name.chars().all(|piece| "KQRBNPv".contains(piece)) {
+            return None;
+        }
+        let piece_count = white.len() + black.len();
+        if piece_count > MAX_PIECES {
+            return None;
+        }
+
+        let count = |side: &str, piece: char| side.chars().filter(|other| *other == piece).count();
+        let has_unique_pieces = [white, black].iter().any(|side| "QRBNP".chars().any(|piece| count(side, piece) == 1));
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+
+        // The leading colour is the one with fewer pawns, since that compresses better
+        let white_leads = (black_pawns == 0) || ((white_pawns > 0) && (black_pawns >= white_pawns));
+
+        return Some(Table {
+            wdl_path,
+            dtz_path,
+            piece_count,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            pawn_count: if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] },
+            symmetric: white == black,
+            wdl: OnceLock::new(),
+            dtz: OnceLock::new(),
+        });
+    }
+
+    fn file(&self, is_dtz: bool) -> Option<&TableFile> {
+        if is_dtz {
+            return self.dtz.get_or_init(|| self.dtz_path.as_ref().and_then(|path| self.read(path, true))).as_ref();
+        }
+        return self.wdl.get_or_init(|| self.read(&self.wdl_path, false)).as_ref();
+    }
+
+    fn read(&self, path: &PathBuf, is_dtz: bool) -> Option<TableFile> {
+        let bytes = fs::read(path).ok()?;
+        let magic = if is_dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if (bytes.len() < 5) || (bytes[..4] != magic) {
+            return None;
+        }
+        return self.parse(bytes, is_dtz);
+    }
+
+    // Reads the header of a file, which is laid out as the pieces and group order of each table, the sizes of each table, the DTZ value
+    // maps, the sparse indices, the block lengths, and then the compressed blocks
+    fn parse(&self, bytes: Vec<u8>, is_dtz: bool) -> Option<TableFile> {
+        let index_tables = &*INDEX_TABLES;
+        let flags = bytes[4];
+        if ((flags & 2 != 0) != self.has_pawns) || ((flags & 1 != 0) == self.symmetric) {
+            return None;
+        }
+
+        let sides = if !is_dtz && !self.symmetric { 2 } else { 1 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let pawns_on_both_sides = self.has_pawns && (self.pawn_count[1] > 0);
+        let mut pairs: [[PairsData; 4]; 2] = Default::default();
+        let mut offset = 5;
+
+        for file in 0..files {
+            let second = if pawns_on_both_sides { *bytes.get(offset + 1)? } else { 0xFF };
+            let first = *bytes.get(offset)?;
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            offset += 1 + pawns_on_both_sides as usize;
+
+            for k in 0..self.piece_count {
+                let piece = *bytes.get(offset)?;
+                for (side, side_pairs) in pairs.iter_mut().enumerate().take(sides) {
+                    side_pairs[file].pieces[k] = if side == 1 { piece >> 4 } else { piece & 0xF };
+                }
+                offset += 1;
+            }
+
+            for (side, side_pairs) in pairs.iter_mut().enumerate().take(sides) {
+                self.set_groups(&mut side_pairs[file], order[side], file, index_tables);
+            }
+        }
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut().take(sides) {
+                offset = set_sizes(&mut side_pairs[file], &bytes, offset)?;
+            }
+        }
+
+        let map = offset;
+        if is_dtz {
+            for file in 0..files {
+                let pairs = &mut pairs[0][file];
+                if pairs.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                if pairs.flags & FLAG_WIDE != 0 {
+                    offset += offset & 1;
+                    for i in 0..4 {
+                        pairs.map_idx[i] = (offset - map) / 2 + 1;
+                        offset += 2 * read_u16(&bytes, offset) + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        pairs.map_idx[i] = offset - map + 1;
+                        offset += *bytes.get(offset)? as usize + 1;
+                    }
+                }
+            }
+            offset += offset & 1;
+        }
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut().take(sides) {
+                side_pairs[file].sparse_index = offset;
+                offset += side_pairs[file].sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut().take(sides) {
+                side_pairs[file].block_length = offset;
+                offset += side_pairs[file].block_length_size * 2;
+            }
+        }
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut().take(sides) {
+                offset = (offset + 0x3F) & !0x3F;
+                side_pairs[file].data = offset;
+                offset += side_pairs[file].num_blocks * side_pairs[file].block_size;
+            }
+        }
+        if offset > bytes.len() {
+            return None;
+        }
+
+        return Some(TableFile { bytes, pairs, map });
+    }
+
+    // Splits the pieces into groups that are encoded together. Usually a group is pieces of the same type and colour, but without pawns
+    // the first group is either three unique pieces or the two kings. The order says in which order the groups make up the index
+    fn set_groups(&self, pairs: &mut PairsData, order: [u8; 2], file: usize, index_tables: &IndexTables) {
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns { 0 } else if self.has_unique_pieces { 3 } else { 2 };
+        pairs.group_len[0] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if (first_len > 0) || (pairs.pieces[i] == pairs.pieces[i - 1]) {
+                pairs.group_len[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        pairs.group_len[n] = 0;
+
+        let pawns_on_both_sides = self.has_pawns && (self.pawn_count[1] > 0);
+        let mut next = if pawns_on_both_sides { 2 } else { 1 };
+        let mut free_squares = 64 - pairs.group_len[0] - if pawns_on_both_sides { pairs.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+
+        let mut k = 0;
+        while (next < n) || (k == order[0] as usize) || (k == order[1] as usize) {
+            if k == order[0] as usize {
+                pairs.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    index_tables.lead_pawns_size[pairs.group_len[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                pairs.group_idx[1] = idx;
+                idx *= index_tables.binomial[pairs.group_len[1]][48 - pairs.group_len[0]];
+            } else {
+                pairs.group_idx[next] = idx;
+                idx *= index_tables.binomial[pairs.group_len[next]][free_squares];
+                free_squares -= pairs.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        pairs.group_idx[n] = idx;
+    }
+}
+
+// Reads the block and Huffman code sizes of one table and returns the offset after them
+fn set_sizes(pairs: &mut PairsData, bytes: &[u8], mut offset: usize) -> Option<usize> {
+    pairs.flags = *bytes.get(offset)?;
+    offset += 1;
+
+    // Every position has the same value, which is stored straight away
+    if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+        pairs.min_sym_len = *bytes.get(offset)?;
+        return Some(offset + 1);
+    }
+
+    let groups = pairs.group_len.iter().position(|len| *len == 0).unwrap_or(MAX_PIECES);
+    let table_size = pairs.group_idx[groups] as usize;
+
+    if offset + 9 > bytes.len() {
+        return None;
+    }
+    pairs.block_size = 1 << bytes[offset];
+    pairs.span = 1 << bytes[offset + 1];
+    pairs.sparse_index_size = table_size.div_ceil(pairs.span);
+    let padding = bytes[offset + 2] as usize;
+    pairs.num_blocks = read_u32(bytes, offset + 3);
+    pairs.block_length_size = pairs.num_blocks + padding;
+    pairs.max_sym_len = bytes[offset + 7];
+    pairs.min_sym_len = bytes[offset + 8];
+    offset += 9;
+    if pairs.max_sym_len < pairs.min_sym_len {
+        return None;
+    }
+
+    // Canonical Huffman codes, where longer codes have lower values. base64 holds the lowest code of each length padded out to 64 bits,
+    // so the length of the code at the start of a bit buffer is the first length whose base64 is at or below the buffer
+    pairs.lowest_sym = offset;
+    let lengths = (pairs.max_sym_len - pairs.min_sym_len + 1) as usize;
+    if offset + lengths * 2 + 2 > bytes.len() {
+        return None;
+    }
+    pairs.base64 = vec![0; lengths];
+    for i in (0..lengths - 1).rev() {
+        let lowest = read_u16(bytes, offset + i * 2) as u64;
+        let next_lowest = read_u16(bytes, offset + (i + 1) * 2) as u64;
+        pairs.base64[i] = (pairs.base64[i + 1] + lowest).wrapping_sub(next_lowest) / 2;
+    }
+    for (i, base) in pairs.base64.iter_mut().enumerate() {
+        let shift = 64 - i as u32 - pairs.min_sym_len as u32;
+        *base = base.checked_shl(shift).unwrap_or(0);
+    }
+    offset += lengths * 2;
+
+    // Symbols are compressed with recursive pairing, so each symbol is either a value or a pair of other symbols
+    let symbols = read_u16(bytes, offset);
+    offset += 2;
+    pairs.btree = offset;
+    if offset + symbols * 3 > bytes.len() {
+        return None;
+    }
+    pairs.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for symbol in 0..symbols {
+        if !visited[symbol] {
+            pairs.symlen[symbol] = set_symlen(pairs, bytes, symbol, &mut visited);
+        }
+    }
+
+    return Some(offset + symbols * 3 + (symbols & 1));
+}
+
+// Number of values, less one, that a symbol expands to
+fn set_symlen(pairs: &mut PairsData, bytes: &[u8], symbol: usize, visited: &mut Vec<bool>) -> u8 {
+    visited[symbol] = true;
+    let right = right_symbol(bytes, pairs.btree, symbol);
+    if right == 0xFFF {
+        return 0;
+    }
+    let left = left_symbol(bytes, pairs.btree, symbol);
+    if (left >= pairs.symlen.len()) || (right >= pairs.symlen.len()) {
+        return 0;
+    }
+    if !visited[left] {
+        pairs.symlen[left] = set_symlen(pairs, bytes, left, visited);
+    }
+    if !visited[right] {
+        pairs.symlen[right] = set_symlen(pairs, bytes, right, visited);
+    }
+    return pairs.symlen[left].wrapping_add(pairs.symlen[right]).wrapping_add(1);
+}
+
+// Each symbol in the tree is 3 bytes holding two 12 bit symbols. A leaf stores its value as the left symbol
+fn left_symbol(bytes: &[u8], btree: usize, symbol: usize) -> usize {
+    let offset = btree + symbol * 3;
+    return (((bytes[offset + 1] & 0xF) as usize) << 8) | bytes[offset] as usize;
+}
+
+fn right_symbol(bytes: &[u8], btree: usize, symbol: usize) -> usize {
+    let offset = btree + symbol * 3;
+    return ((bytes[offset + 2] as usize) << 4) | (bytes[offset + 1] >> 4) as usize;
+}
+
+// Gets the value stored at an index. The sparse index points close to the block holding it, the block lengths find the exact block,
+// then symbols are read from the start of the block until the one covering the index, which is expanded down to a single value
+fn decompress_pairs(pairs: &PairsData, bytes: &[u8], idx: u64) -> i32 {
+    if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+        return pairs.min_sym_len as i32;
+    }
+
+    let k = (idx / pairs.span as u64) as usize;
+    let mut block = read_u32(bytes, pairs.sparse_index + k * 6);
+    let mut offset = read_u16(bytes, pairs.sparse_index + k * 6 + 4) as i64;
+    offset += (idx % pairs.span as u64) as i64 - (pairs.span / 2) as i64;
+
+    let block_length = |block: usize| read_u16(bytes, pairs.block_length + block * 2) as i64;
+    while offset < 0 {
+        block -= 1;
+        offset += block_length(block) + 1;
+    }
+    while offset > block_length(block) {
+        offset -= block_length(block) + 1;
+        block += 1;
+    }
+
+    let mut pointer = pairs.data + block * pairs.block_size;
+    let mut buffer = (read_u32_be(bytes, pointer) << 32) | read_u32_be(bytes, pointer + 4);
+    pointer += 8;
+    let mut buffer_size = 64;
+    let min_sym_len = pairs.min_sym_len as usize;
+
+    let mut symbol;
+    loop {
+        let mut len = 0;
+        while (len + 1 < pairs.base64.len()) && (buffer < pairs.base64[len]) {
+            len += 1;
+        }
+        symbol = ((buffer - pairs.base64[len]) >> (64 - len - min_sym_len)) as usize;
+        symbol += read_u16(bytes, pairs.lowest_sym + len * 2);
+        if symbol >= pairs.symlen.len() {
+            return 0;
+        }
+
+        if offset < pairs.symlen[symbol] as i64 + 1 {
+            break;
+        }
+        offset -= pairs.symlen[symbol] as i64 + 1;
+        len += min_sym_len;
+        buffer <<= len;
+        buffer_size -= len;
+        if buffer_size <= 32 {
+            buffer_size += 32;
+            buffer |= read_u32_be(bytes, pointer) << (64 - buffer_size);
+            pointer += 4;
+        }
+    }
+
+    // Symbols pair up adjacent values, so the value is on the left if the offset is within the left symbol
+    while pairs.symlen[symbol] != 0 {
+        let left = left_symbol(bytes, pairs.btree, symbol);
+        if offset < pairs.symlen[left] as i64 + 1 {
+            symbol = left;
+        } else {
+            offset -= pairs.symlen[left] as i64 + 1;
+            symbol = right_symbol(bytes, pairs.btree, symbol);
+        }
+    }
+
+    return left_symbol(bytes, pairs.btree, symbol) as i32;
+}
+
+enum TableValue {
+    Value(i32),
+    // DTZ tables only store one side to move, and this position has the other
+    ChangeSideToMove,
+}
+
+// Every piece on the board as a square from a1 = 0 and a piece code, which is 1 to 6 for pawn to king plus 8 for black
+fn board_pieces(board: &Board) -> Vec<(usize, u8)> {
+    let mut pieces = Vec::new();
+    for (code, role) in ALL_ROLES.iter().enumerate() {
+        for (colour_code, colour) in [(0, Colour::White), (8, Colour::Black)] {
+            for square in (*board.role.get(*role) & *board.colour.get(colour)).get_component_bitboards() {
+                pieces.push((square.trailing_zeros() as usize ^ 7, code as u8 + 1 + colour_code));
+            }
+        }
+    }
+    pieces.sort();
+    return pieces;
+}
+
+// The pieces of a colour written the way table names are
+fn material_name(board: &Board, colour: Colour) -> String {
+    let mut name = String::new();
+    for (role, piece) in [(Role::King, 'K'), (Role::Queen, 'Q'), (Role::Rook, 'R'), (Role::Bishop, 'B'), (Role::Knight, 'N'), (Role::Pawn, 'P')] {
+        let count = (*board.role.get(role) & *board.colour.get(colour)).count_ones();
+        for _ in 0..count {
+            name.push(piece);
+        }
+    }
+    return name;
+}
+
+// Looks the position up in its WDL or DTZ table. The wdl is only used to decode DTZ values
+fn probe_table(board: &Board, is_dtz: bool, wdl: Wdl) -> Option<TableValue> {
+    if board.occupied.count_ones() == 2 {
+        return Some(TableValue::Value(0));
+    }
+
+    // Tables are stored with the stronger side as white. If black is the stronger side, or both sides are the same and it's blacks
+    // move, the colours are swapped and the board flipped
+    let white = material_name(board, Colour::White);
+    let black = material_name(board, Colour::Black);
+    let tablebases = TABLEBASES.read().unwrap();
+    let (table, flip) = match tablebases.get(&format!("{}v{}", white, black)) {
+        Some(table) => (table.clone(), table.symmetric && (board.turn == Colour::Black)),
+        None => (tablebases.get(&format!("{}v{}", black, white))?.clone(), true),
+    };
+    drop(tablebases);
+
+    let file = table.file(is_dtz)?;
+    let side_to_move = flip as usize ^ (board.turn == Colour::Black) as usize;
+    return Some(probe_file(&table, file, is_dtz, &board_pieces(board), side_to_move, flip, wdl));
+}
+
+// Turns the position into an index into the table and decompresses its value
+fn probe_file(table: &Table, file: &TableFile, is_dtz: bool, pieces: &[(usize, u8)], side_to_move: usize, flip: bool, wdl: Wdl) -> TableValue {
+    let Some((pairs, idx)) = position_index(table, &file.pairs, is_dtz, pieces, side_to_move, flip) else {
+        return TableValue::ChangeSideToMove;
+    };
+
+    let value = decompress_pairs(pairs, &file.bytes, idx);
+    if !is_dtz {
+        return TableValue::Value(value - 2);
+    }
+    return TableValue::Value(map_dtz(file, pairs, value, wdl));
+}
+
+// Finds the table the position is in and its index in that table, or None if it's a DTZ table that only stores the other side to move
+fn position_index<'a>(table: &Table, file_pairs: &'a [[PairsData; 4]; 2], is_dtz: bool, pieces: &[(usize, u8)], side_to_move: usize, flip: bool) -> Option<(&'a PairsData, u64)> {
+    let index_tables = &*INDEX_TABLES;
+    let flip_colour = if flip { 8 } else { 0 };
+    let flip_squares = if flip { 56 } else { 0 };
+
+    let mut squares = [0usize; MAX_PIECES];
+    let mut codes = [0u8; MAX_PIECES];
+    let mut size = 0;
+    let mut lead_pawns = 0;
+    let mut tb_file = 0;
+
+    // With pawns there is a table for each file the leading pawn can be on after mirroring to the queenside. The leading pawn is the one
+    // nearest the edge and lowest down
+    let lead_pawn = file_pairs[0][0].pieces[0] ^ flip_colour;
+    if table.has_pawns {
+        for &(square, code) in pieces {
+            if code == lead_pawn {
+                squares[size] = square ^ flip_squares;
+                size += 1;
+            }
+        }
+        lead_pawns = size;
+        let mut leading = 0;
+        for i in 1..lead_pawns {
+            if index_tables.map_pawns[squares[i]] > index_tables.map_pawns[squares[leading]] {
+                leading = i;
+            }
+        }
+        squares.swap(0, leading);
+        tb_file = file_of(squares[0]).min(7 - file_of(squares[0]));
+    }
+
+    if is_dtz {
+        let flags = file_pairs[0][tb_file].flags;
+        // Symmetric tables without pawns store both sides to move in the one table
+        let both_sides_stored = table.symmetric && !table.has_pawns;
+        if ((flags & FLAG_STM) as usize != side_to_move) && !both_sides_stored {
+            return None;
+        }
+    }
+
+    for &(square, code) in pieces {
+        if table.has_pawns && (code == lead_pawn) {
+            continue;
+        }
+        squares[size] = square ^ flip_squares;
+        codes[size] = code ^ flip_colour;
+        size += 1;
+    }
+
+    let pairs = &file_pairs[if is_dtz { 0 } else { side_to_move }][tb_file];
+
+    // Put the pieces in the order the table stores them in
+    for i in lead_pawns..size.saturating_sub(1) {
+        for j in i + 1..size {
+            if pairs.pieces[i] == codes[j] {
+                codes.swap(i, j);
+                squares.swap(i, j);
+                break;
+            }
+        }
+    }
+
+    // Mirror so the leading piece is on files a to d
+    if file_of(squares[0]) > 3 {
+        for square in squares.iter_mut().take(size) {
+            *square ^= 7;
+        }
+    }
+
+    let mut idx: u64;
+    if table.has_pawns {
+        idx = index_tables.lead_pawn_idx[lead_pawns][squares[0]];
+        squares[1..lead_pawns].sort_by_key(|square| index_tables.map_pawns[*square]);
+        for (i, square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+            idx += index_tables.binomial[i][index_tables.map_pawns[*square]];
+        }
+    } else {
+        // Without pawns the board can also be flipped so the leading piece is on ranks 1 to 4, then mirrored in the a1-h8 diagonal so the
+        // first leading piece off the diagonal is below it
+        if rank_of(squares[0]) > 3 {
+            for square in squares.iter_mut().take(size) {
+                *square ^= 56;
+            }
+        }
+        for i in 0..pairs.group_len[0] {
+            if off_a1h8(squares[i]) == 0 {
+                continue;
+            }
+            if off_a1h8(squares[i]) > 0 {
+                for square in squares.iter_mut().take(size).skip(i) {
+                    *square = ((*square >> 3) | (*square << 3)) & 63;
+                }
+            }
+            break;
+        }
+
+        if table.has_unique_pieces {
+            // The first three pieces are encoded together, with the first in the a1-d1-d4 triangle and the squares of the others
+            // shifted down past the pieces before them
+            let adjust1 = (squares[1] > squares[0]) as u64;
+            let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+            let (second, third) = (squares[1] as u64, squares[2] as u64);
+            idx = if off_a1h8(squares[0]) != 0 {
+                (index_tables.map_a1d1d4[squares[0]] as u64 * 63 + (second - adjust1)) * 62 + third - adjust2
+            } else if off_a1h8(squares[1]) != 0 {
+                (6 * 63 + rank_of(squares[0]) as u64 * 28 + index_tables.map_b1h1h7[squares[1]] as u64) * 62 + third - adjust2
+            } else if off_a1h8(squares[2]) != 0 {
+                6 * 63 * 62 + 4 * 28 * 62 + rank_of(squares[0]) as u64 * 7 * 28 + (rank_of(squares[1]) as u64 - adjust1) * 28 + index_tables.map_b1h1h7[squares[2]] as u64
+            } else {
+                6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank_of(squares[0]) as u64 * 7 * 6 + (rank_of(squares[1]) as u64 - adjust1) * 6 + (rank_of(squares[2]) as u64 - adjust2)
+            };
+        } else {
+            idx = index_tables.map_kk[index_tables.map_a1d1d4[squares[0]]][squares[1]] as u64;
+        }
+    }
+
+    // The rest of the groups, each as a combination of squares with the squares of the groups before taken out
+    idx *= pairs.group_idx[0];
+    let mut group_start = pairs.group_len[0];
+    let mut remaining_pawns = table.has_pawns && (table.pawn_count[1] > 0);
+    let mut next = 1;
+    while pairs.group_len[next] != 0 {
+        let len = pairs.group_len[next];
+        squares[group_start..group_start + len].sort();
+        let mut n = 0;
+        for i in 0..len {
+            let square = squares[group_start + i];
+            let adjust = squares[..group_start].iter().filter(|other| square > **other).count();
+            n += index_tables.binomial[i + 1][square - adjust - if remaining_pawns { 8 } else { 0 }];
+        }
+        remaining_pawns = false;
+        idx += n * pairs.group_idx[next];
+        group_start += len;
+        next += 1;
+    }
+
+    return Some((pairs, idx));
+}
+
+// DTZ values are stored by how common they are, so they are mapped back to distances, then turned into plies if they were stored in moves
+fn map_dtz(file: &TableFile, pairs: &PairsData, mut value: i32, wdl: Wdl) -> i32 {
+    const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+    let map_idx = pairs.map_idx[WDL_MAP[(wdl as i32 + 2) as usize]];
+
+    if pairs.flags & FLAG_MAPPED != 0 {
+        if pairs.flags & FLAG_WIDE != 0 {
+            value = read_u16(&file.bytes, file.map + 2 * (map_idx + value as usize)) as i32;
+        } else {
+            value = file.bytes[file.map + map_idx + value as usize] as i32;
+        }
+    }
+
+    let in_plies = match wdl {
+        Wdl::Win => pairs.flags & FLAG_WIN_PLIES != 0,
+        Wdl::Loss => pairs.flags & FLAG_LOSS_PLIES != 0,
+        _ => false,
+    };
+    if !in_plies {
+        value *= 2;
+    }
+    return value + 1;
+}
+
+// The tables don't store the right value when the side to move has a capture (or for DTZ, a pawn move) that wins, since the search
+// will find it anyway. So those moves are looked at first, and the best of them and the table is the real result. Also says if the best
+// move is one of those moves, because then the stored DTZ can't be trusted
+fn search_zeroing_moves(board: &Board, check_pawn_moves: bool) -> Option<(Wdl, bool)> {
+    let legal_moves = get_legal_moves(board);
+    let mut best = Wdl::Loss;
+    let mut move_count = 0;
+
+    for mv in legal_moves.iter() {
+        if mv.capture.is_none() && (!check_pawn_moves || (mv.role != Some(Role::Pawn))) {
+            continue;
+        }
+        move_count += 1;
+
+        let mut new_board = board.clone();
+        new_board.play_unsafe(*mv);
+        let (value, _) = search_zeroing_moves(&new_board, false)?;
+        let value = value.opposite();
+
+        if value > best {
+            best = value;
+            if value >= Wdl::Win {
+                return Some((value, true));
+            }
+        }
+    }
+
+    // When every legal move has been searched the table isn't needed, and could be wrong anyway
+    let no_more_moves = (move_count > 0) && (move_count == legal_moves.len());
+    let value = if no_more_moves {
+        best
+    } else {
+        match probe_table(board, false, Wdl::Draw)? {
+            TableValue::Value(value) => Wdl::from_value(value),
+            TableValue::ChangeSideToMove => return None,
+        }
+    };
+
+    if best >= value {
+        return Some((best, (best > Wdl::Draw) || no_more_moves));
+    }
+    return Some((value, false));
+}
+
+// Result of the position with best play for the side to move, if it is in the tablebases
+pub fn probe_wdl(board: &Board) -> Option<Wdl> {
+    if !can_probe(board) {
+        return None;
+    }
+    return search_zeroing_moves(board, false).map(|(wdl, _)| wdl);
+}
+
+// DTZ of the position before a capture or pawn move with the result after it
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => return 1,
+        Wdl::CursedWin => return 101,
+        Wdl::BlessedLoss => return -101,
+        Wdl::Loss => return -1,
+        Wdl::Draw => return 0,
+    }
+}
+
+// Plies to the next capture or pawn move with best play, positive when the side to move wins and negative when it loses. 0 is a draw.
+// Values over 100 are wins or losses that the fifty move rule turns into draws
+pub fn probe_dtz(board: &Board) -> Option<i32> {
+    if !can_probe(board) {
+        return None;
+    }
+
+    let (wdl, zeroing_best_move) = search_zeroing_moves(board, true)?;
+    if wdl == Wdl::Draw {
+        return Some(0);
+    }
+    if zeroing_best_move {
+        return Some(dtz_before_zeroing(wdl));
+    }
+
+    match probe_table(board, true, wdl)? {
+        TableValue::Value(dtz) => {
+            let cursed = (wdl == Wdl::CursedWin) || (wdl == Wdl::BlessedLoss);
+            return Some((dtz + if cursed { 100 } else { 0 }) * (wdl as i32).signum());
+        }
+        TableValue::ChangeSideToMove => (),
+    }
+
+    // The table only has the other side to move, so look one move ahead for the winning move with the lowest DTZ
+    let mut min_dtz = 0xFFFF;
+    for mv in get_legal_moves(board) {
+        let zeroing = mv.capture.is_some() || (mv.role == Some(Role::Pawn));
+        let mut new_board = board.clone();
+        new_board.play_unsafe(mv);
+
+        let mut dtz = if zeroing {
+            -dtz_before_zeroing(search_zeroing_moves(&new_board, false)?.0)
+        } else {
+            -probe_dtz(&new_board)?
+        };
+        if (dtz == 1) && new_board.is_checkmate(&new_board.turn) {
+            min_dtz = 1;
+        }
+        if !zeroing {
+            dtz += dtz.signum();
+        }
+        if (dtz < min_dtz) && (dtz.signum() == (wdl as i32).signum()) {
+            min_dtz = dtz;
+        }
+    }
+
+    if min_dtz == 0xFFFF {
+        return Some(-1);
+    }
+    return Some(min_dtz);
+}
+
+// Ranks every root move by what the tablebases say about it, higher being better. Winning moves are ranked by how quickly they reach the
+// next capture or pawn move and losing moves by how long they hold out. Uses DTZ if there are DTZ tables and falls back to WDL if not
+pub fn rank_root_moves(board: &Board) -> Option<Vec<(Move, i32)>> {
+    if !can_probe(board) {
+        return None;
+    }
+    return rank_root_moves_by_dtz(board).or_else(|| rank_root_moves_by_wdl(board));
+}
+
+fn rank_root_moves_by_dtz(board: &Board) -> Option<Vec<(Move, i32)>> {
+    let mut ranked_moves = Vec::new();
+    for mv in get_legal_moves(board) {
+        let mut new_board = board.clone();
+        new_board.play_unsafe(mv);
+
+        let mut dtz = if mv.capture.is_some() || (mv.role == Some(Role::Pawn)) {
+            dtz_before_zeroing(probe_wdl(&new_board)?.opposite())
+        } else {
+            let dtz = -probe_dtz(&new_board)?;
+            dtz + dtz.signum()
+        };
+        if (dtz == 2) && new_board.is_checkmate(&new_board.turn) {
+            dtz = 1;
+        }
+
+        let rank = match dtz {
+            0 => 0,
+            dtz if dtz > 0 => 1000 - dtz,
+            dtz => -1000 - dtz,
+        };
+        ranked_moves.push((mv, rank));
+    }
+    return Some(ranked_moves);
+}
+
+fn rank_root_moves_by_wdl(board: &Board) -> Option<Vec<(Move, i32)>> {
+    let mut ranked_moves = Vec::new();
+    for mv in get_legal_moves(board) {
+        let mut new_board = board.clone();
+        new_board.play_unsafe(mv);
+        let wdl = probe_wdl(&new_board)?.opposite();
+        ranked_moves.push((mv, wdl as i32 * 1000));
+    }
+    return Some(ranked_moves);
+}
+
+// The moves that keep the best result the tablebases know of for the position, or None if it can't be probed
+pub fn tablebase_root_moves(board: &Board) -> Option<Vec<Move>> {
+    let ranked_moves = rank_root_moves(board)?;
+    let best_rank = ranked_moves.iter().map(|(_, rank)| *rank).max()?;
+    return Some(ranked_moves.into_iter().filter(|(_, rank)| *rank == best_rank).map(|(mv, _)| mv).collect());
+}
+
+// Evaluation from whites point of view for a position in the tablebases. Wins the fifty move rule turns into draws are nearly draws
+pub fn tablebase_evaluation(board: &Board) -> Option<i32> {
+    let evaluation = match probe_wdl(board)? {
+        Wdl::Win => TB_WIN,
+        Wdl::CursedWin => 1,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -1,
+        Wdl::Loss => -TB_WIN,
+    };
+    match board.turn {
+        Colour::White => return Some(evaluation),
+        Colour::Black => return Some(-evaluation),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // Where the probing tests look for tables. They need the 3 and 4 piece WDL and DTZ files, which aren't checked in
+    const TABLE_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy");
+
+    // Pairs data with only the piece order and groups filled in, which is all position_index needs
+    fn index_pairs(table: &Table, pieces: &[u8]) -> [[PairsData; 4]; 2] {
+        let mut pairs: [[PairsData; 4]; 2] = Default::default();
+        let files = if table.has_pawns { 4 } else { 1 };
+        for (file, file_pairs) in pairs[0].iter_mut().enumerate().take(files) {
+            file_pairs.pieces[..pieces.len()].copy_from_slice(pieces);
+            table.set_groups(file_pairs, [0, 0xF], file, &INDEX_TABLES);
+        }
+        return pairs;
+    }
+
+    // The same placement for every symmetry the table uses: the 8 flips and rotations of the board without pawns, or the mirror from
+    // side to side with them. Pieces of the same kind can swap places, so they are sorted
+    fn symmetry_class(pieces: &[(usize, u8)], has_pawns: bool) -> Vec<(u8, usize)> {
+        let symmetries = if has_pawns { 2 } else { 8 };
+        let mut class: Option<Vec<(u8, usize)>> = None;
+        for symmetry in 0..symmetries {
+            let mut transformed: Vec<(u8, usize)> = pieces
+                .iter()
+                .map(|&(square, code)| {
+                    let mut square = if symmetry & 1 != 0 { square ^ 7 } else { square };
+                    if symmetry & 2 != 0 {
+                        square ^= 56;
+                    }
+                    if symmetry & 4 != 0 {
+                        square = ((square >> 3) | (square << 3)) & 63;
+                    }
+                    return (code, square);
+                })
+                .collect();
+            transformed.sort();
+            if class.as_ref().is_none_or(|class| transformed < *class) {
+                class = Some(transformed);
+            }
+        }
+        return class.unwrap();
+    }
+
+    // Checks that every placement gets an index below the size of its table, and that two placements get the same index exactly when
+    // they are the same under the table's symmetries. Returns how many different indices were used
+    fn check_index(name: &str, pieces: &[u8], placements: impl Iterator<Item = Vec<(usize, u8)>>) -> usize {
+        let table = Table::new(name, PathBuf::new(), None).unwrap();
+        let pairs = index_pairs(&table, pieces);
+        let mut index_classes: HashMap<(usize, u64), Vec<(u8, usize)>> = HashMap::new();
+        let mut class_indices: HashMap<Vec<(u8, usize)>, (usize, u64)> = HashMap::new();
+
+        for placement in placements {
+            let (found_pairs, idx) = position_index(&table, &pairs, false, &placement, 0, false).unwrap();
+            let file = (0..4).find(|file| std::ptr::eq(found_pairs, &pairs[0][*file])).unwrap();
+            let group_count = found_pairs.group_len.iter().position(|len| *len == 0).unwrap();
+            assert!(idx < found_pairs.group_idx[group_count], "{} index {} is past the end of the table", name, idx);
+
+            let class = symmetry_class(&placement, table.has_pawns);
+            let index = (file, idx);
+            assert_eq!(*index_classes.entry(index).or_insert_with(|| class.clone()), class, "{} gives two placements index {:?}", name, index);
+            assert_eq!(*class_indices.entry(class).or_insert(index), index, "{} gives one placement two indices", name);
+        }
+        return index_classes.len();
+    }
+
+    #[test]
+    fn unique_piece_index_covers_every_placement_once() {
+        // Three different pieces on distinct squares, which is 31332 placements once the symmetries are taken out
+        let placements = (0..64).flat_map(|king| {
+            (0..64).flat_map(move |rook| (0..64).map(move |enemy_king| vec![(king, 6), (rook, 4), (enemy_king, 14)]))
+        });
+        let placements = placements.filter(|pieces| (pieces[0].0 != pieces[1].0) && (pieces[0].0 != pieces[2].0) && (pieces[1].0 != pieces[2].0));
+        assert_eq!(check_index("KRvK", &[6, 4, 14], placements), 31332);
+    }
+
+    #[test]
+    fn pawn_index_covers_every_placement_once() {
+        // A pawn from a2 to h7 and two kings, which is 48 * 63 * 62 / 2 placements once mirrored onto files a to d
+        let placements = (8..56).flat_map(|pawn| {
+            (0..64).flat_map(move |king| (0..64).map(move |enemy_king| vec![(pawn, 1), (king, 6), (enemy_king, 14)]))
+        });
+        let placements = placements.filter(|pieces| (pieces[0].0 != pieces[1].0) && (pieces[0].0 != pieces[2].0) && (pieces[1].0 != pieces[2].0));
+        assert_eq!(check_index("KPvK", &[1, 6, 14], placements), 48 * 63 * 62 / 2);
+    }
+
+    #[test]
+    fn king_pair_index_covers_every_placement_once() {
+        // Without a unique piece the kings are encoded together, in one of 462 ways. The kings are placed on every pair of squares they
+        // can be on with the knights fixed, and the knights on every pair of squares for a few king placements
+        let table = Table::new("KNNvK", PathBuf::new(), None).unwrap();
+        let pairs = index_pairs(&table, &[6, 14, 2, 2]);
+        let kings: Vec<(usize, usize)> = (0..64).flat_map(|king| (0..64).map(move |enemy_king| (king, enemy_king))).filter(|(king, enemy_king)| distance(*king, *enemy_king) > 1).collect();
+        let placements = kings.iter().map(|&(king, enemy_king)| vec![(king, 6), (enemy_king, 14), (27, 2), (36, 2)]).filter(|pieces| pieces.iter().map(|(square, _)| *square).collect::<HashSet<usize>>().len() == 4);
+        check_index("KNNvK", &[6, 14, 2, 2], placements);
+
+        let placements = [(0, 2), (1, 18), (12, 33)].into_iter().flat_map(|(king, enemy_king)| {
+            (0..64).flat_map(move |first| (0..64).map(move |second| vec![(king, 6), (enemy_king, 14), (first, 2), (second, 2)]))
+        });
+        let placements = placements.filter(|pieces| (pieces[2].0 < pieces[3].0) && pieces.iter().map(|(square, _)| *square).collect::<HashSet<usize>>().len() == 4);
+        check_index("KNNvK", &[6, 14, 2, 2], placements);
+
+        let king_pairs: HashSet<usize> = kings.iter().map(|&(king, enemy_king)| {
+            let free: Vec<usize> = (0..64).filter(|square| (*square != king) && (*square != enemy_king)).take(2).collect();
+            let (_, idx) = position_index(&table, &pairs, false, &[(king, 6), (enemy_king, 14), (free[0], 2), (free[1], 2)], 0, false).unwrap();
+            return (idx % 462) as usize;
+        }).collect();
+        assert_eq!(king_pairs.len(), 462);
+    }
+
+    #[test]
+    fn binomials() {
+        let index_tables = &*INDEX_TABLES;
+        assert_eq!(index_tables.binomial[1][48], 48);
+        assert_eq!(index_tables.binomial[2][62], 1891);
+        assert_eq!(index_tables.binomial[3][61], 35990);
+        assert_eq!(index_tables.lead_pawns_size[1], [6, 6, 6, 6]);
+    }
+
+    // The tables are global, so tests that load them take turns
+    static LOADED_TABLES: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    fn load_tables() -> std::sync::MutexGuard<'static, ()> {
+        let guard = LOADED_TABLES.lock().unwrap_or_else(|error| error.into_inner());
+        assert!(init_tablebases(TABLE_DIRECTORY) > 0, "No tables found in {}", TABLE_DIRECTORY);
+        return guard;
+    }
+
+    // The start of a KQvK file up to the table sizes. Both sides to move store the pieces in the same order, in the same group
+    fn kqvk_header(magic: [u8; 4]) -> Vec<u8> {
+        let mut bytes = magic.to_vec();
+        // Not symmetric and without pawns, then the group order and the white king, white queen and black king for both sides
+        bytes.extend([1, 0x00, 0x66, 0x55, 0xEE, 0]);
+        return bytes;
+    }
+
+    // Pads the file out to where the (empty) blocks of data would start
+    fn pad_to_data(mut bytes: Vec<u8>) -> Vec<u8> {
+        bytes.resize((bytes.len() + 0x3F) & !0x3F, 0);
+        return bytes;
+    }
+
+    // A table that is the same value for every position, a win for white to move and a loss for black to move, and a DTZ table of white
+    // to move giving 5 moves, which is 11 plies
+    fn write_single_value_tables(directory: &PathBuf) {
+        fs::create_dir_all(directory).unwrap();
+        let mut wdl = kqvk_header(WDL_MAGIC);
+        wdl.extend([FLAG_SINGLE_VALUE, 4, FLAG_SINGLE_VALUE, 0]);
+        fs::write(directory.join("KQvK.rtbw"), pad_to_data(wdl)).unwrap();
+        let mut dtz = kqvk_header(DTZ_MAGIC);
+        dtz.extend([FLAG_SINGLE_VALUE, 5]);
+        fs::write(directory.join("KQvK.rtbz"), pad_to_data(dtz)).unwrap();
+    }
+
+    #[test]
+    fn probes_a_single_value_table() {
+        let _guard = LOADED_TABLES.lock().unwrap_or_else(|error| error.into_inner());
+        let directory = env::temp_dir().join(format!("chesslibrary_syzygy_{}", std::process::id()));
+        write_single_value_tables(&directory);
+        assert_eq!(init_tablebases(directory.to_str().unwrap()), 1);
+        assert_eq!(largest_table(), 3);
+
+        assert_eq!(wdl("8/8/8/3k4/8/8/8/QK6 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("8/8/8/3k4/8/8/8/QK6 b - - 0 1"), Some(Wdl::Loss));
+        // With black as the side with the queen the board is flipped before looking it up
+        assert_eq!(wdl("qk6/8/8/8/3K4/8/8/8 b - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("qk6/8/8/8/3K4/8/8/8 w - - 0 1"), Some(Wdl::Loss));
+        // Taking the loose queen is searched instead of trusting the table
+        assert_eq!(wdl("8/8/8/8/8/8/1kQ5/7K b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("8/8/8/3k4/8/8/8/4K3 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("8/8/8/3k4/8/8/8/R3K3 w - - 0 1"), None);
+
+        // Only white to move is stored, so black to move looks one move ahead and adds the ply
+        assert_eq!(dtz("8/8/8/3k4/8/8/8/QK6 w - - 0 1"), Some(11));
+        assert_eq!(dtz("8/8/8/3k4/8/8/8/QK6 b - - 0 1"), Some(-12));
+        assert_eq!(tablebase_evaluation(&Board::from_fen("qk6/8/8/8/3K4/8/8/8 b - - 0 1".to_string())), Some(-TB_WIN));
+
+        init_tablebases("");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn decompresses_a_hand_built_table() {
+        // Symbols 0 and 1 are the values 4 and 0, and symbol 2 is the pair of them. Every symbol in the data is symbol 2, a one bit code
+        // of 0, so the values alternate between a win and a loss from index 0. Blocks hold 600 values while the sparse index has an entry
+        // every 1024, so finding the block means stepping over block boundaries both ways
+        let table_size: usize = 31332;
+        let (block_values, span) = (600, 1024);
+        let num_blocks = table_size.div_ceil(block_values);
+
+        let mut bytes = kqvk_header(WDL_MAGIC);
+        bytes.extend([0, 6, 10, 0]);
+        bytes.extend((num_blocks as u32).to_le_bytes());
+        bytes.extend([1, 1]);
+        bytes.extend(2u16.to_le_bytes());
+        bytes.extend(3u16.to_le_bytes());
+        bytes.extend([4, 0xF0, 0xFF, 0, 0xF0, 0xFF, 0x00, 0x10, 0x00, 0]);
+        bytes.extend([FLAG_SINGLE_VALUE, 2]);
+
+        for k in 0..table_size.div_ceil(span) {
+            let idx = k * span + span / 2;
+            bytes.extend(((idx / block_values) as u32).to_le_bytes());
+            bytes.extend(((idx % block_values) as u16).to_le_bytes());
+        }
+        for block in 0..num_blocks {
+            let values = block_values.min(table_size - block * block_values);
+            bytes.extend(((values - 1) as u16).to_le_bytes());
+        }
+        let mut bytes = pad_to_data(bytes);
+        bytes.resize(bytes.len() + num_blocks * 64, 0);
+
+        let table = Table::new("KQvK", PathBuf::new(), None).unwrap();
+        let file = table.parse(bytes, false).unwrap();
+        let pairs = &file.pairs[0][0];
+        assert_eq!(pairs.sparse_index_size, 31);
+        for idx in 0..table_size as u64 {
+            assert_eq!(decompress_pairs(pairs, &file.bytes, idx), if idx % 2 == 0 { 4 } else { 0 }, "index {}", idx);
+        }
+        assert_eq!(decompress_pairs(&file.pairs[1][0], &file.bytes, 0), 2);
+
+        // Through a real position, where the index decides the result
+        let pieces = board_pieces(&Board::from_fen("8/8/8/3k4/8/8/8/QK6 w - - 0 1".to_string()));
+        let (_, idx) = position_index(&table, &file.pairs, false, &pieces, 0, false).unwrap();
+        let TableValue::Value(value) = probe_file(&table, &file, false, &pieces, 0, false, Wdl::Draw) else {
+            panic!("WDL tables store both sides to move");
+        };
+        assert_eq!(value, if idx % 2 == 0 { 2 } else { -2 });
+    }
+
+    fn wdl(fen: &str) -> Option<Wdl> {
+        return probe_wdl(&Board::from_fen(fen.to_string()));
+    }
+
+    fn dtz(fen: &str) -> Option<i32> {
+        return probe_dtz(&Board::from_fen(fen.to_string()));
+    }
+
+    #[test]
+    #[ignore = "needs the 3 and 4 piece Syzygy tables in tests/syzygy"]
+    fn probes_known_results() {
+        let _guard = load_tables();
+        // Rook against a lone king wins with either side to move, and black to move here is in check
+        assert_eq!(wdl("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("8/8/8/8/8/8/8/KR5k b - - 0 1"), Some(Wdl::Loss));
+        // Mate in one is one ply from zeroing, and being mated is a loss in 0
+        assert_eq!(dtz("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Some(1));
+        assert_eq!(wdl("R6k/8/6K1/8/8/8/8/8 b - - 0 1"), Some(Wdl::Loss));
+        // A rook pawn with the defending king in the corner and a stalemate are draws
+        assert_eq!(wdl("k7/8/K7/P7/8/8/8/8 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(dtz("k7/8/K7/P7/8/8/8/8 w - - 0 1"), Some(0));
+        assert_eq!(wdl("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+        // The king on the sixth in front of its pawn wins whoever is to move, and the pawn move that starts it is zeroing
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(Wdl::Loss));
+        // The king takes a loose rook, and a lone knight can't win from either side
+        assert_eq!(wdl("8/8/8/3k4/8/8/2r5/QK6 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("8/8/8/3k4/8/8/8/NK6 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("8/8/8/3k4/8/8/8/NK6 b - - 0 1"), Some(Wdl::Draw));
+        // Castling rights and too many pieces can't be probed
+        assert_eq!(wdl("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"), None);
+        assert_eq!(wdl("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+    }
+
+    // The result of a position is the best of the results after each of its moves, which only holds if every table is read right and
+    // captures into smaller tables, en passant and promotions are handled
+    fn check_against_moves(board: &Board, depth: i32) {
+        let legal_moves = get_legal_moves(board);
+        let Some(result) = probe_wdl(board) else {
+            return;
+        };
+        let Some(dtz) = probe_dtz(board) else {
+            return;
+        };
+        assert_eq!(dtz.signum(), (result as i32).signum(), "DTZ {} and WDL {:?} disagree in {}", dtz, result, board.to_fen());
+        assert_eq!(dtz.abs() > 100, (result == Wdl::CursedWin) | (result == Wdl::BlessedLoss), "DTZ {} and WDL {:?} disagree on the fifty move rule in {}", dtz, result, board.to_fen());
+        if legal_moves.is_empty() {
+            let expected = if board.is_check(&board.turn) { Wdl::Loss } else { Wdl::Draw };
+            assert_eq!(result, expected, "{}", board.to_fen());
+            return;
+        }
+
+        let mut best = Wdl::Loss;
+        for mv in legal_moves {
+            let mut new_board = board.clone();
+            new_board.play_unsafe(mv);
+            best = best.max(probe_wdl(&new_board).unwrap().opposite());
+            if depth > 1 {
+                check_against_moves(&new_board, depth - 1);
+            }
+        }
+        // Moves that aren't captures or pawn moves carry the fifty move counter on, which can turn a win after them into a cursed win or
+        // the other way round, so only who wins is compared
+        assert_eq!((best as i32).signum(), (result as i32).signum(), "{} is {:?} but its best move gives {:?}", board.to_fen(), result, best);
+    }
+
+    #[test]
+    #[ignore = "needs the 3 and 4 piece Syzygy tables in tests/syzygy"]
+    fn results_agree_with_the_moves_after_them() {
+        let _guard = load_tables();
+        let positions = [
+            // The tables don't store en passant, so these are only right if the captures are searched
+            "8/8/8/8/3pP3/8/8/K6k b - e3 0 1",
+            "8/8/8/8/1pP5/8/8/K6k b - c3 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            // Promotions, underpromotions and captures into smaller tables
+            "8/4P3/8/8/8/8/1k6/4K2r w - - 0 1",
+            "8/1P6/8/8/8/k7/8/K2n4 w - - 0 1",
+            "8/8/8/3k4/8/8/2r5/QK6 w - - 0 1",
+            "8/8/8/8/3k4/8/1n6/R3K3 b - - 0 1",
+        ];
+        for fen in positions {
+            check_against_moves(&Board::from_fen(fen.to_string()), 2);
+        }
+    }
+}
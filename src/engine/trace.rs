@@ -0,0 +1,132 @@
+use crate::board::Board;
+use crate::colour::Colour;
+use crate::engine::eval::{evaluation_terms, game_phase, Score};
+use crate::engine::endgame::{endgame_evaluation, scale_evaluation, scale_factor, SCALE_FACTOR_NORMAL};
+use crate::engine::params::EVAL_PARAMS;
+use serde::Serialize;
+use std::fmt;
+
+// Every evaluation term for a position, for seeing why evaluate gives the score it does. Scores are from each colours own point of
+// view, and the total is from whites
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalTrace {
+    pub phase: i32,
+    pub terms: Vec<TraceTerm>,
+    pub total: Score,
+    // The specialised endgame evaluation used instead of the terms, if there is one
+    pub endgame: Option<String>,
+    pub scale_factor: i32,
+    pub evaluation: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceTerm {
+    pub name: String,
+    pub white: Score,
+    pub black: Score,
+}
+
+// Breaks down the evaluation of a position term by term. Uses the same terms, endgames and scale factors as evaluate, so the evaluation
+// here matches it unless USE_LEGACY_MOBILITY is on or a network is loaded
+pub fn evaluate_trace(board: &Board) -> EvalTrace {
+    let params = EVAL_PARAMS.read().unwrap();
+    let phase = game_phase(board);
+    let terms = evaluation_terms(board, &params);
+
+    let total = terms.total();
+    let endgame = endgame_evaluation(board, &params);
+    let strong = if total.taper(phase) > 0 { Colour::White } else { Colour::Black };
+    return EvalTrace {
+        phase,
+        terms: terms.named_terms().iter().map(|(name, term)| TraceTerm { name: name.to_string(), white: term.white, black: term.black }).collect(),
+        total,
+        endgame: endgame.map(|(name, _)| name.to_string()),
+        scale_factor: scale_factor(board, &params, strong),
+        evaluation: match endgame {
+            Some((_, evaluation)) => evaluation,
+            None => scale_evaluation(board, &params, total.taper(phase)),
+        },
+    };
+}
+
+impl EvalTrace {
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string_pretty(self).unwrap();
+    }
+
+    // One term from a colours point of view, or 0 if there is no term by that name
+    pub fn term(&self, name: &str, colour: Colour) -> Score {
+        for term in &self.terms {
+            if term.name == name {
+                match colour {
+                    Colour::White => return term.white,
+                    Colour::Black => return term.black,
+                }
+            }
+        }
+        return Score::default();
+    }
+}
+
+// Prints a table of every term in pawns, with the middlegame and endgame parts for each colour and the difference between them
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<18} | {:^15} | {:^15} | {:^15}", "Term", "White", "Black", "Total")?;
+        writeln!(f, "{:<18} | {:>7} {:>7} | {:>7} {:>7} | {:>7} {:>7}", "", "MG", "EG", "MG", "EG", "MG", "EG")?;
+        writeln!(f, "{}", "-".repeat(70))?;
+        for term in &self.terms {
+            let difference = term.white - term.black;
+            writeln!(
+                f,
+                "{:<18} | {:>7} {:>7} | {:>7} {:>7} | {:>7} {:>7}",
+                term.name, pawns(term.white.mg), pawns(term.white.eg), pawns(term.black.mg), pawns(term.black.eg), pawns(difference.mg), pawns(difference.eg)
+            )?;
+        }
+        writeln!(f, "{}", "-".repeat(70))?;
+        writeln!(f, "{:<18} | {:>15} | {:>15} | {:>7} {:>7}", "Total", "", "", pawns(self.total.mg), pawns(self.total.eg))?;
+        writeln!(f)?;
+        writeln!(f, "Phase: {}", self.phase)?;
+        match &self.endgame {
+            Some(endgame) => writeln!(f, "Endgame: {}", endgame)?,
+            None => writeln!(f, "Scale factor: {}/{}", self.scale_factor, SCALE_FACTOR_NORMAL)?,
+        }
+        write!(f, "Evaluation: {} (white side)", pawns(self.evaluation))
+    }
+}
+
+fn pawns(value: i32) -> String {
+    return format!("{:.2}", value as f64 / 10000.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::eval::evaluate;
+
+    #[test]
+    fn terms_add_up_to_the_evaluation() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/P4PPP/R2QKB1R w KQ -",
+            "6k1/5ppp/8/3b4/8/8/2B2PPP/6K1 b - -",
+            "8/8/3k4/8/8/8/4KBN1/8 w - -",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen.to_string());
+            let trace = evaluate_trace(&board);
+
+            let mut total = Score::default();
+            for term in &trace.terms {
+                total += term.white - term.black;
+            }
+            assert_eq!(total, trace.total, "{}", fen);
+            // Only the last one is a known endgame, which replaces the terms
+            assert_eq!(trace.endgame.is_some(), fen == fens[3], "{}", fen);
+            if trace.endgame.is_none() {
+                assert_eq!(trace.evaluation, scale_evaluation(&board, &EVAL_PARAMS.read().unwrap(), total.taper(trace.phase)), "{}", fen);
+            }
+            assert_eq!(trace.evaluation, evaluate(&board, &Colour::White), "{}", fen);
+            assert_eq!(trace.evaluation, -evaluate(&board, &Colour::Black), "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use crate::board::Board;
+use crate::engine::eval::evaluate_with_params;
+use crate::engine::params::EvalParams;
+use crate::engine::pawns::invalidate_pawn_hash;
+use std::fs;
+use std::thread;
+
+// A position from a finished game, with the result from whites point of view as 1 for a win, 0.5 for a draw and 0 for a loss
+pub struct TuningPosition {
+    pub board: Board,
+    pub result: f64,
+}
+
+pub struct TuneOptions {
+    pub iterations: usize,
+    pub step: i32,
+    // Only tune parameters with these names, or every parameter when empty
+    pub only: Vec<String>,
+    pub threads: usize,
+    // Written after every iteration so a long run can be stopped at any point
+    pub output: String,
+}
+
+// Reads a dataset of labelled positions, one to a line. Each line starts with a FEN or EPD position and has the result somewhere after
+// it, either as a PGN style result (1-0, 0-1, 1/2-1/2, with or without quotes, like the c9 opcode in EPD files) or as a number in
+// square brackets ([1.0], [0.5], [0.0]). The "fen | score | result" lines written by datagen work too. Lines without a result are skipped
+pub fn load_dataset(path: &str) -> Result<Vec<TuningPosition>, String> {
+    let contents = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+    let mut positions: Vec<TuningPosition> = Vec::new();
+
+    for line in contents.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if (fields.len() < 5) || (fields[0].matches('/').count() != 7) || ((fields[1] != "w") && (fields[1] != "b")) {
+            continue;
+        }
+        let Some(result) = parse_result(&fields[4..].join(" ")) else {
+            continue;
+        };
+        let board = Board::from_fen(fields[..4].join(" "));
+        positions.push(TuningPosition { board, result });
+    }
+
+    if positions.is_empty() {
+        return Err(format!("No labelled positions found in {}", path));
+    }
+    return Ok(positions);
+}
+
+fn parse_result(text: &str) -> Option<f64> {
+    // Data from datagen, which is "fen | score | result"
+    if text.contains('|') {
+        return text.rsplit('|').next()?.trim().parse::<f64>().ok();
+    }
+
+    if text.contains("1/2-1/2") {
+        return Some(0.5);
+    } else if text.contains("1-0") {
+        return Some(1.0);
+    } else if text.contains("0-1") {
+        return Some(0.0);
+    }
+
+    let start = text.find('[')?;
+    let end = text[start..].find(']')? + start;
+    return text[start + 1..end].trim().parse::<f64>().ok();
+}
+
+// Expected score for white from an evaluation, where k is the scaling constant being fitted. Evaluations are in the engines units, so
+// they are turned into centipawns first
+pub fn sigmoid(evaluation: i32, k: f64) -> f64 {
+    let centipawns = evaluation as f64 / 100.0;
+    return 1.0 / (1.0 + 10f64.powf(-k * centipawns / 400.0));
+}
+
+// Mean squared difference between the game results and the expected scores the evaluation gives
+pub fn mean_error(positions: &[TuningPosition], params: &EvalParams, k: f64, threads: usize) -> f64 {
+    invalidate_pawn_hash();
+
+    let chunk_size = positions.len().div_ceil(threads.max(1));
+    let total_error: f64 = thread::scope(|scope| {
+        let handles: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut error = 0.0;
+                    for position in chunk {
+                        let evaluation = evaluate_with_params(&position.board, params);
+                        error += (position.result - sigmoid(evaluation, k)).powi(2);
+                    }
+                    return error;
+                })
+            })
+            .collect();
+        return handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+    });
+
+    return total_error / positions.len() as f64;
+}
+
+// Finds the scaling constant that best fits the current evaluation to the results, by scanning with smaller and smaller steps
+pub fn fit_k(positions: &[TuningPosition], params: &EvalParams, threads: usize) -> f64 {
+    let mut best_k = 1.0;
+    let mut best_error = mean_error(positions, params, best_k, threads);
+    let mut step = 1.0;
+
+    for _ in 0..10 {
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for k in [best_k - step, best_k + step] {
+                if k <= 0.0 {
+                    continue;
+                }
+                let error = mean_error(positions, params, k, threads);
+                if error < best_error {
+                    best_error = error;
+                    best_k = k;
+                    improved = true;
+                }
+            }
+        }
+        step /= 2.0;
+    }
+
+    return best_k;
+}
+
+// Texel style local search. Every parameter value is moved up by the step and kept if the error drops, otherwise moved down and kept if
+// that helps. Stops after the given number of iterations or when a whole pass finds nothing better
+pub fn tune(positions: &[TuningPosition], params: &mut EvalParams, k: f64, options: &TuneOptions) -> Result<f64, String> {
+    let mut best_error = mean_error(positions, params, k, options.threads);
+    println!("Starting error {:.8}", best_error);
+
+    let values: Vec<(String, usize)> = params
+        .named_values()
+        .into_iter()
+        .filter(|(name, _)| options.only.is_empty() || options.only.contains(name))
+        .flat_map(|(name, values)| (0..values.len()).map(move |index| (name.clone(), index)))
+        .filter(|(name, index)| !is_fixed(name, *index))
+        .collect();
+
+    for iteration in 1..=options.iterations {
+        let mut improved_count = 0;
+
+        // The names and positions are worked out once, and each value is read and written in place
+        for (name, index) in &values {
+            let original = *params.value_mut(name, *index).unwrap();
+
+            for change in [options.step, -options.step] {
+                *params.value_mut(name, *index).unwrap() = original + change;
+                let error = mean_error(positions, params, k, options.threads);
+                if error < best_error {
+                    best_error = error;
+                    improved_count += 1;
+                    break;
+                }
+                *params.value_mut(name, *index).unwrap() = original;
+            }
+        }
+
+        params.to_file(&options.output)?;
+        println!("Iteration {}: error {:.8}, {} values changed, written to {}", iteration, best_error, improved_count, options.output);
+
+        if improved_count == 0 {
+            break;
+        }
+    }
+
+    return Ok(best_error);
+}
+
+// Values that have no effect on the evaluation, so are not worth tuning. Kings have no material value, pawns and kings don't count as
+// king attackers, and pawns can never be on the first or last rank
+fn is_fixed(name: &str, index: usize) -> bool {
+    if name.starts_with("material_") {
+        return index == 5;
+    }
+    if (name == "king_attack_weight") | name.starts_with("safe_check_") {
+        return (index == 0) | (index == 5);
+    }
+    if name.starts_with("psqt_") && name.ends_with("_pawn") {
+        return !(8..56).contains(&index);
+    }
+    return false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions() -> Vec<TuningPosition> {
+        let labelled = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", 0.5),
+            ("4k3/8/8/8/8/8/8/R3K3 w - -", 1.0),
+            ("4k3/8/8/8/8/8/8/Q3K3 b - -", 1.0),
+            ("r3k3/8/8/8/8/8/8/4K3 w - -", 0.0),
+            ("4k3/pppp4/8/8/8/8/PPP5/4K3 w - -", 0.0),
+            ("4k3/ppp5/8/8/8/8/PPPP4/4K3 b - -", 1.0),
+        ];
+        return labelled.iter().map(|(fen, result)| TuningPosition { board: Board::from_fen(fen.to_string()), result: *result }).collect();
+    }
+
+    #[test]
+    fn results_are_read_in_every_format() {
+        assert_eq!(parse_result("c9 \"1-0\";"), Some(1.0));
+        assert_eq!(parse_result("[0.5]"), Some(0.5));
+        assert_eq!(parse_result("| 35 | 0.0"), Some(0.0));
+        assert_eq!(parse_result("bm e4;"), None);
+    }
+
+    #[test]
+    fn tuning_never_makes_the_error_worse() {
+        let positions = positions();
+        let mut params = EvalParams::default();
+        let starting_error = mean_error(&positions, &params, 1.0, 2);
+
+        let output = std::env::temp_dir().join(format!("chesslibrary_tune_{}.txt", std::process::id()));
+        let options = TuneOptions {
+            iterations: 2,
+            step: 5000,
+            only: vec!["material_mg".to_string(), "material_eg".to_string()],
+            threads: 2,
+            output: output.to_str().unwrap().to_string(),
+        };
+        let error = tune(&positions, &mut params, 1.0, &options).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(error < starting_error);
+        assert_eq!(error, mean_error(&positions, &params, 1.0, 2));
+        // Kings have no material value to tune, and nothing outside only is touched
+        assert_eq!(params.material_mg.king, EvalParams::default().material_mg.king);
+        assert_eq!(params.named_values()[2..], EvalParams::default().named_values()[2..]);
+    }
+}
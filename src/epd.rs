@@ -0,0 +1,231 @@
+use crate::board::Board;
+use crate::fen::validate_fen;
+use crate::mv::Move;
+use crate::san::from_san;
+use crate::uci::from_uci;
+
+// One EPD record: the first four fields of a FEN followed by operations like bm Qg6; id "WAC.001";
+#[derive(Debug, Clone)]
+pub struct EpdRecord {
+    // The position as a full FEN, using the hmvc and fmvn operations for the last two fields if they are there
+    pub fen: String,
+    // Every operation in the order they were written, as the opcode and its operands with any quotes removed
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdRecord {
+    pub fn board(&self) -> Board {
+        return Board::from_fen(self.fen.clone());
+    }
+
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        return self.operations.iter().find(|(name, _)| name == opcode).map(|(_, operands)| operands.as_slice());
+    }
+
+    // The id operation, which names the position in its suite
+    pub fn id(&self) -> Option<&str> {
+        return self.operands("id")?.first().map(|id| id.as_str());
+    }
+
+    // The c0 comment, which suites like STS use to give points for moves other than the best one
+    pub fn comment(&self) -> Option<&str> {
+        return self.operands("c0")?.first().map(|comment| comment.as_str());
+    }
+
+    // Best moves. Finding any one of them solves the position
+    pub fn best_moves(&self) -> Vec<Move> {
+        return self.moves("bm");
+    }
+
+    // Moves to avoid. Playing any one of them fails the position
+    pub fn avoid_moves(&self) -> Vec<Move> {
+        return self.moves("am");
+    }
+
+    // The centipawn evaluation, from the side to move's point of view
+    pub fn centipawn_evaluation(&self) -> Option<i32> {
+        return self.operands("ce")?.first()?.parse().ok();
+    }
+
+    // Direct mate in this many moves
+    pub fn direct_mate(&self) -> Option<i32> {
+        return self.operands("dm")?.first()?.parse().ok();
+    }
+
+    // The predicted variation. Each move is read in the position after the ones before it, and the line stops at the first move that
+    // can't be read
+    pub fn pv(&self) -> Vec<Move> {
+        let mut board = self.board();
+        let mut pv: Vec<Move> = Vec::new();
+        for operand in self.operands("pv").unwrap_or_default() {
+            let Some(mv) = parse_epd_move(&board, operand) else {
+                break;
+            };
+            board.play_unsafe(mv);
+            pv.push(mv);
+        }
+        return pv;
+    }
+
+    // Moves are written in SAN, but some suites use UCI so both are accepted
+    fn moves(&self, opcode: &str) -> Vec<Move> {
+        let board = self.board();
+        return self.operands(opcode).unwrap_or_default().iter().filter_map(|operand| parse_epd_move(&board, operand)).collect();
+    }
+
+    // Whether playing a move solves the position: it has to be one of the best moves if there is a bm operation, and none of the moves
+    // to avoid. A record with neither operation has nothing to solve, so no move solves it
+    pub fn is_solved_by(&self, mv: Move) -> bool {
+        if self.operands("bm").is_none() && self.operands("am").is_none() {
+            return false;
+        }
+        if self.operands("bm").is_some() && !self.best_moves().contains(&mv) {
+            return false;
+        }
+        return !self.avoid_moves().contains(&mv);
+    }
+}
+
+fn parse_epd_move(board: &Board, text: &str) -> Option<Move> {
+    return from_san(board, text).or_else(|| from_uci(board, text).ok());
+}
+
+// Reads one EPD record
+pub fn parse_epd(line: &str) -> Result<EpdRecord, String> {
+    let line = line.trim();
+    let fields: Vec<&str> = line.splitn(5, char::is_whitespace).collect();
+    if fields.len() < 4 {
+        return Err(format!("EPD record needs at least 4 fields: {}", line));
+    }
+
+    let mut operations: Vec<(String, Vec<String>)> = Vec::new();
+    for operation in split_operations(fields.get(4).copied().unwrap_or("")) {
+        let mut tokens = split_operands(&operation).into_iter();
+        let Some(opcode) = tokens.next() else {
+            continue;
+        };
+        operations.push((opcode, tokens.collect()));
+    }
+
+    let operand = |opcode: &str| operations.iter().find(|(name, _)| name == opcode).and_then(|(_, operands)| operands.first().cloned());
+    let halfmove_clock = operand("hmvc").unwrap_or("0".to_string());
+    let fullmove_number = operand("fmvn").unwrap_or("1".to_string());
+    let fen = format!("{} {} {}", fields[..4].join(" "), halfmove_clock, fullmove_number);
+    validate_fen(&fen)?;
+
+    // A best move or move to avoid that isn't legal would quietly change what solves the position, so the record is refused
+    let record = EpdRecord { fen, operations };
+    let board = record.board();
+    for opcode in ["bm", "am"] {
+        for operand in record.operands(opcode).unwrap_or_default() {
+            if parse_epd_move(&board, operand).is_none() {
+                return Err(format!("{} {} is not a legal move in {}", opcode, operand, record.fen));
+            }
+        }
+    }
+
+    return Ok(record);
+}
+
+// Reads every record in an EPD file, skipping blank lines and lines starting with #. Errors say which line they are on
+pub fn parse_epd_file(text: &str) -> Result<Vec<EpdRecord>, String> {
+    let mut records: Vec<EpdRecord> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        records.push(parse_epd(line).map_err(|error| format!("Line {}: {}", index + 1, error))?);
+    }
+    return Ok(records);
+}
+
+// Splits operations on semicolons that aren't inside a quoted string
+fn split_operations(text: &str) -> Vec<String> {
+    let mut operations: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for char in text.chars() {
+        match char {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(char);
+            }
+            ';' if !in_quotes => operations.push(std::mem::take(&mut current)),
+            _ => current.push(char),
+        }
+    }
+    operations.push(current);
+    return operations.into_iter().filter(|operation| !operation.trim().is_empty()).collect();
+}
+
+// Splits an operation into the opcode and its operands on whitespace, keeping quoted strings whole without their quotes
+fn split_operands(operation: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for char in operation.chars().chain(std::iter::once(' ')) {
+        match char {
+            '"' => {
+                if in_quotes {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                in_quotes = !in_quotes;
+            }
+            char if char.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(char),
+        }
+    }
+    return tokens;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(record: &EpdRecord, text: &str) -> Move {
+        return from_san(&record.board(), text).unwrap();
+    }
+
+    #[test]
+    fn reads_the_position_and_operations() {
+        let record = parse_epd("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; id \"test; 1\"; ce 30; fmvn 3;").unwrap();
+        assert_eq!(record.fen, "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3");
+        assert_eq!(record.id(), Some("test; 1"));
+        assert_eq!(record.centipawn_evaluation(), Some(30));
+        assert_eq!(record.best_moves(), vec![san(&record, "Bb5"), san(&record, "Bc4")]);
+    }
+
+    #[test]
+    fn uci_moves_are_accepted() {
+        let record = parse_epd("4k3/8/8/8/8/8/8/R3K3 w Q - bm a1a8; pv a1a8 e8d7;").unwrap();
+        assert_eq!(record.best_moves(), vec![san(&record, "Ra8+")]);
+        assert_eq!(record.pv().len(), 2);
+    }
+
+    #[test]
+    fn solved_by_a_best_move_and_not_a_move_to_avoid() {
+        let record = parse_epd("4k3/8/8/8/8/8/8/R3K3 w Q - bm Ra8+;").unwrap();
+        assert!(record.is_solved_by(san(&record, "Ra8+")));
+        assert!(!record.is_solved_by(san(&record, "Ra7")));
+
+        let record = parse_epd("4k3/8/8/8/8/8/8/R3K3 w Q - am Ra7;").unwrap();
+        assert!(record.is_solved_by(san(&record, "Ra8+")));
+        assert!(!record.is_solved_by(san(&record, "Ra7")));
+
+        // Nothing to check, so nothing solves it
+        let record = parse_epd("4k3/8/8/8/8/8/8/R3K3 w Q - id \"none\";").unwrap();
+        assert!(!record.is_solved_by(san(&record, "Ra8+")));
+    }
+
+    #[test]
+    fn moves_that_cannot_be_read_are_refused() {
+        assert!(parse_epd("4k3/8/8/8/8/8/8/R3K3 w Q - bm Qh5;").is_err());
+        assert!(parse_epd("4k3/8/8/8/8/8/8/R3K3 w Q - am e2e4;").is_err());
+        assert!(parse_epd_file("4k3/8/8/8/8/8/8/R3K3 w Q - bm Ra8+;\n\n4k3/8/8/8/8/8/8/R3K3 w Q - bm Nf3;\n").unwrap_err().starts_with("Line 3"));
+        assert!(parse_epd("4k3/8/8/8 w").is_err());
+    }
+}
@@ -58,6 +58,7 @@ def play():
                             if event.get('status') != None:
                                 if(event['status'] != 'started'):
                                     print("Game Over")
+                                    chesslibrary.stop_ponder()
                                     game_in_progress = False
                                     break
 
@@ -76,6 +77,8 @@ def play():
                                     bot_time = get_time(bot_colour, event)
                                     next_move = get_best_move(starting_position, bot_colour, bot_time, event)
                                     make_move_on_board(game_id, next_move, 3)
+                                    ## Thinks on the opponents time
+                                    chesslibrary.start_ponder(starting_position, (event['moves'] + " " + next_move).strip())
 
 
 ## Determines if a challenge should be accepted or declined based on time control and variant. Returns a boolean
@@ -89,8 +92,13 @@ def accept_challenge(challenge) -> bool:
                 return True
     return False
 
-## Gets the best move
+## Gets the best move, using the ponder search if the opponent played the predicted move
 def get_best_move(starting_position, bot_colour, bot_time, event):
+    moves = event['moves'].split()
+    if len(moves) > 0:
+        ponder_result = chesslibrary.ponder_hit(bot_time, moves[-1])
+        if ponder_result != None:
+            return ponder_result[0]
     best_move = chesslibrary.pick_move(starting_position, bot_time, bot_colour, event['moves'])
     return best_move[0]
 
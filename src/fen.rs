@@ -1,7 +1,11 @@
 use crate::board::Board;
-use crate::bitboard::{Bitboard, string_move_to_bitboard};
-use crate::colour::Colour;
-use crate::role::Role;
+use crate::bitboard::{Bitboard, EMPTY_BITBOARD, bitboard_to_string_move, string_move_to_bitboard};
+use crate::colour::{Colour, get_colour};
+use crate::role::{Role, get_role};
+use crate::castle::{back_rank, CastleSide, CASTLE_SIDES};
+use crate::square::FILES;
+use crate::uci::get_square_string;
+use crate::variant::{parse_check_counter, Variant};
 
 impl Board {
     
@@ -64,22 +68,222 @@ impl Board {
             }
         }
 
+        // Standard FEN and X-FEN use KQkq for the outermost rook on each side of the king, and X-FEN uses the rooks file letter when
+        // there is another rook further out. Shredder-FEN always uses the file letter
         for char in fen_vec[2].chars() {
-            if char == 'K' {
-                board.castling_rights.white.kingside = true;
-            } else if char == 'Q' {
-                board.castling_rights.white.queenside = true;
-            } else if char == 'k' {
-                board.castling_rights.black.kingside = true;
-            } else if char == 'q' {
-                board.castling_rights.black.queenside = true;
+            let colour = if char.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+            let rooks = board.colour.get(colour) & board.role.rook & back_rank(colour);
+            let king = board.colour.get(colour) & board.role.king & back_rank(colour);
+            if (king == EMPTY_BITBOARD) | (char == '-') {
+                continue;
+            }
+            
+            let (side, rook) = match char.to_ascii_lowercase() {
+                'k' => (CastleSide::KingSide, outermost_rook(rooks, king, CastleSide::KingSide)),
+                'q' => (CastleSide::QueenSide, outermost_rook(rooks, king, CastleSide::QueenSide)),
+                file @ 'a'..='h' => {
+                    let rook = FILES[file as usize - 'a' as usize] & rooks;
+                    if rook.trailing_zeros() < king.trailing_zeros() {
+                        (CastleSide::KingSide, rook)
+                    } else {
+                        (CastleSide::QueenSide, rook)
+                    }
+                }
+                _ => continue,
+            };
+            
+            if rook != EMPTY_BITBOARD {
+                *board.castling_rights.get_mut(colour).get_mut(side) = Some(rook.get_file());
             }
         }
 
+        // Rooks given by their file, or a king or rook that isn't where it starts in standard chess, can only be Chess960
+        let file_letters = fen_vec[2].chars().any(|char| char.is_ascii_alphabetic() && !"KQkq".contains(char));
+        board.chess960 = file_letters | [Colour::White, Colour::Black].iter().any(|colour| {
+            let king = board.colour.get(*colour) & board.role.king & back_rank(*colour);
+            let standard_files = (king & FILES[4]) != EMPTY_BITBOARD;
+            return CASTLE_SIDES.iter().any(|side| match board.castling_rights.get(*colour).get(*side) {
+                Some(rook_file) => !standard_files | ((*rook_file != FILES[0]) & (*rook_file != FILES[7])),
+                None => false,
+            });
+        });
+
         board.en_passant_target_square = string_move_to_bitboard(fen_vec[3]);
         
-        // Ignoring half move and full moves for now
+        // The full move number sets the ply count, so a board set up mid game knows how far in it is. The half move clock is ignored,
+        // and the full move number is always the last field, after the check counter in Three-check FENs
+        let fullmove = if fen_vec.len() >= 6 { fen_vec[fen_vec.len() - 1].parse::<i32>().ok() } else { None };
+        if let Some(fullmove) = fullmove {
+            board.half_move_count = 2 * (fullmove.max(1) - 1) + if board.turn == Colour::Black { 1 } else { 0 };
+        }
 
         return board;
     }
-}
\ No newline at end of file
+    
+    // Writes the board as a FEN. The board doesn't keep a half move clock, so that is always 0. Three-check boards add their check counter
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        
+        for rank in (0..8).rev() {
+            let mut empty_squares = 0;
+            for file in (0..8).rev() {
+                let square = Bitboard(1 << (rank * 8 + file));
+                match (get_role(self, &square), get_colour(self, &square)) {
+                    (Some(role), Some(colour)) => {
+                        if empty_squares > 0 {
+                            placement.push_str(&empty_squares.to_string());
+                            empty_squares = 0;
+                        }
+                        let piece = match role {
+                            Role::Pawn => 'p',
+                            Role::Knight => 'n',
+                            Role::Bishop => 'b',
+                            Role::Rook => 'r',
+                            Role::Queen => 'q',
+                            Role::King => 'k',
+                        };
+                        match colour {
+                            Colour::White => placement.push(piece.to_ascii_uppercase()),
+                            Colour::Black => placement.push(piece),
+                        }
+                    }
+                    _ => empty_squares += 1,
+                }
+            }
+            if empty_squares > 0 {
+                placement.push_str(&empty_squares.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+        
+        let turn = match self.turn {
+            Colour::White => "w",
+            Colour::Black => "b",
+        };
+        
+        // Written as X-FEN, which is the same as standard FEN unless a Chess960 rook isn't the outermost one on its side
+        let mut castling = String::new();
+        for colour in [Colour::White, Colour::Black] {
+            let rooks = self.colour.get(colour) & self.role.rook & back_rank(colour);
+            let king = self.colour.get(colour) & self.role.king & back_rank(colour);
+            for side in CASTLE_SIDES {
+                let Some(rook) = self.castle_rook_square(colour, side) else {
+                    continue;
+                };
+                let letter = if rook == outermost_rook(rooks, king, side) {
+                    match side {
+                        CastleSide::KingSide => 'k',
+                        CastleSide::QueenSide => 'q',
+                    }
+                } else {
+                    get_square_string(rook).chars().next().unwrap()
+                };
+                match colour {
+                    Colour::White => castling.push(letter.to_ascii_uppercase()),
+                    Colour::Black => castling.push(letter),
+                }
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        
+        let en_passant = if self.en_passant_target_square == EMPTY_BITBOARD {
+            "-".to_string()
+        } else {
+            bitboard_to_string_move(self.en_passant_target_square).to_lowercase()
+        };
+        
+        // Three-check writes the checks each side has left after the en passant square, the way lichess does
+        if self.variant == Variant::ThreeCheck {
+            return format!("{} {} {} {} {}+{} 0 {}", placement, turn, castling, en_passant, self.remaining_checks.white, self.remaining_checks.black, self.half_move_count / 2 + 1);
+        }
+        
+        return format!("{} {} {} {} 0 {}", placement, turn, castling, en_passant, self.half_move_count / 2 + 1);
+    }
+}
+
+// The rook furthest from the king on one side of it. Kingside is towards the H file, which is the low bits
+fn outermost_rook(rooks: Bitboard, king: Bitboard, side: CastleSide) -> Bitboard {
+    let outside = match side {
+        CastleSide::KingSide => rooks.0 & (king.0 - 1),
+        CastleSide::QueenSide => rooks.0 & !(king.0 | (king.0 - 1)),
+    };
+    if outside == 0 {
+        return EMPTY_BITBOARD;
+    }
+    match side {
+        CastleSide::KingSide => return Bitboard(1 << outside.trailing_zeros()),
+        CastleSide::QueenSide => return Bitboard(1 << (63 - outside.leading_zeros())),
+    }
+}
+
+// Checks a FEN is well formed before it is given to from_fen, which assumes it is. Needs the placement, side to move, castling and en
+// passant fields, with one king for each side and no pawns on the first or last rank. The clocks are optional
+pub fn validate_fen(fen: &str) -> Result<(), String> {
+    return validate_variant_fen(fen, Variant::Standard);
+}
+
+// The same checks for a variant's FEN. Horde has no white king and white pawns can be on the first rank, Antichess can have any number
+// of kings, and Three-check FENs can have a check counter, which is checked and then left out of the rest
+pub fn validate_variant_fen(fen: &str, variant: Variant) -> Result<(), String> {
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    if variant == Variant::ThreeCheck {
+        let counters: Vec<usize> = (4..fields.len()).filter(|index| fields[*index].contains('+')).collect();
+        if (counters.len() > 1) || ((counters.len() == 1) && parse_check_counter(fen).is_none()) {
+            return Err(format!("Invalid check counter in FEN {}", fen));
+        }
+        if let Some(index) = counters.first() {
+            fields.remove(*index);
+        }
+    }
+    if (fields.len() < 4) || (fields.len() > 6) {
+        return Err(format!("Expected 4 to 6 fields in FEN {}", fen));
+    }
+    
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(format!("Expected 8 ranks in FEN {}", fen));
+    }
+    for (index, rank) in ranks.iter().enumerate() {
+        let mut squares = 0;
+        for char in rank.chars() {
+            match char {
+                '1'..='8' => squares += char.to_digit(10).unwrap(),
+                'P' if (index == 7) & (variant == Variant::Horde) => squares += 1,
+                'p' | 'P' if (index == 0) | (index == 7) => return Err(format!("Pawn on the first or last rank in FEN {}", fen)),
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => squares += 1,
+                _ => return Err(format!("Unexpected character {} in FEN {}", char, fen)),
+            }
+        }
+        if squares != 8 {
+            return Err(format!("Rank {} doesn't have 8 squares in FEN {}", 8 - index, fen));
+        }
+    }
+    let (white_kings, black_kings) = (fields[0].matches('K').count(), fields[0].matches('k').count());
+    match variant {
+        Variant::Antichess => (),
+        Variant::Horde if (white_kings != 0) | (black_kings != 1) => return Err(format!("Expected a black king and no white king in FEN {}", fen)),
+        Variant::Horde => (),
+        _ if (white_kings != 1) | (black_kings != 1) => return Err(format!("Expected one king for each side in FEN {}", fen)),
+        _ => (),
+    }
+    
+    if (fields[1] != "w") && (fields[1] != "b") {
+        return Err(format!("Side to move must be w or b in FEN {}", fen));
+    }
+    if (fields[2] != "-") && !fields[2].chars().all(|char| matches!(char.to_ascii_lowercase(), 'k' | 'q' | 'a'..='h')) {
+        return Err(format!("Invalid castling rights in FEN {}", fen));
+    }
+    if (fields[3] != "-") && ((fields[3].len() != 2) || !matches!(&fields[3][1..], "3" | "6") || (string_move_to_bitboard(fields[3]) == EMPTY_BITBOARD)) {
+        return Err(format!("Invalid en passant square in FEN {}", fen));
+    }
+    if fields[4..].iter().any(|clock| clock.parse::<u32>().is_err()) {
+        return Err(format!("Invalid move counters in FEN {}", fen));
+    }
+    
+    return Ok(());
+}
+
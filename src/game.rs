@@ -0,0 +1,454 @@
+use crate::board::Board;
+use crate::colour::Colour;
+use crate::fen::validate_variant_fen;
+use crate::movegen::get_legal_moves;
+use crate::mv::{IllegalMove, Move};
+use crate::pgn::PgnGame;
+use crate::role::Role;
+use crate::san::to_san;
+use crate::uci::to_uci;
+use crate::variant::Variant;
+use std::time::Duration;
+
+// Why a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoves,
+    InsufficientMaterial,
+    Resignation,
+    Timeout,
+    // Called by whoever is running the game, like a match runner giving up a lost position or agreeing a dead draw
+    Adjudication,
+    // Won by a variant's own rules, like a king reaching the hill or an Antichess player running out of moves
+    VariantWin,
+}
+
+impl Termination {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Termination::Checkmate => return "checkmate",
+            Termination::Stalemate => return "stalemate",
+            Termination::ThreefoldRepetition => return "threefold_repetition",
+            Termination::FiftyMoves => return "fifty_moves",
+            Termination::InsufficientMaterial => return "insufficient_material",
+            Termination::Resignation => return "resignation",
+            Termination::Timeout => return "timeout",
+            Termination::Adjudication => return "adjudication",
+            Termination::VariantWin => return "variant_win",
+        }
+    }
+}
+
+// How a game ended. The winner is None for a draw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outcome {
+    pub termination: Termination,
+    pub winner: Option<Colour>,
+}
+
+impl Outcome {
+    // "1-0", "0-1" or "1/2-1/2"
+    pub fn result(&self) -> &'static str {
+        match self.winner {
+            Some(Colour::White) => return "1-0",
+            Some(Colour::Black) => return "0-1",
+            None => return "1/2-1/2",
+        }
+    }
+}
+
+// A game from a starting position, keeping everything the board doesn't: the moves played, the position before each of them so they can
+// be taken back, the clock after each move, and the keys of every position for spotting repetitions
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub starting_position: Board,
+    pub board: Board,
+    pub moves: Vec<Move>,
+    // Time left on the clock of the side that made each move, if it is known
+    pub clocks: Vec<Option<Duration>>,
+    // Zobrist keys of every position so far, including the starting position and the current one
+    pub keys: Vec<u64>,
+    // The board before each move, and the fifty move rule clock before each move
+    boards: Vec<Board>,
+    halfmove_clocks: Vec<u32>,
+    // Plies since the last capture or pawn move, and the full move number, which the board doesn't track
+    halfmove_clock: u32,
+    starting_fullmove_number: u32,
+    // Set when a game ends by resignation or on time, which nothing on the board shows
+    ended: Option<Outcome>,
+}
+
+impl Game {
+    pub fn new(board: Board) -> Game {
+        let key = board.zobrist_hash();
+        return Game {
+            starting_position: board.clone(),
+            board,
+            moves: Vec::new(),
+            clocks: Vec::new(),
+            keys: vec![key],
+            boards: Vec::new(),
+            halfmove_clocks: Vec::new(),
+            halfmove_clock: 0,
+            starting_fullmove_number: 1,
+            ended: None,
+        };
+    }
+
+    pub fn starting_position() -> Game {
+        return Game::new(Board::starting_position());
+    }
+
+    pub fn variant_starting_position(variant: Variant) -> Game {
+        return Game::new(variant.starting_position());
+    }
+
+    // A game starting from a FEN, keeping its halfmove clock and full move number
+    pub fn from_fen(fen: &str) -> Result<Game, String> {
+        return Game::from_variant_fen(fen, Variant::Standard);
+    }
+
+    pub fn from_variant_fen(fen: &str, variant: Variant) -> Result<Game, String> {
+        validate_variant_fen(fen, variant)?;
+        // The Three-check counter isn't one of the clocks
+        let fields: Vec<&str> = fen.split_whitespace().filter(|field| !field.contains('+')).collect();
+        let mut game = Game::new(Board::from_variant_fen(fen.to_string(), variant));
+        game.halfmove_clock = fields.get(4).and_then(|clock| clock.parse().ok()).unwrap_or(0);
+        game.starting_fullmove_number = fields.get(5).and_then(|number| number.parse().ok()).unwrap_or(1).max(1);
+        return Ok(game);
+    }
+
+    // Plies played since the starting position
+    pub fn ply(&self) -> usize {
+        return self.moves.len();
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        return self.halfmove_clock;
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        let black_started = self.starting_position.turn == Colour::Black;
+        return self.starting_fullmove_number + ((self.ply() as u32 + black_started as u32) / 2);
+    }
+
+    pub fn fen(&self) -> String {
+        return format!("{} {} {}", position_fields(&self.board), self.halfmove_clock, self.fullmove_number());
+    }
+
+    pub fn push(&mut self, mv: Move) -> Result<(), IllegalMove> {
+        return self.push_with_clock(mv, None);
+    }
+
+    // Plays a move along with the time left on the mover's clock after it
+    pub fn push_with_clock(&mut self, mv: Move, clock: Option<Duration>) -> Result<(), IllegalMove> {
+        if self.ended.is_some() || !get_legal_moves(&self.board).contains(&mv) {
+            return Err(IllegalMove::new(&self.board, to_uci(Some(mv), self.board.chess960)));
+        }
+
+        self.boards.push(self.board.clone());
+        self.halfmove_clocks.push(self.halfmove_clock);
+        if mv.capture.is_some() | (mv.role == Some(Role::Pawn)) {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        self.board.play_unsafe(mv);
+        self.moves.push(mv);
+        self.clocks.push(clock);
+        self.keys.push(self.board.zobrist_hash());
+        return Ok(());
+    }
+
+    // Takes back the last move and returns it. A resignation or loss on time is taken back with it
+    pub fn pop(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        self.board = self.boards.pop().unwrap();
+        self.halfmove_clock = self.halfmove_clocks.pop().unwrap();
+        self.clocks.pop();
+        self.keys.pop();
+        self.ended = None;
+        return Some(mv);
+    }
+
+    // Takes back moves until only the first ply of them are left
+    pub fn undo_to(&mut self, ply: usize) {
+        while self.ply() > ply {
+            self.pop();
+        }
+    }
+
+    pub fn peek(&self) -> Option<Move> {
+        return self.moves.last().copied();
+    }
+
+    pub fn resign(&mut self, colour: Colour) {
+        if self.outcome().is_none() {
+            self.ended = Some(Outcome { termination: Termination::Resignation, winner: Some(colour.opposite()) });
+        }
+    }
+
+    // Running out of time loses, unless the opponent has too little left to ever mate with, like a lone king or a king and a minor piece
+    pub fn timeout(&mut self, colour: Colour) {
+        if self.outcome().is_none() {
+            let winner = if self.board.has_insufficient_material(colour.opposite()) { None } else { Some(colour.opposite()) };
+            self.ended = Some(Outcome { termination: Termination::Timeout, winner });
+        }
+    }
+
+    // Ends the game with a result decided outside the rules, a win for the winner or a draw when there is none
+    pub fn adjudicate(&mut self, winner: Option<Colour>) {
+        if self.outcome().is_none() {
+            self.ended = Some(Outcome { termination: Termination::Adjudication, winner });
+        }
+    }
+
+    // Whether the current position has come up count times since the last capture or pawn move
+    pub fn is_repetition(&self, count: usize) -> bool {
+        let current = self.board.zobrist_hash();
+        let reversible = &self.keys[self.keys.len().saturating_sub(self.halfmove_clock as usize + 1)..];
+        return reversible.iter().filter(|key| **key == current).count() >= count;
+    }
+
+    // How the game ended, or None while it is still going
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.ended.is_some() {
+            return self.ended;
+        }
+        if let Some(winner) = self.board.variant.winner(&self.board) {
+            return Some(Outcome { termination: Termination::VariantWin, winner: Some(winner) });
+        }
+        if get_legal_moves(&self.board).is_empty() {
+            // Running out of moves wins Antichess, whether or not there are pieces left
+            if self.board.variant == Variant::Antichess {
+                return Some(Outcome { termination: Termination::VariantWin, winner: Some(self.board.turn) });
+            }
+            if self.board.is_check(&self.board.turn) {
+                return Some(Outcome { termination: Termination::Checkmate, winner: Some(self.board.turn.opposite()) });
+            }
+            return Some(Outcome { termination: Termination::Stalemate, winner: None });
+        }
+        if self.board.is_insufficient_material() {
+            return Some(Outcome { termination: Termination::InsufficientMaterial, winner: None });
+        }
+        if self.halfmove_clock >= 100 {
+            return Some(Outcome { termination: Termination::FiftyMoves, winner: None });
+        }
+        if self.is_repetition(3) {
+            return Some(Outcome { termination: Termination::ThreefoldRepetition, winner: None });
+        }
+        return None;
+    }
+
+    // The PGN result, "*" while the game is still going
+    pub fn result(&self) -> &'static str {
+        return self.outcome().map(|outcome| outcome.result()).unwrap_or("*");
+    }
+
+    // The moves in standard algebraic notation
+    pub fn san_moves(&self) -> Vec<String> {
+        let mut board = self.starting_position.clone();
+        let mut san_moves: Vec<String> = Vec::new();
+        for mv in &self.moves {
+            san_moves.push(to_san(&board, *mv));
+            board.play_unsafe(*mv);
+        }
+        return san_moves;
+    }
+
+    // The game as PGN. Tags given here replace the Seven Tag Roster defaults or are added after them, and clock times are written as
+    // [%clk] comments like lichess does
+    pub fn to_pgn(&self, tags: &[(String, String)]) -> String {
+        let mut pgn_tags: Vec<(String, String)> = [("Event", "?"), ("Site", "?"), ("Date", "????.??.??"), ("Round", "?"), ("White", "?"), ("Black", "?")]
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        for (name, value) in tags {
+            match pgn_tags.iter_mut().find(|(tag, _)| tag == name) {
+                Some(tag) => tag.1 = value.clone(),
+                None => pgn_tags.push((name.clone(), value.clone())),
+            }
+        }
+        pgn_tags.retain(|(name, _)| !matches!(name.as_str(), "Result" | "Variant" | "SetUp" | "FEN" | "Termination"));
+        pgn_tags.insert(6, ("Result".to_string(), self.result().to_string()));
+
+        let variant = self.starting_position.variant;
+        if variant != Variant::Standard {
+            pgn_tags.push(("Variant".to_string(), variant.pgn_name().to_string()));
+        }
+        let starting_fen = self.starting_fen();
+        if starting_fen != variant.starting_fen() {
+            pgn_tags.push(("SetUp".to_string(), "1".to_string()));
+            pgn_tags.push(("FEN".to_string(), starting_fen));
+        }
+        // The PGN standard only tells losses on time and adjudications apart from games that ended normally
+        if let Some(outcome) = self.outcome() {
+            let termination = match outcome.termination {
+                Termination::Timeout => "time forfeit",
+                Termination::Adjudication => "adjudication",
+                _ => "normal",
+            };
+            pgn_tags.push(("Termination".to_string(), termination.to_string()));
+        }
+
+        let game = PgnGame {
+            tags: pgn_tags,
+            moves: self.san_moves(),
+            comments: self.clocks.iter().map(|clock| clock.map(clock_comment)).collect(),
+            result: self.result().to_string(),
+        };
+        return game.to_pgn();
+    }
+
+    // The FEN of the starting position with its halfmove clock and full move number
+    pub fn starting_fen(&self) -> String {
+        let halfmove_clock = self.halfmove_clocks.first().copied().unwrap_or(self.halfmove_clock);
+        return format!("{} {} {}", position_fields(&self.starting_position), halfmove_clock, self.starting_fullmove_number);
+    }
+}
+
+// The board's FEN without the clocks it doesn't keep, which is everything but the last two fields
+fn position_fields(board: &Board) -> String {
+    let fen = board.to_fen();
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    return fields[..fields.len() - 2].join(" ");
+}
+
+fn clock_comment(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+    return format!("[%clk {}:{:02}:{:02}]", seconds / 3600, (seconds / 60) % 60, seconds % 60);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_pgn;
+    use crate::san::from_san;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for san in moves {
+            let mv = from_san(&game.board, san).unwrap_or_else(|| panic!("{} is not legal", san));
+            game.push(mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn checkmate_ends_the_game() {
+        let mut game = Game::starting_position();
+        play(&mut game, &["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(game.outcome(), Some(Outcome { termination: Termination::Checkmate, winner: Some(Colour::Black) }));
+        assert_eq!(game.result(), "0-1");
+        assert_eq!(game.san_moves(), vec!["f3", "e5", "g4", "Qh4#"]);
+    }
+
+    #[test]
+    fn reaching_the_hill_ends_the_game() {
+        let mut game = Game::from_variant_fen("8/8/8/8/8/4K3/8/k7 w - - 0 1", Variant::KingOfTheHill).unwrap();
+        let reply = from_san(&Board::from_fen("8/8/8/8/8/5K2/8/k7 b - - 1 1".to_string()), "Kb1").unwrap();
+        play(&mut game, &["Kf3"]);
+        assert_eq!(game.outcome(), None);
+        game.push(reply).unwrap();
+
+        play(&mut game, &["Ke4"]);
+        assert_eq!(game.outcome(), Some(Outcome { termination: Termination::VariantWin, winner: Some(Colour::White) }));
+        assert_eq!(game.result(), "1-0");
+        assert!(get_legal_moves(&game.board).is_empty());
+        let reply = from_san(&Board::from_fen("8/8/8/8/4K3/8/8/1k6 b - - 3 2".to_string()), "Kc2").unwrap();
+        assert!(game.push(reply).is_err());
+
+        // The same king walk is nothing special in standard chess
+        let mut game = Game::from_fen("8/8/8/8/8/4K3/7P/k7 w - - 0 1").unwrap();
+        play(&mut game, &["Kf3", "Kb1", "Ke4", "Kc2"]);
+        assert_eq!(game.outcome(), None);
+    }
+
+    #[test]
+    fn illegal_moves_are_refused() {
+        let mut game = Game::starting_position();
+        let black_move = from_san(&Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1".to_string()), "e5").unwrap();
+        assert!(game.push(black_move).is_err());
+        assert_eq!(game.ply(), 0);
+
+        // Nothing can be played once the game is over
+        game.resign(Colour::White);
+        assert_eq!(game.result(), "0-1");
+        assert!(game.push(from_san(&game.board, "e4").unwrap()).is_err());
+    }
+
+    #[test]
+    fn pop_takes_back_moves_and_clocks() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w Q - 7 20").unwrap();
+        let start = game.fen();
+        play(&mut game, &["Ra2", "Kd7", "e4"]);
+        assert_eq!(game.halfmove_clock(), 0);
+        assert_eq!(game.fullmove_number(), 21);
+        game.pop();
+        assert_eq!(game.halfmove_clock(), 9);
+        assert_eq!(game.fen(), "8/3k4/8/8/8/8/R3P3/4K3 w - - 9 21");
+        game.undo_to(0);
+        assert_eq!(game.fen(), start);
+        assert_eq!(game.pop(), None);
+    }
+
+    #[test]
+    fn draws_by_the_rules() {
+        let mut game = Game::starting_position();
+        play(&mut game, &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"]);
+        assert_eq!(game.outcome(), None);
+        play(&mut game, &["Ng8"]);
+        assert_eq!(game.outcome().map(|outcome| outcome.termination), Some(Termination::ThreefoldRepetition));
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        play(&mut game, &["Ra2"]);
+        assert_eq!(game.outcome().map(|outcome| outcome.termination), Some(Termination::FiftyMoves));
+
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.outcome().map(|outcome| outcome.termination), Some(Termination::Stalemate));
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(game.outcome().map(|outcome| outcome.termination), Some(Termination::InsufficientMaterial));
+    }
+
+    #[test]
+    fn pgn_round_trips() {
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 12").unwrap();
+        play(&mut game, &["O-O", "O-O-O", "Rf7"]);
+        game.adjudicate(Some(Colour::Black));
+        let pgn = game.to_pgn(&[("White".to_string(), "Engine \"A\"".to_string())]);
+
+        let parsed = &parse_pgn(&pgn)[0];
+        assert_eq!(parsed.tag("White"), Some("Engine \"A\""));
+        assert_eq!(parsed.tag("FEN"), Some("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 12"));
+        assert_eq!(parsed.tag("Termination"), Some("adjudication"));
+        assert_eq!(parsed.moves, game.san_moves());
+        assert_eq!(parsed.result, "0-1");
+        assert!(pgn.contains("12... O-O 13. O-O-O Rf7 0-1"));
+    }
+
+    fn timeout_winner(fen: &str, colour: Colour) -> Option<Colour> {
+        let mut game = Game::from_fen(fen).unwrap();
+        game.timeout(colour);
+        let outcome = game.outcome().unwrap();
+        assert_eq!(outcome.termination, Termination::Timeout);
+        return outcome.winner;
+    }
+
+    #[test]
+    fn timeout_is_a_draw_when_the_opponent_cannot_mate() {
+        // White flags against a lone king, a king and bishop and a king and knight
+        assert_eq!(timeout_winner("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", Colour::White), None);
+        assert_eq!(timeout_winner("4k3/8/3b4/8/8/8/4P3/4K3 w - - 0 1", Colour::White), None);
+        assert_eq!(timeout_winner("4k3/8/3n4/8/8/8/4P3/4K3 w - - 0 1", Colour::White), None);
+    }
+
+    #[test]
+    fn timeout_loses_when_the_opponent_can_mate() {
+        assert_eq!(timeout_winner("4k3/4p3/8/8/8/8/8/4K3 w - - 0 1", Colour::White), Some(Colour::Black));
+        assert_eq!(timeout_winner("4k3/8/2nb4/8/8/8/8/4K3 w - - 0 1", Colour::White), Some(Colour::Black));
+        assert_eq!(timeout_winner("4k3/8/8/8/8/8/8/R3K3 b - - 0 1", Colour::Black), Some(Colour::White));
+    }
+}
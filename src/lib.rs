@@ -8,8 +8,16 @@ pub mod bitboard;
 pub mod magic;
 pub mod castle;
 pub mod engine;
+pub mod epd;
 pub mod fen;
+pub mod game;
 pub mod perft;
+pub mod pgn;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod san;
 pub mod uci;
+pub mod variant;
+pub mod zobrist;
 
 // maturin develop --features "extension-module" --release
\ No newline at end of file
@@ -3,7 +3,7 @@
 use arrayvec::ArrayVec;
 use chesslibrary::bitboard::EMPTY_BITBOARD;
 use chesslibrary::board::Board;
-use chesslibrary::engine::search::{pick_move, NODE_COUNT};
+use chesslibrary::engine::search::NODE_COUNT;
 use chesslibrary::engine::eval::evaluate;
 use chesslibrary::movegen::{get_legal_moves, get_white_moves};
 use chesslibrary::bitboard::{Bitboard, bitboard_to_string_move};
@@ -12,14 +12,23 @@ use chesslibrary::perft::perft_test;
 use chesslibrary::role::Role;
 use chesslibrary::square::{Square, SECOND_RANK, SEVENTH_RANK, SIXTH_RANK, THIRD_RANK};
 use chesslibrary::mv::Move;
-use chesslibrary::uci::to_uci;
+use chesslibrary::uci::{to_uci, uci_loop};
 use chesslibrary::square::{FILE_A,FILE_B,FILE_C,FILE_D,FILE_E,FILE_F,FILE_G,FILE_H};
 
 fn main() {
+    // Runs as a UCI engine unless asked for the benchmark
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench();
+    } else {
+        uci_loop();
+    }
+}
+
+fn bench() {
     use std::time::Instant;
     let now = Instant::now();
     
-    let mut board = Board::starting_position();
+    let board = Board::starting_position();
     
     board.display_board();
     
@@ -38,4 +47,4 @@ fn main() {
     println!("Elapsed: {:.2?}", elapsed);
     println!("Nodes evaluated per second: {:.2?}", boards_per_second);
     //println!("Nodes evaluated: {:?}", NODE_COUNT.load(std::sync::atomic::Ordering::Relaxed));
-}
\ No newline at end of file
+}
@@ -1,11 +1,12 @@
 use crate::board::{Board};
-use crate::castle::{BLACK_KINGSIDE_CASTLE_CHECK_SQUARES, BLACK_KINGSIDE_CASTLE_UNOCCUPIED_SQUARES, BLACK_QUEENSIDE_CASTLE_CHECK_SQUARES, BLACK_QUEENSIDE_CASTLE_UNOCCUPIED_SQUARES, WHITE_KINGSIDE_CASTLE_CHECK_SQUARES, WHITE_KINGSIDE_CASTLE_UNOCCUPIED_SQUARES, WHITE_QUEENSIDE_CASTLE_CHECK_SQUARES, WHITE_QUEENSIDE_CASTLE_UNOCCUPIED_SQUARES};
+use crate::castle::CASTLE_SIDES;
 use crate::colour::{Colour};
 use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
 use crate::mv::Move;
-use crate::square::{Square, EIGHTH_RANK, FILE_A, FILE_H, FIRST_RANK, SECOND_RANK, SEVENTH_RANK};
+use crate::square::{EIGHTH_RANK, FILE_A, FILE_H, FIRST_RANK, SECOND_RANK, SEVENTH_RANK};
 use crate::magic::{bishop_attacks, rook_attacks};
 use crate::role::Role;
+use crate::variant::{get_antichess_moves, is_atomic_move_legal, Variant};
 use arrayvec::ArrayVec;
 
 // H1, G1, F1, E1, D1, C1, B1, A1
@@ -30,10 +31,17 @@ const WHITE_PAWN_H_FILE_ATTACK: i32 = 9;
 const BLACK_PAWN_A_FILE_ATTACK: i32 = 9;
 const BLACK_PAWN_H_FILE_ATTACK: i32 = 7;
 
-// Generates a vector of legal moves for the side to move
+// Generates a vector of legal moves for the side to move. There are none once a variant's own rules have ended the game
 pub fn get_legal_moves(board: &Board) -> ArrayVec<Move, 218> {
     let mut move_vector = ArrayVec::<Move, 218>::new();
     
+    if board.variant.winner(board).is_some() {
+        return move_vector;
+    }
+    if board.variant == Variant::Antichess {
+        return get_antichess_moves(board);
+    }
+    
     match board.turn {
         Colour::White => get_white_moves(board, &mut move_vector),
         Colour::Black => get_black_moves(board, &mut move_vector),
@@ -44,7 +52,11 @@ pub fn get_legal_moves(board: &Board) -> ArrayVec<Move, 218> {
     for mv in move_vector {
         let mut board_copy = board.clone();
         board_copy.play_unsafe(mv);
-        if board_copy.is_check(&board.turn) == false {
+        let legal = match board.variant {
+            Variant::Atomic => is_atomic_move_legal(&board_copy, board.turn),
+            _ => !board_copy.is_check(&board.turn),
+        };
+        if legal {
             legal_move_vector.push(mv);
         }
     }
@@ -96,53 +108,84 @@ pub fn get_black_attacks(board: &Board) -> Bitboard {
     return attack_bitboard;
 }
 
-// Accepts and mutates an ArrayVec with all of whites legal king moves
+// Accepts and mutates an ArrayVec with all of whites legal king moves. Antichess can have any number of kings, and Horde none
 pub fn get_white_king_moves(board: &Board, move_vector: &mut ArrayVec<Move, 218>) {
-    let king_bitboard: Bitboard = board.colour.white & board.role.king;
     let turn_colour: Bitboard = board.colour.white;
     
-    let white_king_attacks = get_king_attacks(board, &(board.colour.white & board.role.king));
-    for single_move in white_king_attacks.get_component_bitboards() {
-        if (single_move & turn_colour).count_ones() == 0 {
-            move_vector.push(Move::new(&board, &king_bitboard, &single_move, &EMPTY_BITBOARD, false, false, None));
-        }
+    // Kings can't capture in Atomic, since they would blow themselves up
+    let mut blocked: Bitboard = turn_colour;
+    if board.variant == Variant::Atomic {
+        blocked |= board.colour.black;
     }
     
-    let black_attack_bitboard = get_black_attacks(board);
-    if (board.castling_rights.white.kingside == true) & (((WHITE_KINGSIDE_CASTLE_UNOCCUPIED_SQUARES & board.occupied) == EMPTY_BITBOARD) & ((WHITE_KINGSIDE_CASTLE_CHECK_SQUARES & black_attack_bitboard) == EMPTY_BITBOARD)) {
-        move_vector.push(Move::new(&board, &king_bitboard, &Square::G1, &EMPTY_BITBOARD, false, true, None))
+    for king_bitboard in (board.colour.white & board.role.king).get_component_bitboards() {
+        for single_move in get_king_attacks(board, &king_bitboard).get_component_bitboards() {
+            if (single_move & blocked).count_ones() == 0 {
+                move_vector.push(Move::new(board, &king_bitboard, &single_move, &EMPTY_BITBOARD, false, false, None));
+            }
+        }
     }
     
-    if (board.castling_rights.white.queenside == true) & (((WHITE_QUEENSIDE_CASTLE_UNOCCUPIED_SQUARES & board.occupied) == EMPTY_BITBOARD) & ((WHITE_QUEENSIDE_CASTLE_CHECK_SQUARES & black_attack_bitboard) == EMPTY_BITBOARD)) {
-        move_vector.push(Move::new(&board, &king_bitboard, &Square::C1, &EMPTY_BITBOARD, false, true, None))
+    // In Atomic the king can pass over squares next to the enemy king, since taking it there would blow up the enemy king too
+    let mut opponent_attacks = get_black_attacks(board);
+    if board.variant == Variant::Atomic {
+        opponent_attacks &= !get_king_attacks(board, &(board.colour.black & board.role.king));
     }
+    get_castle_moves(board, &Colour::White, opponent_attacks, move_vector);
 }
 
-// Accepts and mutates an ArrayVec with all of blacks legal king moves
+// Accepts and mutates an ArrayVec with all of blacks legal king moves. Antichess can have any number of kings, and Horde none
 pub fn get_black_king_moves(board: &Board, move_vector: &mut ArrayVec<Move, 218>) {
-    let king_bitboard: Bitboard = board.colour.black & board.role.king;
     let turn_colour: Bitboard = board.colour.black;
     
-    let black_king_attacks = get_king_attacks(board, &(board.colour.black & board.role.king));
-    for single_move in black_king_attacks.get_component_bitboards() {
-        if (single_move & turn_colour).count_ones() == 0 {
-            move_vector.push(Move::new(&board, &king_bitboard, &single_move, &EMPTY_BITBOARD, false, false, None));
-        }
+    // Kings can't capture in Atomic, since they would blow themselves up
+    let mut blocked: Bitboard = turn_colour;
+    if board.variant == Variant::Atomic {
+        blocked |= board.colour.white;
     }
     
-    let white_attack_bitboard = get_white_attacks(board);
-    if (board.castling_rights.black.kingside == true) & (((BLACK_KINGSIDE_CASTLE_UNOCCUPIED_SQUARES & board.occupied) == EMPTY_BITBOARD) & ((BLACK_KINGSIDE_CASTLE_CHECK_SQUARES & white_attack_bitboard) == EMPTY_BITBOARD)) {
-        move_vector.push(Move::new(&board, &king_bitboard, &Square::G8, &EMPTY_BITBOARD, false, true, None))
+    for king_bitboard in (board.colour.black & board.role.king).get_component_bitboards() {
+        for single_move in get_king_attacks(board, &king_bitboard).get_component_bitboards() {
+            if (single_move & blocked).count_ones() == 0 {
+                move_vector.push(Move::new(board, &king_bitboard, &single_move, &EMPTY_BITBOARD, false, false, None));
+            }
+        }
     }
     
-    if (board.castling_rights.black.queenside == true) & (((BLACK_QUEENSIDE_CASTLE_UNOCCUPIED_SQUARES & board.occupied) == EMPTY_BITBOARD) & ((BLACK_QUEENSIDE_CASTLE_CHECK_SQUARES & white_attack_bitboard) == EMPTY_BITBOARD)) {
-        move_vector.push(Move::new(&board, &king_bitboard, &Square::C8, &EMPTY_BITBOARD, false, true, None))
+    // In Atomic the king can pass over squares next to the enemy king, since taking it there would blow up the enemy king too
+    let mut opponent_attacks = get_white_attacks(board);
+    if board.variant == Variant::Atomic {
+        opponent_attacks &= !get_king_attacks(board, &(board.colour.white & board.role.king));
     }
+    get_castle_moves(board, &Colour::Black, opponent_attacks, move_vector);
 }
 
-// Returns a Bitboard with all of a specified colours king attacks
+// Accepts and mutates an ArrayVec with a colours castling moves, which are written as the king taking its own rook. Works for
+// Chess960 too, where the king and rooks can start anywhere on the back rank
+pub fn get_castle_moves(board: &Board, colour: &Colour, opponent_attacks: Bitboard, move_vector: &mut ArrayVec<Move, 218>) {
+    for side in CASTLE_SIDES {
+        let Some((must_be_empty, king_path)) = board.castle_path(*colour, side) else {
+            continue;
+        };
+        if ((must_be_empty & board.occupied) == EMPTY_BITBOARD) & ((king_path & opponent_attacks) == EMPTY_BITBOARD) {
+            let king_bitboard = board.colour.get(*colour) & board.role.king;
+            let rook_square = board.castle_rook_square(*colour, side).unwrap();
+            let mut mv = Move::new(board, &king_bitboard, &rook_square, &EMPTY_BITBOARD, false, true, None);
+            mv.capture = None;
+            move_vector.push(mv);
+        }
+    }
+}
+
+// Returns a Bitboard with all of the squares the kings given attack
 pub fn get_king_attacks(_board: &Board, king_bitboard: &Bitboard) -> Bitboard {
-    return KING_ATTACKS[king_bitboard.0.trailing_zeros() as usize];
+    let mut king_attack_bitboard = Bitboard(0);
+    
+    for individual_king in king_bitboard.get_component_bitboards() {
+        king_attack_bitboard |= KING_ATTACKS[individual_king.0.trailing_zeros() as usize];
+    }
+    
+    return king_attack_bitboard;
 }
 
 const fn bootstrap_king_attacks() -> [Bitboard; 64] {
@@ -229,6 +272,11 @@ pub fn get_white_pawn_moves(board: &Board, move_vector: &mut ArrayVec<Move,218>)
             if ((single_pawn & SECOND_RANK) != EMPTY_BITBOARD) & ((two_forward & !board.occupied) != EMPTY_BITBOARD) {
                 move_vector.push(Move::new(&board, &single_pawn, &two_forward, &one_forward, false, false, None));
             }
+            
+            // Horde pawns on the first rank can move two squares too, but can't be taken en passant when they do
+            if (board.variant == Variant::Horde) & ((single_pawn & FIRST_RANK) != EMPTY_BITBOARD) & ((two_forward & !board.occupied) != EMPTY_BITBOARD) {
+                move_vector.push(Move::new(board, &single_pawn, &two_forward, &EMPTY_BITBOARD, false, false, None));
+            }
         }
     }
 }
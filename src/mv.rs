@@ -2,6 +2,8 @@ use crate::bitboard::Bitboard;
 use crate::role::{Role, get_role};
 use crate::board::Board;
 use crate::colour::{Colour, get_colour};
+use std::error::Error;
+use std::fmt;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Move {
@@ -16,6 +18,28 @@ pub struct Move {
     pub capture: Option<Role>,
 }
 
+// A move that can't be played in a position, either because it isn't legal or because it couldn't be read
+#[derive(Debug, Clone, PartialEq)]
+pub struct IllegalMove {
+    // The move as it was given, or as UCI if it was given as a Move
+    pub mv: String,
+    pub fen: String,
+}
+
+impl IllegalMove {
+    pub fn new(board: &Board, mv: String) -> IllegalMove {
+        return IllegalMove { mv, fen: board.to_fen() };
+    }
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "Illegal move {} in {}", self.mv, self.fen);
+    }
+}
+
+impl Error for IllegalMove {}
+
 pub const EMPTY_MOVE: Move = Move {
     role: None,
     colour: None,
@@ -58,7 +82,7 @@ impl Move {
 pub fn is_check(board: &Board, mv: Move) -> bool {
     
     let mut board_copy = board.clone();
-    board_copy.play(mv);
+    board_copy.play_unsafe(mv);
     
     return board_copy.is_check(&board_copy.turn)
 
@@ -0,0 +1,238 @@
+// A game read from PGN. Moves are left as SAN text so they can be played against the board with from_san
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    // A comment after each move, written by to_pgn. Comments are skipped when reading, so games from parse_pgn have none
+    pub comments: Vec<Option<String>>,
+    // "1-0", "0-1", "1/2-1/2" or "*"
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        return self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+    }
+
+    // Writes the game in PGN export format, with the tags in the order they are in and the movetext wrapped at 80 columns. Move numbers
+    // start from the FEN tag if there is one
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in &self.tags {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        pgn.push('\n');
+
+        let fen_fields: Vec<&str> = self.tag("FEN").map(|fen| fen.split_whitespace().collect()).unwrap_or_default();
+        let mut white_to_move = fen_fields.get(1) != Some(&"b");
+        let mut move_number: u32 = fen_fields.get(5).and_then(|number| number.parse().ok()).unwrap_or(1);
+
+        // Black's moves get their number written as "12..." when they don't follow straight on from white's
+        let mut tokens: Vec<String> = Vec::new();
+        let mut needs_number = true;
+        for (index, mv) in self.moves.iter().enumerate() {
+            if white_to_move {
+                tokens.push(format!("{}.", move_number));
+            } else if needs_number {
+                tokens.push(format!("{}...", move_number));
+            }
+            tokens.push(mv.clone());
+            needs_number = false;
+            if let Some(Some(comment)) = self.comments.get(index) {
+                tokens.push(format!("{{{}}}", comment));
+                needs_number = true;
+            }
+            if !white_to_move {
+                move_number += 1;
+            }
+            white_to_move = !white_to_move;
+        }
+        tokens.push(self.result.clone());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && (line.len() + 1 + token.len() > 80) {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        return pgn;
+    }
+}
+
+// Reads every game from PGN text. Comments, variations, numeric annotation glyphs and move numbers are skipped, so only the main line
+// is kept
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games: Vec<PgnGame> = Vec::new();
+    let mut game = new_game();
+    let mut in_movetext = false;
+
+    // Comments and variations can run over several lines, so these carry on from one line to the next
+    let mut comment_depth = 0;
+    let mut variation_depth = 0;
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        if (comment_depth == 0) && (variation_depth == 0) && line.starts_with('[') {
+            // A tag after some moves means the last game had no result and a new one has started
+            if in_movetext {
+                games.push(game);
+                game = new_game();
+                in_movetext = false;
+            }
+            if let Some(tag) = parse_tag(line) {
+                game.tags.push(tag);
+            }
+            continue;
+        }
+
+        // Escaped lines and rest of line comments
+        if line.starts_with('%') {
+            continue;
+        }
+        let line = match line.find(';') {
+            Some(index) if (comment_depth == 0) => &line[..index],
+            _ => line,
+        };
+
+        let mut token = String::new();
+        for char in line.chars().chain(std::iter::once(' ')) {
+            if comment_depth > 0 {
+                if char == '}' {
+                    comment_depth = 0;
+                }
+                continue;
+            }
+            match char {
+                '{' => {
+                    comment_depth = 1;
+                    finish_token(&mut token, &mut game, &mut in_movetext, variation_depth, &mut games);
+                }
+                '(' => {
+                    finish_token(&mut token, &mut game, &mut in_movetext, variation_depth, &mut games);
+                    variation_depth += 1;
+                }
+                ')' => {
+                    finish_token(&mut token, &mut game, &mut in_movetext, variation_depth, &mut games);
+                    variation_depth = (variation_depth - 1).max(0);
+                }
+                ' ' | '\t' => finish_token(&mut token, &mut game, &mut in_movetext, variation_depth, &mut games),
+                _ => token.push(char),
+            }
+        }
+    }
+
+    if in_movetext | !game.tags.is_empty() {
+        games.push(game);
+    }
+
+    return games;
+}
+
+fn new_game() -> PgnGame {
+    return PgnGame { tags: Vec::new(), moves: Vec::new(), comments: Vec::new(), result: "*".to_string() };
+}
+
+// Parses a tag pair like [White "Carlsen, Magnus"]. Only the outer quotes are taken off, so a value ending in an escaped quote keeps it
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.trim_start_matches('[').trim_end_matches(']');
+    let (name, value) = inner.split_once(' ')?;
+    let value = value.trim();
+    let value = value.strip_prefix('"').unwrap_or(value);
+    let value = value.strip_suffix('"').unwrap_or(value);
+
+    // A backslash escapes the character after it, which is either a quote or another backslash
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(char),
+        }
+    }
+    return Some((name.to_string(), unescaped));
+}
+
+// Handles one whitespace separated token of movetext. A result ends the game
+fn finish_token(token: &mut String, game: &mut PgnGame, in_movetext: &mut bool, variation_depth: i32, games: &mut Vec<PgnGame>) {
+    if token.is_empty() {
+        return;
+    }
+    let text = std::mem::take(token);
+    if variation_depth > 0 {
+        return;
+    }
+    *in_movetext = true;
+
+    if matches!(text.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+        game.result = text;
+        games.push(std::mem::replace(game, new_game()));
+        *in_movetext = false;
+        return;
+    }
+
+    // Move numbers can be stuck to the move, like "1.e4" or "12...Nf6". Castling written as "0-0" has no dot, so is left alone
+    let mv = match text.rfind('.') {
+        Some(index) => &text[index + 1..],
+        None if text.chars().all(|char| char.is_ascii_digit()) => "",
+        None => &text,
+    };
+    if mv.is_empty() || mv.starts_with('$') {
+        return;
+    }
+    game.moves.push(mv.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tags_and_the_main_line() {
+        let text = "[Event \"Test \\\"1\\\"\"]\n[Result \"1-0\"]\n\n\
+                    1. e4 {best by test} e5 (1... c5 2. Nf3 (2. c3)) 2.Nf3 $1 Nc6; a rest of line comment\n\
+                    % an escaped line\n\
+                    3. Bb5 {a comment\nover two lines} 3... a6 1-0\n";
+        let games = parse_pgn(text);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tag("Event"), Some("Test \"1\""));
+        assert_eq!(games[0].moves, vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(games[0].result, "1-0");
+    }
+
+    #[test]
+    fn splits_games() {
+        // The second game has no result before the next one's tags start
+        let text = "[Round \"1\"]\n\n1. d4 d5 1/2-1/2\n\n[Round \"2\"]\n\n1. c4 0-0\n[Round \"3\"]\n\n1. O-O *\n";
+        let games = parse_pgn(text);
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].result, "1/2-1/2");
+        assert_eq!(games[1].moves, vec!["c4", "0-0"]);
+        assert_eq!(games[1].result, "*");
+        assert_eq!(games[2].tag("Round"), Some("3"));
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let game = PgnGame {
+            tags: vec![("White".to_string(), "A \\ B".to_string()), ("FEN".to_string(), "4k3/8/8/8/8/8/8/R3K3 b - - 0 30".to_string())],
+            moves: vec!["Kd7".to_string(), "Ra7+".to_string(), "Kc6".to_string()],
+            comments: vec![None, Some("[%clk 0:01:00]".to_string()), None],
+            result: "*".to_string(),
+        };
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("30... Kd7 31. Ra7+ {[%clk 0:01:00]} 31... Kc6 *"));
+
+        let read = &parse_pgn(&pgn)[0];
+        assert_eq!(read.tags, game.tags);
+        assert_eq!(read.moves, game.moves);
+        assert_eq!(read.result, game.result);
+    }
+}
@@ -0,0 +1,571 @@
+use crate::bitboard::{string_move_to_bitboard, Bitboard};
+use crate::board::Board;
+use crate::colour::{get_colour, Colour};
+use crate::game::Game;
+use crate::movegen::get_legal_moves;
+use crate::mv::Move;
+use crate::perft::perft_test;
+use crate::python::{parse_colour, parse_legal_uci, IllegalMoveError, InvalidColourError, InvalidFenError, InvalidSquareError};
+use crate::role::{get_role, Role};
+use crate::san::{from_san, to_san};
+use crate::uci::{get_square_string, is_square_name, to_uci};
+use crate::variant::Variant;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyIterator, PyList};
+
+
+// Python classes for using the library directly, instead of going through pick_move with a FEN and move list. Squares are numbered
+// like python-chess, A1 as 0 and H8 as 63, which is the bitboard bit with the file flipped
+
+#[pyclass(name = "Square", frozen)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PySquare {
+    index: u8,
+}
+
+impl PySquare {
+    pub fn from_bitboard(square: Bitboard) -> PySquare {
+        return PySquare { index: (square.trailing_zeros() ^ 7) as u8 };
+    }
+
+    pub fn bitboard(&self) -> Bitboard {
+        return Bitboard(1 << (self.index ^ 7));
+    }
+}
+
+#[pymethods]
+impl PySquare {
+    // Takes an index from 0 to 63 or a name like "e4"
+    #[new]
+    fn new(square: &Bound<'_, PyAny>) -> PyResult<PySquare> {
+        if let Ok(index) = square.extract::<u8>() {
+            if index < 64 {
+                return Ok(PySquare { index });
+            }
+        } else if let Ok(name) = square.extract::<String>() {
+            let name = name.to_ascii_lowercase();
+            if is_square_name(&name) {
+                return Ok(PySquare::from_bitboard(string_move_to_bitboard(&name)));
+            }
+        }
+        return Err(InvalidSquareError::new_err(format!("Invalid square {}", square)));
+    }
+
+    #[getter]
+    fn index(&self) -> u8 {
+        return self.index;
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        return get_square_string(self.bitboard());
+    }
+
+    // 0 for the A file up to 7 for the H file
+    #[getter]
+    fn file(&self) -> u8 {
+        return self.index % 8;
+    }
+
+    // 0 for the first rank up to 7 for the eighth
+    #[getter]
+    fn rank(&self) -> u8 {
+        return self.index / 8;
+    }
+
+    #[pyo3(name = "bitboard")]
+    fn py_bitboard(&self) -> PyBitboard {
+        return PyBitboard { value: self.bitboard().0 };
+    }
+
+    fn __int__(&self) -> u8 {
+        return self.index;
+    }
+
+    fn __eq__(&self, other: &Bound<'_, PyAny>) -> bool {
+        return other.extract::<PySquare>().map(|other| other.index == self.index).unwrap_or(false);
+    }
+
+    fn __hash__(&self) -> u64 {
+        return self.index as u64;
+    }
+
+    fn __str__(&self) -> String {
+        return self.name();
+    }
+
+    fn __repr__(&self) -> String {
+        return format!("Square('{}')", self.name());
+    }
+}
+
+#[pyclass(name = "Bitboard", frozen)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PyBitboard {
+    value: u64,
+}
+
+impl PyBitboard {
+    fn squares_list(&self) -> Vec<PySquare> {
+        let mut squares: Vec<PySquare> = Vec::new();
+        let mut remaining = self.value;
+        while remaining != 0 {
+            squares.push(PySquare::from_bitboard(Bitboard(remaining & remaining.wrapping_neg())));
+            remaining &= remaining - 1;
+        }
+        squares.sort_by_key(|square| square.index);
+        return squares;
+    }
+}
+
+#[pymethods]
+impl PyBitboard {
+    // The raw value uses the libraries own bit order, with H1 as bit 0 and A8 as bit 63
+    #[new]
+    #[pyo3(signature = (value = 0))]
+    fn new(value: u64) -> PyBitboard {
+        return PyBitboard { value };
+    }
+
+    #[getter]
+    fn value(&self) -> u64 {
+        return self.value;
+    }
+
+    // The squares in the bitboard from A1 up to H8
+    fn squares(&self) -> Vec<PySquare> {
+        return self.squares_list();
+    }
+
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        return PyList::new(py, self.squares_list())?.try_iter();
+    }
+
+    fn __len__(&self) -> usize {
+        return self.value.count_ones() as usize;
+    }
+
+    fn __bool__(&self) -> bool {
+        return self.value != 0;
+    }
+
+    fn __contains__(&self, square: PySquare) -> bool {
+        return (self.value & square.bitboard().0) != 0;
+    }
+
+    fn __and__(&self, other: PyBitboard) -> PyBitboard {
+        return PyBitboard { value: self.value & other.value };
+    }
+
+    fn __or__(&self, other: PyBitboard) -> PyBitboard {
+        return PyBitboard { value: self.value | other.value };
+    }
+
+    fn __xor__(&self, other: PyBitboard) -> PyBitboard {
+        return PyBitboard { value: self.value ^ other.value };
+    }
+
+    fn __invert__(&self) -> PyBitboard {
+        return PyBitboard { value: !self.value };
+    }
+
+    fn __int__(&self) -> u64 {
+        return self.value;
+    }
+
+    fn __eq__(&self, other: &Bound<'_, PyAny>) -> bool {
+        return other.extract::<PyBitboard>().map(|other| other.value == self.value).unwrap_or(false);
+    }
+
+    fn __hash__(&self) -> u64 {
+        return self.value;
+    }
+
+    fn __repr__(&self) -> String {
+        return format!("Bitboard(0x{:016x})", self.value);
+    }
+}
+
+#[pyclass(name = "Move", frozen)]
+#[derive(Debug, Clone, Copy)]
+pub struct PyMove {
+    pub mv: Move,
+    // Whether the move is from a Chess960 board, which writes castling differently in UCI
+    pub chess960: bool,
+}
+
+#[pymethods]
+impl PyMove {
+    #[getter(from_square)]
+    fn get_from_square(&self) -> PySquare {
+        return PySquare::from_bitboard(self.mv.from_square);
+    }
+
+    // For castling this is the square of the rook, since castling moves are the king taking its own rook
+    #[getter(to_square)]
+    fn get_to_square(&self) -> PySquare {
+        return PySquare::from_bitboard(self.mv.to_square);
+    }
+
+    #[getter]
+    fn piece(&self) -> Option<&'static str> {
+        return self.mv.role.map(role_name);
+    }
+
+    #[getter]
+    fn promotion(&self) -> Option<&'static str> {
+        return self.mv.promotion.map(role_name);
+    }
+
+    #[getter]
+    fn captured(&self) -> Option<&'static str> {
+        return self.mv.capture.map(role_name);
+    }
+
+    #[getter]
+    fn is_capture(&self) -> bool {
+        return self.mv.capture.is_some();
+    }
+
+    #[getter]
+    fn is_castle(&self) -> bool {
+        return self.mv.castle;
+    }
+
+    #[getter]
+    fn is_en_passant(&self) -> bool {
+        return self.mv.en_passant;
+    }
+
+    fn uci(&self) -> String {
+        return to_uci(Some(self.mv), self.chess960);
+    }
+
+    fn __eq__(&self, other: &Bound<'_, PyAny>) -> bool {
+        return other.extract::<PyMove>().map(|other| other.mv == self.mv).unwrap_or(false);
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.mv.from_square.0, self.mv.to_square.0, self.mv.promotion.map(role_name)).hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    fn __str__(&self) -> String {
+        return self.uci();
+    }
+
+    fn __repr__(&self) -> String {
+        return format!("Move('{}')", self.uci());
+    }
+}
+
+// How a finished game ended. The winner is None for a draw
+#[pyclass(name = "Outcome", get_all, frozen)]
+#[derive(Debug, Clone)]
+pub struct PyOutcome {
+    pub termination: &'static str,
+    pub winner: Option<&'static str>,
+}
+
+#[pymethods]
+impl PyOutcome {
+    // "1-0", "0-1" or "1/2-1/2"
+    fn result(&self) -> &'static str {
+        match self.winner {
+            Some("white") => return "1-0",
+            Some(_) => return "0-1",
+            None => return "1/2-1/2",
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        let winner = match self.winner {
+            Some(winner) => format!("'{}'", winner),
+            None => "None".to_string(),
+        };
+        return format!("Outcome(termination='{}', winner={})", self.termination, winner);
+    }
+}
+
+#[pyclass(name = "Board")]
+#[derive(Debug, Clone)]
+pub struct PyBoard {
+    game: Game,
+}
+
+impl PyBoard {
+    pub fn from_board(board: Board) -> PyBoard {
+        return PyBoard { game: Game::new(board) };
+    }
+
+    pub fn board(&self) -> &Board {
+        return &self.game.board;
+    }
+
+    // Raises IllegalMoveError for anything that isn't legal. The clock is in seconds
+    fn play(&mut self, mv: Move, clock: Option<f64>) -> PyResult<()> {
+        let clock = clock.map(|seconds| Duration::from_secs_f64(seconds.max(0.0)));
+        return self.game.push_with_clock(mv, clock).map_err(|error| IllegalMoveError::new_err(error.to_string()));
+    }
+}
+
+#[pymethods]
+impl PyBoard {
+    // The starting position, or the position from a FEN, played by the rules of a variant like "atomic" or "kingofthehill"
+    #[new]
+    #[pyo3(signature = (fen = None, variant = "standard"))]
+    fn new(fen: Option<String>, variant: &str) -> PyResult<PyBoard> {
+        let variant = Variant::from_name(variant).ok_or_else(|| PyValueError::new_err(format!("Unknown variant {}", variant)))?;
+        let game = match fen {
+            Some(fen) => Game::from_variant_fen(&fen, variant).map_err(InvalidFenError::new_err)?,
+            None => Game::variant_starting_position(variant),
+        };
+        return Ok(PyBoard { game });
+    }
+
+    // One of the 960 Chess960 start positions, where 518 is the normal start position
+    #[staticmethod]
+    fn chess960(index: u32) -> PyResult<PyBoard> {
+        let board = Board::chess960_position(index).ok_or_else(|| PyValueError::new_err(format!("Chess960 index {} is not below 960", index)))?;
+        return Ok(PyBoard::from_board(board));
+    }
+
+    fn fen(&self) -> String {
+        return self.game.fen();
+    }
+
+    #[getter]
+    fn variant(&self) -> &'static str {
+        return self.game.board.variant.name();
+    }
+
+    #[getter]
+    fn turn(&self) -> &'static str {
+        return colour_name(self.game.board.turn);
+    }
+
+    #[getter]
+    fn halfmove_clock(&self) -> u32 {
+        return self.game.halfmove_clock();
+    }
+
+    #[getter]
+    fn fullmove_number(&self) -> u32 {
+        return self.game.fullmove_number();
+    }
+
+    #[getter]
+    fn move_stack(&self) -> Vec<PyMove> {
+        return self.game.moves.iter().map(|mv| PyMove { mv: *mv, chess960: self.game.board.chess960 }).collect();
+    }
+
+    #[getter]
+    fn occupied(&self) -> PyBitboard {
+        return PyBitboard { value: self.game.board.occupied.0 };
+    }
+
+    fn legal_moves(&self) -> Vec<PyMove> {
+        return get_legal_moves(&self.game.board).iter().map(|mv| PyMove { mv: *mv, chess960: self.game.board.chess960 }).collect();
+    }
+
+    fn is_legal(&self, mv: PyMove) -> bool {
+        return get_legal_moves(&self.game.board).contains(&mv.mv);
+    }
+
+    // Clocks are the seconds left for the side making the move, and are written to the PGN
+    #[pyo3(signature = (mv, clock = None))]
+    fn push(&mut self, mv: PyMove, clock: Option<f64>) -> PyResult<()> {
+        return self.play(mv.mv, clock);
+    }
+
+    fn parse_uci(&self, uci: &str) -> PyResult<PyMove> {
+        return parse_legal_uci(&self.game.board, uci).map(|mv| PyMove { mv, chess960: self.game.board.chess960 });
+    }
+
+    fn parse_san(&self, san: &str) -> PyResult<PyMove> {
+        return from_san(&self.game.board, san).map(|mv| PyMove { mv, chess960: self.game.board.chess960 }).ok_or_else(|| IllegalMoveError::new_err(format!("Illegal or ambiguous move {} in {}", san, self.fen())));
+    }
+
+    #[pyo3(signature = (uci, clock = None))]
+    fn push_uci(&mut self, uci: &str, clock: Option<f64>) -> PyResult<PyMove> {
+        let mv = self.parse_uci(uci)?;
+        self.play(mv.mv, clock)?;
+        return Ok(mv);
+    }
+
+    #[pyo3(signature = (san, clock = None))]
+    fn push_san(&mut self, san: &str, clock: Option<f64>) -> PyResult<PyMove> {
+        let mv = self.parse_san(san)?;
+        self.play(mv.mv, clock)?;
+        return Ok(mv);
+    }
+
+    // Takes back the last move and returns it
+    fn pop(&mut self) -> PyResult<PyMove> {
+        let mv = self.game.pop().ok_or_else(|| PyIndexError::new_err("pop from a board with no moves"))?;
+        return Ok(PyMove { mv, chess960: self.game.board.chess960 });
+    }
+
+    // Takes back moves until only the first ply of them are left
+    fn undo_to(&mut self, ply: usize) {
+        self.game.undo_to(ply);
+    }
+
+    #[getter]
+    fn ply(&self) -> usize {
+        return self.game.ply();
+    }
+
+    fn peek(&self) -> Option<PyMove> {
+        return self.game.peek().map(|mv| PyMove { mv, chess960: self.game.board.chess960 });
+    }
+
+    fn san(&self, mv: PyMove) -> PyResult<String> {
+        if !get_legal_moves(&self.game.board).contains(&mv.mv) {
+            return Err(IllegalMoveError::new_err(format!("Illegal move {} in {}", to_uci(Some(mv.mv), self.game.board.chess960), self.fen())));
+        }
+        return Ok(to_san(&self.game.board, mv.mv));
+    }
+
+    fn is_check(&self) -> bool {
+        return self.game.board.is_check(&self.game.board.turn);
+    }
+
+    fn is_checkmate(&self) -> bool {
+        return self.game.board.is_checkmate(&self.game.board.turn);
+    }
+
+    fn is_stalemate(&self) -> bool {
+        return self.game.board.is_stalemate(&self.game.board.turn);
+    }
+
+    fn is_insufficient_material(&self) -> bool {
+        return self.game.board.is_insufficient_material();
+    }
+
+    // The game is over by checkmate, stalemate, insufficient material, the fifty move rule, threefold repetition, resignation or on time.
+    // None while the game is still going
+    fn outcome(&self) -> Option<PyOutcome> {
+        let outcome = self.game.outcome()?;
+        return Some(PyOutcome { termination: outcome.termination.name(), winner: outcome.winner.map(colour_name) });
+    }
+
+    fn resign(&mut self, colour: &str) -> PyResult<()> {
+        self.game.resign(parse_colour(colour)?);
+        return Ok(());
+    }
+
+    // The colour has run out of time
+    fn timeout(&mut self, colour: &str) -> PyResult<()> {
+        self.game.timeout(parse_colour(colour)?);
+        return Ok(());
+    }
+
+    // The game as PGN, with a dict of tags added to or replacing the defaults, like {"White": "ChessLibrary"}
+    #[pyo3(signature = (tags = None))]
+    fn pgn(&self, tags: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+        let mut pgn_tags: Vec<(String, String)> = Vec::new();
+        if let Some(tags) = tags {
+            for (name, value) in tags.iter() {
+                pgn_tags.push((name.extract()?, value.str()?.to_string()));
+            }
+        }
+        return Ok(self.game.to_pgn(&pgn_tags));
+    }
+
+    fn is_game_over(&self) -> bool {
+        return self.outcome().is_some();
+    }
+
+    // The piece on a square as a FEN letter, upper case for white, or None if it's empty
+    fn piece_at(&self, square: PySquare) -> Option<String> {
+        let bitboard = square.bitboard();
+        let role = get_role(&self.game.board, &bitboard)?;
+        let letter = role_letter(role);
+        match get_colour(&self.game.board, &bitboard)? {
+            Colour::White => return Some(letter.to_ascii_uppercase().to_string()),
+            Colour::Black => return Some(letter.to_string()),
+        }
+    }
+
+    // Squares with a piece of a type and colour, like pieces("knight", "white")
+    fn pieces(&self, piece: &str, colour: &str) -> PyResult<PyBitboard> {
+        let role = match piece {
+            "pawn" => Role::Pawn,
+            "knight" => Role::Knight,
+            "bishop" => Role::Bishop,
+            "rook" => Role::Rook,
+            "queen" => Role::Queen,
+            "king" => Role::King,
+            _ => return Err(PyValueError::new_err(format!("Unknown piece {}", piece))),
+        };
+        let colour = match colour {
+            "white" => Colour::White,
+            "black" => Colour::Black,
+            _ => return Err(InvalidColourError::new_err(format!("Unknown colour {}", colour))),
+        };
+        return Ok(PyBitboard { value: (self.game.board.role.get(role) & self.game.board.colour.get(colour)).0 });
+    }
+
+    // Polyglot Zobrist hash of the position
+    fn zobrist_hash(&self) -> u64 {
+        return self.game.board.zobrist_hash();
+    }
+
+    // Runs without the GIL, so other Python threads keep going during a deep perft
+    fn perft(&self, py: Python<'_>, depth: i32) -> i64 {
+        return py.allow_threads(|| perft_test(&self.game.board, depth));
+    }
+
+    fn copy(&self) -> PyBoard {
+        return self.clone();
+    }
+
+    fn __str__(&self) -> String {
+        let mut rows: Vec<String> = Vec::new();
+        for rank in (0..8).rev() {
+            let row: Vec<String> = (0..8).map(|file| self.piece_at(PySquare { index: rank * 8 + file }).unwrap_or(".".to_string())).collect();
+            rows.push(row.join(" "));
+        }
+        return rows.join("\n");
+    }
+
+    fn __repr__(&self) -> String {
+        return format!("Board('{}')", self.fen());
+    }
+}
+
+fn colour_name(colour: Colour) -> &'static str {
+    match colour {
+        Colour::White => return "white",
+        Colour::Black => return "black",
+    }
+}
+
+fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::Pawn => return "pawn",
+        Role::Knight => return "knight",
+        Role::Bishop => return "bishop",
+        Role::Rook => return "rook",
+        Role::Queen => return "queen",
+        Role::King => return "king",
+    }
+}
+
+fn role_letter(role: Role) -> char {
+    match role {
+        Role::Pawn => return 'p',
+        Role::Knight => return 'n',
+        Role::Bishop => return 'b',
+        Role::Rook => return 'r',
+        Role::Queen => return 'q',
+        Role::King => return 'k',
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "nnue")]
+use crate::engine::nnue;
+use crate::engine::book::{self, BookSelection, DEFAULT_MAX_BOOK_DEPTH, MAX_BOOK_DEPTH, USE_BOOK};
+use crate::engine::{params, syzygy};
+use std::fs;
+use std::sync::atomic::Ordering;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+// Python wrapper for load_eval_params
+#[pyfunction]
+pub fn load_eval_params(path: String) -> PyResult<()> {
+    return params::load_eval_params(&path).map_err(PyValueError::new_err);
+}
+
+// Python wrapper for init_tablebases. Returns the number of tables found
+#[pyfunction]
+pub fn load_syzygy(path: String) -> PyResult<usize> {
+    let count = syzygy::init_tablebases(&path);
+    if (count == 0) && !path.is_empty() {
+        return Err(PyValueError::new_err(format!("No Syzygy tables found in {}", path)));
+    }
+    return Ok(count);
+}
+
+// Python wrapper for load_network and unload_network. Passing None goes back to the hand written evaluation
+#[cfg(feature = "nnue")]
+#[pyfunction]
+#[pyo3(signature = (path=None))]
+pub fn load_nnue(path: Option<String>) -> PyResult<()> {
+    match path {
+        Some(path) => return nnue::load_network(&path).map_err(PyValueError::new_err),
+        None => {
+            nnue::unload_network();
+            return Ok(());
+        }
+    }
+}
+
+// Python wrapper for load_book. The book is turned on once it loads, and selection is "random" or "best"
+#[pyfunction]
+#[pyo3(signature = (path, max_depth = DEFAULT_MAX_BOOK_DEPTH, selection = "random"))]
+pub fn load_book(path: String, max_depth: usize, selection: &str) -> PyResult<()> {
+    let selection = match selection {
+        "random" => BookSelection::WeightedRandom,
+        "best" => BookSelection::Best,
+        _ => return Err(PyValueError::new_err(format!("Unknown book selection {}, expected \"random\" or \"best\"", selection))),
+    };
+    book::load_book(&path).map_err(PyValueError::new_err)?;
+    book::set_book_selection(selection);
+    MAX_BOOK_DEPTH.store(max_depth, Ordering::Relaxed);
+    USE_BOOK.store(true, Ordering::Relaxed);
+    return Ok(());
+}
+
+#[pyfunction]
+pub fn unload_book() {
+    USE_BOOK.store(false, Ordering::Relaxed);
+    let _ = book::load_book("");
+}
+
+// Python wrapper for make_book, reading PGN from one file and writing the book to another. Returns the number of entries written
+#[pyfunction]
+#[pyo3(signature = (pgn_path, output_path, max_plies = DEFAULT_MAX_BOOK_DEPTH))]
+pub fn make_book(pgn_path: String, output_path: String, max_plies: usize) -> PyResult<usize> {
+    let pgn = fs::read_to_string(&pgn_path).map_err(|error| PyValueError::new_err(format!("Could not read {}: {}", pgn_path, error)))?;
+    let book = book::make_book(&pgn, max_plies);
+    book.to_file(&output_path).map_err(PyValueError::new_err)?;
+    return Ok(book.entries.len());
+}
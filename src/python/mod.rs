@@ -0,0 +1,87 @@
+// Python bindings, built with the "python" cargo feature. The wheel is built with "extension-module", which turns it on
+use crate::board::Board;
+use crate::colour::Colour;
+use crate::engine::endgame::init_endgame_tables;
+use crate::fen::validate_fen;
+use crate::mv::Move;
+use crate::uci::from_uci;
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::wrap_pyfunction;
+
+pub mod board;
+pub mod loaders;
+pub mod search;
+
+use board::{PyBitboard, PyBoard, PyMove, PyOutcome, PySquare};
+use loaders::{load_book, load_eval_params, load_syzygy, make_book, unload_book};
+use search::{analyse, find_mate, pick_move, ponder_hit, start_ponder, stop_ponder, AnalysisLine};
+
+
+// All of these are ValueErrors, so code catching ValueError from before they existed still works
+create_exception!(chesslibrary, IllegalMoveError, PyValueError, "A move that is illegal in the position, or that can't be read");
+create_exception!(chesslibrary, InvalidFenError, PyValueError, "A FEN that is badly formed");
+create_exception!(chesslibrary, InvalidSquareError, PyValueError, "A square name or index that isn't on the board");
+create_exception!(chesslibrary, InvalidColourError, PyValueError, "A colour that isn't \"white\" or \"black\"");
+
+// The legal move a UCI string stands for, or an IllegalMoveError if it is illegal or can't be read
+pub fn parse_legal_uci(board: &Board, uci: &str) -> PyResult<Move> {
+    return from_uci(board, uci).map_err(|error| IllegalMoveError::new_err(format!("{} in {}", error, board.to_fen())));
+}
+
+// The board after playing a list of UCI moves from "startpos" or a FEN, as given to pick_move and the other search functions
+pub fn board_from_move_list(board_starting_position: &str, move_list: &str) -> PyResult<Board> {
+    let mut board = if board_starting_position == "startpos" {
+        Board::starting_position()
+    } else {
+        validate_fen(board_starting_position).map_err(InvalidFenError::new_err)?;
+        Board::from_fen(board_starting_position.to_string())
+    };
+    
+    for uci in move_list.split_whitespace() {
+        board.play_uci(uci).map_err(|error| IllegalMoveError::new_err(error.to_string()))?;
+    }
+    
+    return Ok(board);
+}
+
+// Checks a colour given to pick_move
+pub fn parse_colour(colour: &str) -> PyResult<Colour> {
+    match colour {
+        "white" => return Ok(Colour::White),
+        "black" => return Ok(Colour::Black),
+        _ => return Err(InvalidColourError::new_err(format!("Invalid colour {}, expected \"white\" or \"black\"", colour))),
+    }
+}
+
+// Python module definition
+#[pymodule]
+fn chesslibrary(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    init_endgame_tables();
+    m.add_function(wrap_pyfunction!(pick_move, m)?)?;
+    m.add_function(wrap_pyfunction!(start_ponder, m)?)?;
+    m.add_function(wrap_pyfunction!(ponder_hit, m)?)?;
+    m.add_function(wrap_pyfunction!(stop_ponder, m)?)?;
+    m.add_function(wrap_pyfunction!(analyse, m)?)?;
+    m.add_function(wrap_pyfunction!(find_mate, m)?)?;
+    m.add_function(wrap_pyfunction!(load_eval_params, m)?)?;
+    m.add_function(wrap_pyfunction!(load_syzygy, m)?)?;
+    m.add_function(wrap_pyfunction!(load_book, m)?)?;
+    m.add_function(wrap_pyfunction!(unload_book, m)?)?;
+    m.add_function(wrap_pyfunction!(make_book, m)?)?;
+    #[cfg(feature = "nnue")]
+    m.add_function(wrap_pyfunction!(loaders::load_nnue, m)?)?;
+    m.add_class::<AnalysisLine>()?;
+    m.add_class::<PyBoard>()?;
+    m.add_class::<PyMove>()?;
+    m.add_class::<PySquare>()?;
+    m.add_class::<PyBitboard>()?;
+    m.add_class::<PyOutcome>()?;
+    m.add("IllegalMoveError", m.py().get_type::<IllegalMoveError>())?;
+    m.add("InvalidFenError", m.py().get_type::<InvalidFenError>())?;
+    m.add("InvalidSquareError", m.py().get_type::<InvalidSquareError>())?;
+    m.add("InvalidColourError", m.py().get_type::<InvalidColourError>())?;
+    Ok(())
+}
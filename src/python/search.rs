@@ -0,0 +1,205 @@
+use crate::engine::book::book_move;
+use crate::engine::mate::{mate_search, MateSearchResult};
+use crate::engine::search::{search, search_time, SearchControl, SearchResult, NODE_COUNT};
+use crate::movegen::get_legal_moves;
+use crate::python::{board_from_move_list, parse_colour};
+use crate::uci::{from_uci, to_uci};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use once_cell::sync::Lazy;
+use std::time::Duration;
+
+use pyo3::exceptions::PyTimeoutError;
+use pyo3::prelude::*;
+
+// The background search started by start_ponder, if there is one
+static PONDER_SEARCH: Lazy<Mutex<Option<PonderSearch>>> = Lazy::new(|| Mutex::new(None));
+
+// The expected reply from the last search picked by pick_move, kept so start_ponder doesn't need to search for it again
+static LAST_PREDICTION: Lazy<Mutex<Option<PonderPrediction>>> = Lazy::new(|| Mutex::new(None));
+
+// How long start_ponder searches for to guess the opponents reply when pick_move didn't leave a prediction
+const PREDICTION_SEARCH_TIME: Duration = Duration::from_millis(100);
+
+// A PvLine converted to uci strings for Python
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+pub struct AnalysisLine {
+    pub uci_move: String,
+    pub evaluation: i32,
+    pub pv: Vec<String>,
+    pub depth: i32,
+}
+
+#[pymethods]
+impl AnalysisLine {
+    fn __repr__(&self) -> String {
+        return format!("AnalysisLine(uci_move='{}', evaluation={}, pv={:?}, depth={})", self.uci_move, self.evaluation, self.pv, self.depth);
+    }
+}
+
+struct PonderSearch {
+    predicted_move: String,
+    chess960: bool,
+    control: Arc<SearchControl>,
+    handle: JoinHandle<SearchResult>,
+}
+
+struct PonderPrediction {
+    move_list: String,
+    predicted_move: String,
+}
+
+#[pyfunction]
+pub fn pick_move(py: Python<'_>, board_starting_position: String, bot_time: (u64, u64), bot_colour: String, move_list: String) -> PyResult<(String, i32)> {
+    
+    NODE_COUNT.store(0, Ordering::Relaxed);
+    
+    // The search is always from the side to move, the colour is only checked to be valid
+    parse_colour(&bot_colour)?;
+    
+    let board = board_from_move_list(&board_starting_position, &move_list)?;
+    
+    if let Some(mv) = book_move(&board) {
+        return Ok((to_uci(Some(mv), board.chess960), 0));
+    }
+    
+    let max_search_time: Duration = search_time(bot_time);
+    
+    // The GIL is released while searching so other Python threads, like the one handling lichess events, keep running
+    let control = SearchControl::new(max_search_time, false);
+    let result = py.allow_threads(|| search(&board, &control, 1));
+    
+    if let Some(predicted_move) = result.ponder_move() {
+        *LAST_PREDICTION.lock().unwrap() = Some(PonderPrediction {
+            move_list: format!("{} {}", move_list, to_uci(result.best_move, board.chess960)).trim().to_string(),
+            predicted_move: to_uci(Some(predicted_move), board.chess960),
+        });
+    }
+    
+    return Ok((to_uci(result.best_move, board.chess960), result.evaluation));
+}
+
+// Plays the opponents expected reply to move_list and searches the resulting position in the background until ponder_hit or stop_ponder
+// is called. Returns the predicted move, or None if there is nothing to ponder on
+#[pyfunction]
+pub fn start_ponder(py: Python<'_>, board_starting_position: String, move_list: String) -> PyResult<Option<String>> {
+    
+    stop_ponder(py)?;
+    
+    let mut board = board_from_move_list(&board_starting_position, &move_list)?;
+    
+    let cached_prediction = LAST_PREDICTION.lock().unwrap().take();
+    let predicted_move = match cached_prediction {
+        Some(prediction) if prediction.move_list == move_list.trim() => from_uci(&board, &prediction.predicted_move).ok(),
+        _ => py.allow_threads(|| search(&board, &SearchControl::new(PREDICTION_SEARCH_TIME, false), 1).best_move),
+    };
+    
+    let Some(predicted_move) = predicted_move else {
+        return Ok(None);
+    };
+    
+    if !get_legal_moves(&board).contains(&predicted_move) {
+        return Ok(None);
+    }
+    
+    board.play_unsafe(predicted_move);
+    
+    if get_legal_moves(&board).is_empty() {
+        return Ok(None);
+    }
+    
+    NODE_COUNT.store(0, Ordering::Relaxed);
+    
+    let control = Arc::new(SearchControl::new(Duration::ZERO, true));
+    let search_control = Arc::clone(&control);
+    let chess960 = board.chess960;
+    let handle = thread::spawn(move || search(&board, &search_control, 1));
+    
+    let predicted_move = to_uci(Some(predicted_move), chess960);
+    *PONDER_SEARCH.lock().unwrap() = Some(PonderSearch {
+        predicted_move: predicted_move.clone(),
+        chess960,
+        control,
+        handle,
+    });
+    
+    return Ok(Some(predicted_move));
+}
+
+// Called with the opponents actual move. If it was the predicted one the ponder becomes the real search, with the time spent pondering
+// counted against bot_time, and its result is returned. Otherwise the ponder is thrown away and None is returned
+#[pyfunction]
+pub fn ponder_hit(py: Python<'_>, bot_time: (u64, u64), opponent_move: String) -> PyResult<Option<(String, i32)>> {
+    
+    let Some(ponder) = PONDER_SEARCH.lock().unwrap().take() else {
+        return Ok(None);
+    };
+    
+    if ponder.predicted_move != opponent_move {
+        ponder.control.stop();
+        let _ = py.allow_threads(|| ponder.handle.join());
+        return Ok(None);
+    }
+    
+    ponder.control.ponderhit(Some(search_time(bot_time)));
+    
+    let result = py.allow_threads(|| ponder.handle.join().unwrap());
+    
+    return Ok(Some((to_uci(result.best_move, ponder.chess960), result.evaluation)));
+}
+
+// Searches for search_time_ms and returns the best multi_pv root moves, best first, each with its own evaluation and principal variation
+#[pyfunction]
+pub fn analyse(py: Python<'_>, board_starting_position: String, move_list: String, search_time_ms: u64, multi_pv: usize) -> PyResult<Vec<AnalysisLine>> {
+    
+    NODE_COUNT.store(0, Ordering::Relaxed);
+    
+    let board = board_from_move_list(&board_starting_position, &move_list)?;
+    
+    let control = SearchControl::new(Duration::from_millis(search_time_ms), false);
+    let result = py.allow_threads(|| search(&board, &control, multi_pv));
+    
+    let mut analysis_lines: Vec<AnalysisLine> = Vec::new();
+    for line in result.lines {
+        analysis_lines.push(AnalysisLine {
+            uci_move: to_uci(line.pv.first().copied(), board.chess960),
+            evaluation: line.evaluation,
+            pv: line.pv.iter().map(|mv| to_uci(Some(*mv), board.chess960)).collect(),
+            depth: result.depth,
+        });
+    }
+    
+    return Ok(analysis_lines);
+}
+
+// Stops any ponder that is running and discards it
+#[pyfunction]
+pub fn stop_ponder(py: Python<'_>) -> PyResult<()> {
+    if let Some(ponder) = PONDER_SEARCH.lock().unwrap().take() {
+        ponder.control.stop();
+        let _ = py.allow_threads(|| ponder.handle.join());
+    }
+    Ok(())
+}
+
+// Looks for a forced mate in max_moves after playing move_list. Returns the mating line as uci moves, or None if there is no mate.
+// Raises TimeoutError if search_time_ms runs out before a mate is proven or refuted
+#[pyfunction]
+#[pyo3(signature = (board_starting_position, move_list, max_moves, search_time_ms=None))]
+pub fn find_mate(py: Python<'_>, board_starting_position: String, move_list: String, max_moves: i32, search_time_ms: Option<u64>) -> PyResult<Option<Vec<String>>> {
+    
+    let board = board_from_move_list(&board_starting_position, &move_list)?;
+    
+    let max_search_time = match search_time_ms {
+        Some(search_time_ms) => Duration::from_millis(search_time_ms),
+        None => Duration::MAX,
+    };
+    
+    match py.allow_threads(|| mate_search(&board, max_moves, &SearchControl::new(max_search_time, false))) {
+        MateSearchResult::Mate(pv) => return Ok(Some(pv.iter().map(|mv| to_uci(Some(*mv), board.chess960)).collect())),
+        MateSearchResult::NoMate => return Ok(None),
+        MateSearchResult::Aborted => return Err(PyTimeoutError::new_err("Mate search ran out of time")),
+    }
+}
@@ -11,6 +11,22 @@ pub enum Role {
     King
 }
 
+pub const ALL_ROLES: [Role; 6] = [Role::Pawn, Role::Knight, Role::Bishop, Role::Rook, Role::Queen, Role::King];
+
+impl Role {
+    // Lowercase name of the role, as used in parameter files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Pawn => "pawn",
+            Role::Knight => "knight",
+            Role::Bishop => "bishop",
+            Role::Rook => "rook",
+            Role::Queen => "queen",
+            Role::King => "king",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ByRole<T> {
     pub pawn: T,
@@ -21,6 +37,30 @@ pub struct ByRole<T> {
     pub king: T,
 }
 
+impl<T> ByRole<T> {
+    pub fn get(&self, role: Role) -> &T {
+        match role {
+            Role::Pawn => &self.pawn,
+            Role::Knight => &self.knight,
+            Role::Bishop => &self.bishop,
+            Role::Rook => &self.rook,
+            Role::Queen => &self.queen,
+            Role::King => &self.king,
+        }
+    }
+    
+    pub fn get_mut(&mut self, role: Role) -> &mut T {
+        match role {
+            Role::Pawn => &mut self.pawn,
+            Role::Knight => &mut self.knight,
+            Role::Bishop => &mut self.bishop,
+            Role::Rook => &mut self.rook,
+            Role::Queen => &mut self.queen,
+            Role::King => &mut self.king,
+        }
+    }
+}
+
 // Gets the role at a square.
 pub fn get_role(board: &Board, square: &Bitboard) -> Option<Role> {
     if (board.role.pawn & square) != EMPTY_BITBOARD {
@@ -0,0 +1,214 @@
+use crate::bitboard::{string_move_to_bitboard, Bitboard, EMPTY_BITBOARD};
+use crate::board::Board;
+use crate::movegen::get_legal_moves;
+use crate::mv::Move;
+use crate::role::Role;
+use crate::castle::CastleSide;
+use crate::uci::get_square_string;
+
+// Converts a legal move to standard algebraic notation, like "Nbd7", "exd5", "e8=Q+" or "O-O"
+pub fn to_san(board: &Board, mv: Move) -> String {
+    let mut san = String::new();
+
+    if mv.castle {
+        match Board::castle_side_of(mv) {
+            CastleSide::KingSide => san.push_str("O-O"),
+            CastleSide::QueenSide => san.push_str("O-O-O"),
+        }
+    } else if mv.role == Some(Role::Pawn) {
+        if mv.capture.is_some() {
+            san.push_str(&get_square_string(mv.from_square)[..1]);
+            san.push('x');
+        }
+        san.push_str(&get_square_string(mv.to_square));
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push(role_letter(promotion));
+        }
+    } else {
+        let Some(role) = mv.role else {
+            return san;
+        };
+        san.push(role_letter(role));
+
+        // Other pieces of the same type that could also move to the square need the move to say which piece it is, by file if that's
+        // enough, then by rank, then by both
+        let others: Vec<Bitboard> = get_legal_moves(board)
+            .iter()
+            .filter(|other| (other.role == mv.role) && (other.to_square == mv.to_square) && (other.from_square != mv.from_square) && !other.castle)
+            .map(|other| other.from_square)
+            .collect();
+        if !others.is_empty() {
+            let from = get_square_string(mv.from_square);
+            let same_file = others.iter().any(|other| get_square_string(*other)[..1] == from[..1]);
+            let same_rank = others.iter().any(|other| get_square_string(*other)[1..] == from[1..]);
+            if !same_file {
+                san.push_str(&from[..1]);
+            } else if !same_rank {
+                san.push_str(&from[1..]);
+            } else {
+                san.push_str(&from);
+            }
+        }
+
+        if mv.capture.is_some() {
+            san.push('x');
+        }
+        san.push_str(&get_square_string(mv.to_square));
+    }
+
+    let mut new_board = board.clone();
+    new_board.play_unsafe(mv);
+    if new_board.is_checkmate(&new_board.turn) {
+        san.push('#');
+    } else if new_board.is_check(&new_board.turn) {
+        san.push('+');
+    }
+
+    return san;
+}
+
+// Finds the legal move a move in standard algebraic notation stands for. Check and annotation symbols are ignored, and castling can be
+// written with O or 0. Returns None if the move is illegal, ambiguous or can't be read
+pub fn from_san(board: &Board, san: &str) -> Option<Move> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal_moves = get_legal_moves(board);
+
+    if (san == "O-O") | (san == "0-0") {
+        return legal_moves.iter().find(|mv| mv.castle && (Board::castle_side_of(**mv) == CastleSide::KingSide)).copied();
+    }
+    if (san == "O-O-O") | (san == "0-0-0") {
+        return legal_moves.iter().find(|mv| mv.castle && (Board::castle_side_of(**mv) == CastleSide::QueenSide)).copied();
+    }
+
+    let mut chars: Vec<char> = san.chars().collect();
+
+    let role = match chars.first()? {
+        'N' => Role::Knight,
+        'B' => Role::Bishop,
+        'R' => Role::Rook,
+        'Q' => Role::Queen,
+        'K' => Role::King,
+        _ => Role::Pawn,
+    };
+    if role != Role::Pawn {
+        chars.remove(0);
+    }
+
+    // Promotions can be written as "e8=Q" or "e8Q"
+    let mut promotion = None;
+    if let Some(last) = chars.last() {
+        promotion = match last {
+            'N' => Some(Role::Knight),
+            'B' => Some(Role::Bishop),
+            'R' => Some(Role::Rook),
+            'Q' => Some(Role::Queen),
+            'K' if role == Role::Pawn => Some(Role::King),
+            _ => None,
+        };
+        if promotion.is_some() {
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    if chars.len() < 2 {
+        return None;
+    }
+    let to_square = string_move_to_bitboard(&chars[chars.len() - 2..].iter().collect::<String>());
+    if to_square == EMPTY_BITBOARD {
+        return None;
+    }
+
+    // Whatever is left before the destination is the file and or rank of the piece moving, and maybe an x for a capture
+    let from_hint: String = chars[..chars.len() - 2].iter().filter(|char| **char != 'x').collect();
+
+    let matching: Vec<Move> = legal_moves
+        .iter()
+        .filter(|mv| (mv.role == Some(role)) && (mv.to_square == to_square) && (mv.promotion == promotion) && !mv.castle)
+        .filter(|mv| {
+            let from = get_square_string(mv.from_square);
+            return from_hint.chars().all(|hint| from.contains(hint));
+        })
+        .copied()
+        .collect();
+
+    if matching.len() != 1 {
+        return None;
+    }
+    return Some(matching[0]);
+}
+
+fn role_letter(role: Role) -> char {
+    match role {
+        Role::Pawn => return 'P',
+        Role::Knight => return 'N',
+        Role::Bishop => return 'B',
+        Role::Rook => return 'R',
+        Role::Queen => return 'Q',
+        Role::King => return 'K',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        return Board::from_fen(fen.to_string());
+    }
+
+    fn san(fen: &str, text: &str) -> String {
+        let board = board(fen);
+        return to_san(&board, from_san(&board, text).unwrap_or_else(|| panic!("{} is not legal in {}", text, fen)));
+    }
+
+    #[test]
+    fn every_legal_move_round_trips() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPpP/R3K2R b KQkq - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            "2k5/8/8/8/4Q2Q/8/8/K6Q w - - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+        for fen in fens {
+            let board = board(fen);
+            for mv in get_legal_moves(&board) {
+                assert_eq!(from_san(&board, &to_san(&board, mv)), Some(mv), "{} in {}", to_san(&board, mv), fen);
+            }
+        }
+    }
+
+    #[test]
+    fn writes_standard_notation() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san(start, "Nf3"), "Nf3");
+        assert_eq!(san("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", "Qh4"), "Qh4#");
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6"), "exd6");
+        assert_eq!(san("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "bxa8Q"), "bxa8=Q+");
+        assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "0-0-0"), "O-O-O");
+        assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "O-O"), "O-O");
+    }
+
+    #[test]
+    fn says_which_piece_moves_when_needed() {
+        assert_eq!(san("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", "Rad1"), "Rad1");
+        assert_eq!(san("4k3/8/8/R7/8/8/4K3/R7 w - - 0 1", "R1a3"), "R1a3");
+        assert_eq!(san("2k5/8/8/8/4Q2Q/8/8/K6Q w - - 0 1", "Qh4e1"), "Qh4e1");
+        assert_eq!(san("2k5/8/8/8/4Q2Q/8/8/K6Q w - - 0 1", "Qe4e1"), "Qee1");
+    }
+
+    #[test]
+    fn refuses_illegal_and_ambiguous_moves() {
+        let start = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(from_san(&start, "Nf6"), None);
+        assert_eq!(from_san(&start, "O-O"), None);
+        assert_eq!(from_san(&start, "e5"), None);
+        assert_eq!(from_san(&start, "x"), None);
+        assert_eq!(from_san(&board("4k3/8/8/8/8/8/4K3/R6R w - - 0 1"), "Rd1"), None);
+    }
+}
@@ -109,6 +109,7 @@ pub const FILE_E: Bitboard = Bitboard(0b0000100000001000000010000000100000001000
 pub const FILE_F: Bitboard = Bitboard(0b0000010000000100000001000000010000000100000001000000010000000100);
 pub const FILE_G: Bitboard = Bitboard(0b0000001000000010000000100000001000000010000000100000001000000010);
 pub const FILE_H: Bitboard = Bitboard(0b0000000100000001000000010000000100000001000000010000000100000001);
+pub const FILES: [Bitboard; 8] = [FILE_A, FILE_B, FILE_C, FILE_D, FILE_E, FILE_F, FILE_G, FILE_H];
 
 pub const NEIGHBOUR_FILES: [(Bitboard, Bitboard); 8] = [(FILE_A, FILE_B), (FILE_B, Bitboard(0b1010000010100000101000001010000010100000101000001010000010100000)), (FILE_C, Bitboard(0b0101000001010000010100000101000001010000010100000101000001010000)), (FILE_D, Bitboard(0b0010100000101000001010000010100000101000001010000010100000101000)), (FILE_E, Bitboard(0b0001010000010100000101000001010000010100000101000001010000010100)), (FILE_F, Bitboard(0b0000101000001010000010100000101000001010000010100000101000001010)), (FILE_G, Bitboard(0b0000010100000101000001010000010100000101000001010000010100000101)), (FILE_H, FILE_G)];
 
@@ -127,6 +128,11 @@ impl Board {
     // Clears a specific square
     pub fn clear_square(&mut self, square: &Bitboard) {
         
+        #[cfg(feature = "nnue")]
+        if let (Some(role), Some(colour)) = (get_role(self, square), get_colour(self, square)) {
+            self.accumulator.remove_piece(role, colour, square);
+        }
+        
         let square_bitboard_clear_bit: u64 = !square.0;
         
         self.occupied &= square_bitboard_clear_bit;
@@ -168,6 +174,9 @@ impl Board {
                     Colour::White => self.colour.white |= square,
                     Colour::Black => self.colour.black |= square,
                 }
+                
+                #[cfg(feature = "nnue")]
+                self.accumulator.add_piece(*role, *colour, square);
             }
         }
     }
@@ -1,25 +1,351 @@
-use crate::mv::Move;
+use crate::mv::{Move, EMPTY_MOVE};
+use crate::movegen::get_legal_moves;
 use crate::board::Board;
 use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
-use crate::colour::{Colour, get_colour};
+use crate::colour::Colour;
 use crate::square::{EIGHTH_RANK, FIFTH_RANK, FILE_A, FILE_B, FILE_C, FILE_D, FILE_E, FILE_F, FILE_G, FILE_H, FIRST_RANK, FOURTH_RANK, SECOND_RANK, SEVENTH_RANK, SIXTH_RANK, THIRD_RANK};
-use crate::role::{Role, get_role};
+use crate::role::Role;
+use crate::castle::castle_destinations;
+use crate::engine::search::{search, search_time_ms, SearchControl, NODE_COUNT};
+use crate::engine::mate::{mate_search, MateSearchResult};
+use crate::engine::params::load_eval_params;
+use crate::engine::endgame::init_endgame_tables;
+use crate::engine::eval::USE_LEGACY_MOBILITY;
+use crate::engine::trace::evaluate_trace;
+use crate::engine::syzygy::init_tablebases;
+use crate::engine::book::{book_move, load_book, set_book_selection, BookSelection, DEFAULT_MAX_BOOK_DEPTH, MAX_BOOK_DEPTH, USE_BOOK};
+#[cfg(feature = "nnue")]
+use crate::engine::nnue::{load_network, unload_network};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-pub fn to_uci(mv: Option<Move>) -> String {
+// Set by the UCI_Chess960 option. Positions set up while it is on are Chess960 boards, so castling moves are written and read as the
+// king taking its own rook instead of the king moving two squares
+pub static CHESS960: AtomicBool = AtomicBool::new(false);
+
+// How long a "go ponder" without a clock searches for once it gets a ponderhit, counted from when the ponder started
+const PONDERHIT_SEARCH_TIME: Duration = Duration::from_secs(1);
+
+// A search started by "go" that is running on another thread
+struct RunningSearch {
+    control: Arc<SearchControl>,
+    handle: JoinHandle<()>,
+}
+
+// Runs the UCI protocol over stdin/stdout until "quit" is received
+pub fn uci_loop() {
+    let mut board = Board::starting_position();
+    let mut running_search: Option<RunningSearch> = None;
+    let mut multi_pv: usize = 1;
+    
+    init_endgame_tables();
+    
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name ChessLibrary");
+                println!("id author Grant Barkway");
+                println!("option name Ponder type check default false");
+                println!("option name UCI_Chess960 type check default false");
+                println!("option name MultiPV type spin default 1 min 1 max 218");
+                println!("option name EvalFile type string default <empty>");
+                println!("option name LegacyMobility type check default false");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("option name OwnBook type check default false");
+                println!("option name BookFile type string default <empty>");
+                println!("option name BookDepth type spin default {} min 0 max 1000", DEFAULT_MAX_BOOK_DEPTH);
+                println!("option name BookSelection type combo default Random var Random var Best");
+                #[cfg(feature = "nnue")]
+                println!("option name NnueFile type string default <empty>");
+                println!("uciok");
+            }
+            Some(&"isready") => println!("readyok"),
+            Some(&"setoption") => {
+                let (name, value) = parse_option(&tokens);
+                if name.eq_ignore_ascii_case("UCI_Chess960") {
+                    CHESS960.store(value == "true", Ordering::Relaxed);
+                } else if name.eq_ignore_ascii_case("MultiPV") {
+                    multi_pv = value.parse::<usize>().unwrap_or(1).clamp(1, 218);
+                } else if name.eq_ignore_ascii_case("EvalFile") {
+                    if let Err(error) = load_eval_params(&value) {
+                        println!("info string {}", error);
+                    }
+                } else if name.eq_ignore_ascii_case("LegacyMobility") {
+                    USE_LEGACY_MOBILITY.store(value == "true", Ordering::Relaxed);
+                } else if name.eq_ignore_ascii_case("SyzygyPath") {
+                    let count = init_tablebases(&value);
+                    println!("info string Found {} tablebases", count);
+                } else if name.eq_ignore_ascii_case("OwnBook") {
+                    USE_BOOK.store(value == "true", Ordering::Relaxed);
+                } else if name.eq_ignore_ascii_case("BookFile") {
+                    if let Err(error) = load_book(&value) {
+                        println!("info string {}", error);
+                    }
+                } else if name.eq_ignore_ascii_case("BookDepth") {
+                    MAX_BOOK_DEPTH.store(value.parse::<usize>().unwrap_or(DEFAULT_MAX_BOOK_DEPTH), Ordering::Relaxed);
+                } else if name.eq_ignore_ascii_case("BookSelection") {
+                    set_book_selection(if value.eq_ignore_ascii_case("Best") { BookSelection::Best } else { BookSelection::WeightedRandom });
+                }
+                #[cfg(feature = "nnue")]
+                if name.eq_ignore_ascii_case("NnueFile") {
+                    // An empty value goes back to the hand written evaluation
+                    if value.is_empty() || (value == "<empty>") {
+                        unload_network();
+                    } else if let Err(error) = load_network(&value) {
+                        println!("info string {}", error);
+                    } else {
+                        board = board.with_fresh_accumulator();
+                    }
+                }
+            }
+            Some(&"ucinewgame") => {
+                stop_search(&mut running_search);
+                board = parse_position(&["position", "startpos"]);
+            }
+            Some(&"position") => board = parse_position(&tokens),
+            Some(&"go") => {
+                stop_search(&mut running_search);
+                // Book moves are played straight away, unless the GUI wants analysis or a mate search
+                let wants_search = tokens.iter().any(|token| matches!(*token, "infinite" | "ponder" | "mate"));
+                match book_move(&board) {
+                    Some(mv) if !wants_search => println!("bestmove {}", to_uci(Some(mv), board.chess960)),
+                    _ => running_search = Some(start_search(&board, &tokens, multi_pv)),
+                }
+            }
+            Some(&"ponderhit") => {
+                if let Some(running_search) = &running_search {
+                    running_search.control.ponderhit(None);
+                }
+            }
+            Some(&"stop") => stop_search(&mut running_search),
+            // Not part of UCI, but handy for debugging. "eval json" prints the trace as JSON instead of a table
+            Some(&"eval") => {
+                let trace = evaluate_trace(&board);
+                if tokens.get(1) == Some(&"json") {
+                    println!("{}", trace.to_json());
+                } else {
+                    println!("{}", trace);
+                }
+            }
+            Some(&"quit") => {
+                stop_search(&mut running_search);
+                break;
+            }
+            _ => (),
+        }
+    }
+}
+
+// Parses "setoption name <name> [value <value>]" into the name and value, either of which can contain spaces
+fn parse_option(tokens: &[&str]) -> (String, String) {
+    let value_index = tokens.iter().position(|token| *token == "value").unwrap_or(tokens.len());
+    let name = tokens[2.min(value_index)..value_index].join(" ");
+    let value = tokens.iter().skip(value_index + 1).copied().collect::<Vec<&str>>().join(" ");
+    return (name, value);
+}
+
+// Parses "position [startpos | fen <fen>] [moves <move list>]"
+fn parse_position(tokens: &[&str]) -> Board {
+    let moves_index = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+    
+    let mut board = match tokens.get(1) {
+        Some(&"fen") => Board::from_fen(tokens[2..moves_index].join(" ")),
+        _ => Board::starting_position(),
+    };
+    // With UCI_Chess960 on, the GUI writes castling as the king taking its rook even in positions that could be standard chess
+    board.chess960 |= CHESS960.load(Ordering::Relaxed);
+    
+    // Moves after one that can't be played are left off, rather than playing them in the wrong position
+    for uci in tokens.iter().skip(moves_index + 1) {
+        match from_uci(&board, uci) {
+            Ok(mv) => board.play_unsafe(mv),
+            Err(error) => {
+                println!("info string {}", error);
+                break;
+            }
+        }
+    }
+    
+    return board;
+}
+
+// Parses the "go" parameters and starts searching on another thread. The bestmove is printed by that thread once the search is done,
+// but never while still pondering or running an infinite search
+fn start_search(board: &Board, tokens: &[&str], multi_pv: usize) -> RunningSearch {
+    let mut time_ms: Option<u64> = None;
+    let mut increment_ms: u64 = 0;
+    let mut move_time_ms: Option<u64> = None;
+    let mut infinite = false;
+    let mut ponder = false;
+    let mut mate_moves: Option<u64> = None;
+    let mut max_nodes: Option<u64> = None;
+    let mut max_depth: Option<u64> = None;
+    
+    let (time_token, increment_token) = match board.turn {
+        Colour::White => ("wtime", "winc"),
+        Colour::Black => ("btime", "binc"),
+    };
+    
+    let mut i = 1;
+    while i < tokens.len() {
+        let value = tokens.get(i + 1).and_then(|value| value.parse::<u64>().ok());
+        match tokens[i] {
+            "ponder" => ponder = true,
+            "infinite" => infinite = true,
+            "movetime" => move_time_ms = value,
+            "mate" => mate_moves = value,
+            "nodes" => max_nodes = value,
+            "depth" => max_depth = value,
+            token if token == time_token => time_ms = value,
+            token if token == increment_token => increment_ms = value.unwrap_or(0),
+            _ => (),
+        }
+        i += 1;
+    }
+    
+    // A ponder keeps the time it is given, since that is what it searches for after a ponderhit
+    let max_search_time = if let Some(move_time_ms) = move_time_ms {
+        Duration::from_millis(move_time_ms)
+    } else if let Some(time_ms) = time_ms {
+        search_time_ms(time_ms, increment_ms)
+    } else if ponder {
+        PONDERHIT_SEARCH_TIME
+    } else if mate_moves.is_some() | max_nodes.is_some() | max_depth.is_some() {
+        Duration::MAX
+    } else {
+        infinite = true;
+        Duration::MAX
+    };
+    
+    NODE_COUNT.store(0, Ordering::Relaxed);
+    
+    if let Some(mate_moves) = mate_moves {
+        return start_mate_search(board, mate_moves as i32, max_search_time);
+    }
+    
+    // An infinite search is treated as a ponder that never gets a ponderhit
+    let control = Arc::new(SearchControl::new(max_search_time, ponder | infinite));
+    if let Some(max_nodes) = max_nodes {
+        control.max_nodes.store(max_nodes, Ordering::Relaxed);
+    }
+    if let Some(max_depth) = max_depth {
+        control.max_depth.store(max_depth.min(i32::MAX as u64) as i32, Ordering::Relaxed);
+    }
+    let search_control = Arc::clone(&control);
+    let board = board.clone();
+    
+    let handle = thread::spawn(move || {
+        let result = search(&board, &search_control, multi_pv);
+        
+        while search_control.pondering.load(Ordering::Relaxed) & !search_control.stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        
+        for (i, line) in result.lines.iter().enumerate() {
+            let pv: Vec<String> = line.pv.iter().map(|mv| to_uci(Some(*mv), board.chess960)).collect();
+            println!("info multipv {} depth {} score cp {} nodes {} time {} pv {}", i + 1, result.depth, line.evaluation / 100, NODE_COUNT.load(Ordering::Relaxed), search_control.start_time.elapsed().as_millis(), pv.join(" "));
+        }
+        
+        let best_move = if result.best_move.is_some() {
+            to_uci(result.best_move, board.chess960)
+        } else {
+            "0000".to_string()
+        };
+        
+        if let Some(ponder_move) = result.ponder_move() {
+            println!("bestmove {} ponder {}", best_move, to_uci(Some(ponder_move), board.chess960));
+        } else {
+            println!("bestmove {}", best_move);
+        }
+    });
+    
+    return RunningSearch {
+        control,
+        handle,
+    };
+}
+
+// Runs "go mate <moves>" on another thread, reporting the mate distance and line if a mate is found
+fn start_mate_search(board: &Board, mate_moves: i32, max_search_time: Duration) -> RunningSearch {
+    let control = Arc::new(SearchControl::new(max_search_time, false));
+    let search_control = Arc::clone(&control);
+    let board = board.clone();
+    
+    let handle = thread::spawn(move || {
+        let result = mate_search(&board, mate_moves, &search_control);
+        for line in mate_report(&result, mate_moves, search_control.start_time.elapsed(), board.chess960) {
+            println!("{}", line);
+        }
+    });
+    
+    return RunningSearch {
+        control,
+        handle,
+    };
+}
+
+// The info and bestmove lines for a finished mate search
+fn mate_report(result: &MateSearchResult, mate_moves: i32, time: Duration, chess960: bool) -> Vec<String> {
+    match result {
+        MateSearchResult::Mate(pv) => {
+            let uci_pv: Vec<String> = pv.iter().map(|mv| to_uci(Some(*mv), chess960)).collect();
+            return vec![
+                format!("info depth {} score mate {} nodes {} time {} pv {}", pv.len(), pv.len().div_ceil(2), NODE_COUNT.load(Ordering::Relaxed), time.as_millis(), uci_pv.join(" ")),
+                format!("bestmove {}", uci_pv[0]),
+            ];
+        }
+        MateSearchResult::NoMate => return vec![format!("info string no mate in {}", mate_moves), "bestmove 0000".to_string()],
+        MateSearchResult::Aborted => return vec!["info string mate search stopped before finishing".to_string(), "bestmove 0000".to_string()],
+    }
+}
+
+// Stops the running search, if any, and waits for it to print its bestmove
+fn stop_search(running_search: &mut Option<RunningSearch>) {
+    if let Some(running_search) = running_search.take() {
+        running_search.control.stop();
+        let _ = running_search.handle.join();
+    }
+}
+
+// Writes a move in UCI notation. Castling is written as the king taking its own rook in Chess960, and as the king moving two squares
+// otherwise
+pub fn to_uci(mv: Option<Move>, chess960: bool) -> String {
     let mut uci_string = "".to_string();
 
+    if mv == Some(EMPTY_MOVE) {
+        return "0000".to_string();
+    }
+
     if let Some(mv) = mv {
         
         uci_string.push_str(&get_square_string(mv.from_square));
-        uci_string.push_str(&get_square_string(mv.to_square));
+        
+        // Castling moves are stored as the king taking its own rook, which is only written that way in Chess960
+        if mv.castle && !chess960 && let Some(colour) = mv.colour {
+            let (king_destination, _) = castle_destinations(colour, Board::castle_side_of(mv));
+            uci_string.push_str(&get_square_string(king_destination));
+        } else {
+            uci_string.push_str(&get_square_string(mv.to_square));
+        }
         
         if !mv.castle {
             if let Some(promotion) = mv.promotion {
                 match promotion {
-                    Role::Knight => uci_string.push_str("n"),
-                    Role::Bishop => uci_string.push_str("b"),
-                    Role::Rook => uci_string.push_str("r"),
-                    Role::Queen => uci_string.push_str("q"),
+                    Role::Knight => uci_string.push('n'),
+                    Role::Bishop => uci_string.push('b'),
+                    Role::Rook => uci_string.push('r'),
+                    Role::Queen => uci_string.push('q'),
+                    Role::King => uci_string.push('k'),
                     _ => (),
                 }
             }
@@ -29,6 +355,12 @@ pub fn to_uci(mv: Option<Move>) -> String {
     return uci_string;
 }
 
+// A square name like "e4"
+pub fn is_square_name(name: &str) -> bool {
+    let chars: Vec<char> = name.chars().collect();
+    return (chars.len() == 2) && ('a'..='h').contains(&chars[0]) && ('1'..='8').contains(&chars[1]);
+}
+
 pub fn get_square_string(sq: Bitboard) -> String {
     let mut square_string: String = "".to_string();
 
@@ -132,138 +464,215 @@ pub fn is_uci_promotion(uci_move: &str) -> Option<Role> {
             return Some(Role::Bishop);
         } else if promotion_candidate == 'n' {
             return Some(Role::Knight);
+        } else if promotion_candidate == 'k' {
+            return Some(Role::King);
         }
     }
     return None;
 }
 
-pub fn from_uci(board: &Board, uci: &str) -> Move {
-    let to_square = get_uci_square_bitboard(&uci[2..4]);
+// Why a UCI move couldn't be read
+#[derive(Debug, Clone, PartialEq)]
+pub enum UciMoveError {
+    // Not written like a UCI move, such as "e2e" or "e7e8p"
+    Malformed(String),
+    // Written like a UCI move but not legal in the position
+    Illegal(String),
+}
+
+impl fmt::Display for UciMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciMoveError::Malformed(uci) => return write!(f, "{} is not a UCI move", uci),
+            UciMoveError::Illegal(uci) => return write!(f, "{} is not a legal move", uci),
+        }
+    }
+}
+
+impl Error for UciMoveError {}
+
+// Finds the legal move a UCI move like "e2e4" or "e7e8q" stands for. "0000" is the null move, which only passes the turn. Castling is
+// accepted as the king taking its own rook, which is how Chess960 writes it, and on boards that aren't Chess960 also as the king moving
+// two squares, like e1g1
+pub fn from_uci(board: &Board, uci: &str) -> Result<Move, UciMoveError> {
+    if uci == "0000" {
+        return Ok(EMPTY_MOVE);
+    }
+    
+    if !uci.is_ascii() || ((uci.len() != 4) & (uci.len() != 5)) || !is_square_name(&uci[0..2]) || !is_square_name(&uci[2..4]) {
+        return Err(UciMoveError::Malformed(uci.to_string()));
+    }
+    let promotion = match uci.chars().nth(4) {
+        None => None,
+        Some('q') => Some(Role::Queen),
+        Some('r') => Some(Role::Rook),
+        Some('b') => Some(Role::Bishop),
+        Some('n') => Some(Role::Knight),
+        // Antichess pawns can promote to a king
+        Some('k') => Some(Role::King),
+        Some(_) => return Err(UciMoveError::Malformed(uci.to_string())),
+    };
     let from_square = get_uci_square_bitboard(&uci[0..2]);
-    // Specific castling logic for specific moves by kings, same logic for everything else
-    if get_role(board, &from_square) == Some(Role::King) {
-        match uci {
-            "e1g1" => return Move {
-                role: Some(Role::King),
-                colour: Some(Colour::White),
-                from_square: from_square,
-                to_square: to_square,
-                en_passant: false,
-                en_passant_target: EMPTY_BITBOARD,
-                castle: true,
-                promotion: None,
-                capture: None,
-            },
-            "e1c1" => return Move {
-                role: Some(Role::King),
-                colour: Some(Colour::White),
-                from_square: from_square,
-                to_square: to_square,
-                en_passant: false,
-                en_passant_target: EMPTY_BITBOARD,
-                castle: true,
-                promotion: None,
-                capture: None,
-            },
-            "e8g8" => return Move {
-                role: Some(Role::King),
-                colour: Some(Colour::Black),
-                from_square: from_square,
-                to_square: to_square,
-                en_passant: false,
-                en_passant_target: EMPTY_BITBOARD,
-                castle: true,
-                promotion: None,
-                capture: None,
-            },
-            "e8c8" => return Move {
-                role: Some(Role::King),
-                colour: Some(Colour::Black),
-                from_square: from_square,
-                to_square: to_square,
-                en_passant: false,
-                en_passant_target: EMPTY_BITBOARD,
-                castle: true,
-                promotion: None,
-                capture: None,
-            },
-            _ => {
-                return Move {
-                    role: if let Some(get_role) = get_role(&board, &from_square) {
-                        Some(get_role)
-                    } else {
-                        None},
-                    colour: if let Some(get_colour) = get_colour(&board, &from_square) {
-                        Some(get_colour)
-                    } else {
-                        None},
-                    from_square: from_square,
-                    to_square: to_square,
-                    en_passant_target: if let Some(Role::Pawn) = get_role(&board, &from_square) {
-                        if (from_square & SECOND_RANK != EMPTY_BITBOARD) & (to_square & FOURTH_RANK != EMPTY_BITBOARD) {
-                            from_square.get_file() & THIRD_RANK
-                        } else if (from_square & SEVENTH_RANK != EMPTY_BITBOARD) & (to_square & FIFTH_RANK != EMPTY_BITBOARD) {
-                            from_square.get_file() & SIXTH_RANK
-                        } else {
-                            EMPTY_BITBOARD
-                        }
-                    } else {
-                        EMPTY_BITBOARD
-                    },
-                    en_passant: if to_square == board.en_passant_target_square {
-                        true
-                    } else {
-                        false
-                    },
-                    castle: false,
-                    promotion: is_uci_promotion(&uci),
-                    capture: if let Some(piece) = get_role(&board, &to_square) {
-                        Some(piece)
-                    } else if to_square == board.en_passant_target_square {
-                        Some(Role::Pawn)
-                    } else {
-                        None}
-                
-                };
+    let to_square = get_uci_square_bitboard(&uci[2..4]);
+    
+    let legal_moves = get_legal_moves(board);
+    let candidates = legal_moves.iter().filter(|mv| (mv.from_square == from_square) & (mv.promotion == promotion));
+    
+    // Castling moves go to the rook's square, so the king taking its own rook is matched here along with every other move
+    if let Some(mv) = candidates.clone().find(|mv| mv.to_square == to_square) {
+        return Ok(*mv);
+    }
+    
+    if !board.chess960 {
+        let standard_castle = candidates.filter(|mv| mv.castle & mv.colour.is_some()).find(|mv| {
+            let (king_destination, _) = castle_destinations(mv.colour.unwrap(), Board::castle_side_of(**mv));
+            return king_destination == to_square;
+        });
+        if let Some(mv) = standard_castle {
+            return Ok(*mv);
+        }
+    }
+    
+    return Err(UciMoveError::Illegal(uci.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn go(tokens: &[&str]) -> RunningSearch {
+        return start_search(&Board::starting_position(), tokens, 1);
+    }
+
+    // Whether the search printed its bestmove within the time
+    fn finishes_within(running_search: &RunningSearch, time: Duration) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < time {
+            if running_search.handle.is_finished() {
+                return true;
             }
+            thread::sleep(Duration::from_millis(5));
         }
+        return false;
     }
-    else {
-        return Move {
-            role: if let Some(get_role) = get_role(&board, &from_square) {
-                Some(get_role)
-            } else {
-                None},
-            colour: if let Some(get_colour) = get_colour(&board, &from_square) {
-                Some(get_colour)
-            } else {
-                None},
-            from_square: from_square,
-            to_square: to_square,
-            en_passant_target: if let Some(Role::Pawn) = get_role(&board, &from_square) {
-                if (from_square & SECOND_RANK != EMPTY_BITBOARD) & (to_square & FOURTH_RANK != EMPTY_BITBOARD) {
-                    from_square.get_file() & THIRD_RANK
-                } else if (from_square & SEVENTH_RANK != EMPTY_BITBOARD) & (to_square & FIFTH_RANK != EMPTY_BITBOARD) {
-                    from_square.get_file() & SIXTH_RANK
-                } else {
-                    EMPTY_BITBOARD
+
+    #[test]
+    fn ponder_ends_on_stop() {
+        let mut running_search = Some(go(&["go", "ponder", "wtime", "60000", "btime", "60000"]));
+        assert!(!finishes_within(running_search.as_ref().unwrap(), Duration::from_millis(200)));
+        stop_search(&mut running_search);
+        assert!(running_search.is_none());
+    }
+
+    #[test]
+    fn ponder_ends_after_a_ponderhit() {
+        for tokens in [&["go", "ponder", "wtime", "3000", "btime", "3000"][..], &["go", "ponder"][..]] {
+            let running_search = go(tokens);
+            assert!(!finishes_within(&running_search, Duration::from_millis(200)), "{:?}", tokens);
+            running_search.control.ponderhit(None);
+            assert!(finishes_within(&running_search, Duration::from_secs(5)), "{:?}", tokens);
+        }
+    }
+
+    #[test]
+    fn go_mate_reports_the_mate() {
+        // Qd7 and then Re8 mates whatever black plays, and nothing mates straight away
+        let board = Board::from_fen("5k2/8/5pp1/3Q3p/7P/8/5PP1/4R1K1 w - - 0 7".to_string());
+        let running_search = start_search(&board, &["go", "mate", "2"], 1);
+        assert!(finishes_within(&running_search, Duration::from_secs(60)));
+
+        let control = SearchControl::new(Duration::MAX, false);
+        assert_eq!(mate_search(&board, 1, &control), MateSearchResult::NoMate);
+        let result = mate_search(&board, 2, &control);
+        assert_eq!(result.mate_in(), Some(2));
+        let report = mate_report(&result, 2, Duration::ZERO, false);
+        assert!(report[0].contains("score mate 2 "), "{}", report[0]);
+        assert_eq!(report[1], "bestmove d5d7");
+
+        let report = mate_report(&MateSearchResult::NoMate, 2, Duration::ZERO, false);
+        assert_eq!(report, vec!["info string no mate in 2", "bestmove 0000"]);
+    }
+
+    #[test]
+    fn clock_times_are_kept_in_milliseconds() {
+        // With a second left and a two second increment the move still has to fit in the second
+        let running_search = go(&["go", "ponder", "wtime", "1000", "btime", "1000", "winc", "2000", "binc", "2000"]);
+        assert!(running_search.control.max_search_time_ms.load(Ordering::Relaxed) <= 1000 / 3);
+        running_search.control.stop();
+        running_search.handle.join().unwrap();
+    }
+
+
+    #[test]
+    fn every_legal_move_round_trips() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPpP/R3K2R b KQkq - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen.to_string());
+            for mv in get_legal_moves(&board) {
+                assert_eq!(from_uci(&board, &to_uci(Some(mv), board.chess960)), Ok(mv), "{} in {}", to_uci(Some(mv), board.chess960), fen);
+            }
+        }
+    }
+
+    #[test]
+    fn castling_is_read_either_way() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".to_string());
+        let king_side = from_uci(&board, "e1g1").unwrap();
+        assert!(king_side.castle);
+        assert_eq!(from_uci(&board, "e1h1"), Ok(king_side));
+        assert_eq!(from_uci(&board, "e1a1"), from_uci(&board, "e1c1"));
+    }
+
+    #[test]
+    fn chess960_castling_is_written_as_king_takes_rook() {
+        // King and rook side by side, where the king's destination square is where a plain king move would go
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/RK6 w A - 0 1".to_string());
+        let castle = get_legal_moves(&board).into_iter().find(|mv| mv.castle).unwrap();
+        assert_eq!(to_uci(Some(castle), board.chess960), "b1a1");
+        assert_eq!(from_uci(&board, "b1a1"), Ok(castle));
+        assert!(!from_uci(&board, "b1c1").unwrap().castle);
+
+        // The king already stands on its destination square
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/6KR w H - 0 1".to_string());
+        let castle = get_legal_moves(&board).into_iter().find(|mv| mv.castle).unwrap();
+        assert_eq!(to_uci(Some(castle), board.chess960), "g1h1");
+        assert_eq!(from_uci(&board, "g1h1"), Ok(castle));
+
+        for index in [0, 518, 959] {
+            let mut board = Board::chess960_position(index).unwrap();
+            for uci in ["b1c3", "b8c6", "g1f3", "g8f6", "e2e3", "e7e6", "f1e2", "f8e7", "d2d3", "d7d6", "c1d2", "c8d7"] {
+                if let Ok(mv) = from_uci(&board, uci) {
+                    let _ = board.try_play(mv);
                 }
-            } else {
-                EMPTY_BITBOARD
-            },
-            en_passant: if to_square == board.en_passant_target_square {
-                true
-            } else {
-                false
-            },
-            castle: false,
-            promotion: is_uci_promotion(&uci),
-            capture: if let Some(piece) = get_role(&board, &to_square) {
-                Some(piece)
-            } else if to_square == board.en_passant_target_square {
-                Some(Role::Pawn)
-            } else {
-                None}
-        };
+            }
+            for mv in get_legal_moves(&board) {
+                assert_eq!(from_uci(&board, &to_uci(Some(mv), board.chess960)), Ok(mv), "{} in {}", to_uci(Some(mv), board.chess960), board.to_fen());
+            }
+        }
+    }
+
+    #[test]
+    fn promotions_and_the_null_move() {
+        let board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1".to_string());
+        assert_eq!(from_uci(&board, "a7a8n").unwrap().promotion, Some(Role::Knight));
+        assert_eq!(from_uci(&board, "a7a8q").unwrap().promotion, Some(Role::Queen));
+        assert_eq!(from_uci(&board, "a7a8"), Err(UciMoveError::Illegal("a7a8".to_string())));
+        assert_eq!(from_uci(&board, "0000"), Ok(EMPTY_MOVE));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn bad_moves_are_errors() {
+        let board = Board::starting_position();
+        assert_eq!(from_uci(&board, "e2e5"), Err(UciMoveError::Illegal("e2e5".to_string())));
+        assert_eq!(from_uci(&board, "e7e5"), Err(UciMoveError::Illegal("e7e5".to_string())));
+        for malformed in ["", "e2", "e2e9", "i2e4", "e2e4x", "e2e4qq", "é2e4"] {
+            assert_eq!(from_uci(&board, malformed), Err(UciMoveError::Malformed(malformed.to_string())), "{}", malformed);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use crate::bitboard::{Bitboard, EMPTY_BITBOARD};
+use crate::board::Board;
+use crate::castle::{CastleSide, CASTLE_SIDES};
+use crate::colour::{ByColour, Colour};
+use crate::movegen::{get_black_moves, get_king_attacks, get_white_moves};
+use crate::mv::Move;
+use crate::role::Role;
+use arrayvec::ArrayVec;
+
+// The rules a board is played by. Standard is ordinary chess, and the others change what is legal, how the game ends, or what a move
+// does to the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Standard,
+    // A king reaching one of the four centre squares wins
+    KingOfTheHill,
+    // Checking the opponent three times wins. Boards count down the checks each side still has to give
+    ThreeCheck,
+    // Captures explode, taking off the capturing piece and every piece other than a pawn next to the capture. Blowing up the enemy king
+    // wins, and kings can't capture
+    Atomic,
+    // Captures are compulsory and there is no check. Losing all your pieces, or having no moves, wins
+    Antichess,
+    // White has 36 pawns and no king, and wins by checkmate. Black wins by taking every white piece
+    Horde,
+}
+
+pub const ALL_VARIANTS: [Variant; 6] = [Variant::Standard, Variant::KingOfTheHill, Variant::ThreeCheck, Variant::Atomic, Variant::Antichess, Variant::Horde];
+
+// D4, E4, D5 and E5
+pub const HILL: Bitboard = Bitboard(0x0000_0018_1800_0000);
+
+// Checks each side has to give to win Three-check
+pub const THREE_CHECKS: u8 = 3;
+
+impl Variant {
+    // Lower case names as they are used in FEN tools and the UCI_Variant option
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Standard => return "standard",
+            Variant::KingOfTheHill => return "kingofthehill",
+            Variant::ThreeCheck => return "threecheck",
+            Variant::Atomic => return "atomic",
+            Variant::Antichess => return "antichess",
+            Variant::Horde => return "horde",
+        }
+    }
+
+    // Names are matched ignoring case, along with the short names other tools use
+    pub fn from_name(name: &str) -> Option<Variant> {
+        match name.to_ascii_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "standard" | "chess" => return Some(Variant::Standard),
+            "kingofthehill" | "koth" => return Some(Variant::KingOfTheHill),
+            "threecheck" | "3check" => return Some(Variant::ThreeCheck),
+            "atomic" => return Some(Variant::Atomic),
+            "antichess" | "giveaway" => return Some(Variant::Antichess),
+            "horde" => return Some(Variant::Horde),
+            _ => return None,
+        }
+    }
+
+    // The name lichess writes in the PGN Variant tag
+    pub fn pgn_name(&self) -> &'static str {
+        match self {
+            Variant::Standard => return "Standard",
+            Variant::KingOfTheHill => return "King of the Hill",
+            Variant::ThreeCheck => return "Three-check",
+            Variant::Atomic => return "Atomic",
+            Variant::Antichess => return "Antichess",
+            Variant::Horde => return "Horde",
+        }
+    }
+
+    pub fn starting_fen(&self) -> &'static str {
+        match self {
+            Variant::Standard | Variant::KingOfTheHill | Variant::Atomic => return "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            Variant::ThreeCheck => return "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1",
+            Variant::Antichess => return "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            Variant::Horde => return "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1",
+        }
+    }
+
+    pub fn starting_position(&self) -> Board {
+        return Board::from_variant_fen(self.starting_fen().to_string(), *self);
+    }
+
+    // A win under the variant's own rules, which ends the game whatever moves are left. Antichess has none, since it is won by running
+    // out of moves
+    pub fn winner(&self, board: &Board) -> Option<Colour> {
+        match self {
+            Variant::Standard | Variant::Antichess => return None,
+            Variant::KingOfTheHill => {
+                for colour in [Colour::White, Colour::Black] {
+                    if (board.colour.get(colour) & board.role.king & HILL) != EMPTY_BITBOARD {
+                        return Some(colour);
+                    }
+                }
+                return None;
+            }
+            Variant::ThreeCheck => {
+                for colour in [Colour::White, Colour::Black] {
+                    if *board.remaining_checks.get(colour) == 0 {
+                        return Some(colour);
+                    }
+                }
+                return None;
+            }
+            Variant::Atomic => {
+                for colour in [Colour::White, Colour::Black] {
+                    if (board.colour.get(colour) & board.role.king) == EMPTY_BITBOARD {
+                        return Some(colour.opposite());
+                    }
+                }
+                return None;
+            }
+            Variant::Horde => {
+                if board.colour.white == EMPTY_BITBOARD {
+                    return Some(Colour::Black);
+                }
+                return None;
+            }
+        }
+    }
+
+    // Neither side can win. Only standard chess keeps its usual rule, Three-check and Atomic need at least one piece other than a king,
+    // and the rest are never called drawn this way
+    pub fn is_insufficient_material(&self, board: &Board) -> bool {
+        match self {
+            Variant::Standard => {
+                if (board.role.pawn | board.role.rook | board.role.queen) != EMPTY_BITBOARD {
+                    return false;
+                }
+                return (board.role.knight | board.role.bishop).count_ones() <= 1;
+            }
+            Variant::ThreeCheck | Variant::Atomic => return board.occupied == board.role.king,
+            Variant::KingOfTheHill | Variant::Antichess | Variant::Horde => return false,
+        }
+    }
+
+    // The colour can never mate on its own, whatever the other side has: in standard chess a bare king or a king with a single knight
+    // or bishop, and in Three-check and Atomic a bare king. Used to call a loss on time a draw
+    pub fn has_insufficient_material(&self, board: &Board, colour: Colour) -> bool {
+        let pieces = *board.colour.get(colour);
+        match self {
+            Variant::Standard => {
+                if (pieces & (board.role.pawn | board.role.rook | board.role.queen)) != EMPTY_BITBOARD {
+                    return false;
+                }
+                return (pieces & (board.role.knight | board.role.bishop)).count_ones() <= 1;
+            }
+            Variant::ThreeCheck | Variant::Atomic => return pieces == (pieces & board.role.king),
+            Variant::KingOfTheHill | Variant::Antichess | Variant::Horde => return false,
+        }
+    }
+}
+
+// Legal moves in Antichess. Kings are ordinary pieces, pawns can also promote to one, and a capture has to be made if there is one
+pub fn get_antichess_moves(board: &Board) -> ArrayVec<Move, 218> {
+    let mut moves = ArrayVec::<Move, 218>::new();
+    match board.turn {
+        Colour::White => get_white_moves(board, &mut moves),
+        Colour::Black => get_black_moves(board, &mut moves),
+    }
+
+    let mut legal_moves = ArrayVec::<Move, 218>::new();
+    let captures_only = moves.iter().any(|mv| mv.capture.is_some());
+    for mv in moves {
+        if captures_only && mv.capture.is_none() {
+            continue;
+        }
+        legal_moves.push(mv);
+        if mv.promotion == Some(Role::Queen) {
+            let mut king_promotion = mv;
+            king_promotion.promotion = Some(Role::King);
+            legal_moves.push(king_promotion);
+        }
+    }
+    return legal_moves;
+}
+
+// Whether a move played on a copy of the board in Atomic leaves the mover's king safe. The king has to survive the explosion, and is
+// safe from check once the enemy king has gone up
+pub fn is_atomic_move_legal(after_move: &Board, colour: Colour) -> bool {
+    if (after_move.colour.get(colour) & after_move.role.king) == EMPTY_BITBOARD {
+        return false;
+    }
+    if (after_move.colour.get(colour.opposite()) & after_move.role.king) == EMPTY_BITBOARD {
+        return true;
+    }
+    return !after_move.is_check(&colour);
+}
+
+impl Board {
+    pub fn from_variant_fen(fen: String, variant: Variant) -> Board {
+        let mut board = Board::from_fen(fen.clone());
+        board.variant = variant;
+        if variant == Variant::ThreeCheck {
+            board.remaining_checks = parse_check_counter(&fen).unwrap_or(ByColour { white: THREE_CHECKS, black: THREE_CHECKS });
+        }
+        // There is no castling in Antichess, whatever the FEN says
+        if variant == Variant::Antichess {
+            for colour in [Colour::White, Colour::Black] {
+                for side in CASTLE_SIDES {
+                    *castling_right(&mut board, colour, side) = None;
+                }
+            }
+        }
+        return board;
+    }
+
+    // Blows up everything next to a capture in Atomic, other than pawns, along with the piece that captured. Castling rights go with
+    // any king or rook caught in the blast
+    pub fn explode(&mut self, square: Bitboard) {
+        let blast = (get_king_attacks(self, &square) & self.occupied & !self.role.pawn) | square;
+        for exploded in blast.get_component_bitboards() {
+            self.clear_square(&exploded);
+        }
+
+        for colour in [Colour::White, Colour::Black] {
+            let has_king = (self.colour.get(colour) & self.role.king) != EMPTY_BITBOARD;
+            for side in CASTLE_SIDES {
+                let Some(rook_square) = self.castle_rook_square(colour, side) else {
+                    continue;
+                };
+                if !has_king | ((rook_square & self.role.rook & self.colour.get(colour)) == EMPTY_BITBOARD) {
+                    *castling_right(self, colour, side) = None;
+                }
+            }
+        }
+    }
+}
+
+fn castling_right(board: &mut Board, colour: Colour, side: CastleSide) -> &mut Option<Bitboard> {
+    return board.castling_rights.get_mut(colour).get_mut(side);
+}
+
+// The Three-check counter, either written after the en passant square as the checks each side has left, like "3+3", or at the end as
+// the checks each side has given, like "+0+0"
+pub fn parse_check_counter(fen: &str) -> Option<ByColour<u8>> {
+    for field in fen.split_whitespace().skip(4) {
+        let given = field.starts_with('+');
+        let counts: Vec<&str> = field.trim_start_matches('+').split('+').collect();
+        if counts.len() != 2 {
+            continue;
+        }
+        let (Ok(white), Ok(black)) = (counts[0].parse::<u8>(), counts[1].parse::<u8>()) else {
+            continue;
+        };
+        if (white > THREE_CHECKS) | (black > THREE_CHECKS) {
+            return None;
+        }
+        if given {
+            return Some(ByColour { white: THREE_CHECKS - white, black: THREE_CHECKS - black });
+        }
+        return Some(ByColour { white, black });
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::perft_test;
+
+    // Node counts from the perft suites lichess and shakmaty use for their variants
+    fn assert_perft(fen: &str, variant: Variant, counts: &[i64]) {
+        let board = Board::from_variant_fen(fen.to_string(), variant);
+        for (depth, count) in counts.iter().enumerate() {
+            assert_eq!(perft_test(&board, depth as i32 + 1), *count, "{} {} at depth {}", variant.name(), fen, depth + 1);
+        }
+    }
+
+    #[test]
+    fn king_of_the_hill_perft() {
+        // Nothing reaches the hill in three plies from the start, so these are the standard counts
+        assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", Variant::KingOfTheHill, &[20, 400, 8902]);
+
+        // Kd4 and Ke4 win on the spot, which leaves black nothing to answer them with
+        assert_perft("8/8/8/8/8/4K3/8/k7 w - - 0 1", Variant::KingOfTheHill, &[8, 18]);
+        assert_perft("8/8/8/8/8/4K3/8/k7 w - - 0 1", Variant::Standard, &[8, 24]);
+        assert_perft("8/8/8/3k4/8/4K3/8/8 w - - 0 1", Variant::KingOfTheHill, &[0]);
+    }
+
+    #[test]
+    fn three_check_perft() {
+        assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 1+1", Variant::ThreeCheck, &[48, 2039]);
+        assert_perft("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 1+1", Variant::ThreeCheck, &[26, 562, 13410]);
+    }
+
+    #[test]
+    fn atomic_perft() {
+        assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", Variant::Atomic, &[20, 400, 8902]);
+        assert_perft("rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq -", Variant::Atomic, &[40, 1238]);
+        assert_perft("rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq -", Variant::Atomic, &[28, 833, 23353]);
+    }
+
+    #[test]
+    fn antichess_perft() {
+        assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - -", Variant::Antichess, &[20, 400, 8067]);
+        assert_perft("8/1p6/8/8/8/8/P7/8 w - -", Variant::Antichess, &[2, 4, 4, 3, 1, 0]);
+    }
+
+    #[test]
+    fn horde_perft() {
+        assert_perft("rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq -", Variant::Horde, &[8, 128, 1274]);
+        assert_perft("4k3/pp4q1/3P2p1/8/P3PP2/PPP2r2/PPP5/PPPP4 b - -", Variant::Horde, &[30, 241, 6633]);
+        assert_perft("k7/5p2/4p2P/3p2P1/2p2P2/1p2P2P/p2P2P1/2P2P2 w - -", Variant::Horde, &[13, 172, 2205]);
+    }
+
+    #[test]
+    fn variant_wins() {
+        let hill = Board::from_variant_fen("8/8/8/8/3K4/8/8/7k b - - 0 1".to_string(), Variant::KingOfTheHill);
+        assert_eq!(Variant::KingOfTheHill.winner(&hill), Some(Colour::White));
+        assert_eq!(Variant::Standard.winner(&hill), None);
+
+        let mut checks = Board::from_variant_fen("4k3/8/8/8/8/8/8/R3K3 w - - 1+3 0 1".to_string(), Variant::ThreeCheck);
+        assert_eq!(Variant::ThreeCheck.winner(&checks), None);
+        checks.play_unsafe(crate::san::from_san(&checks, "Ra8+").unwrap());
+        assert_eq!((checks.remaining_checks.white, checks.remaining_checks.black), (0, 3));
+        assert_eq!(Variant::ThreeCheck.winner(&checks), Some(Colour::White));
+
+        let horde = Board::from_variant_fen("4k3/8/8/8/8/8/8/8 w - - 0 1".to_string(), Variant::Horde);
+        assert_eq!(Variant::Horde.winner(&horde), Some(Colour::Black));
+    }
+
+    #[test]
+    fn captures_explode_in_atomic() {
+        // Nxd7 blows up the knight, the bishop on c8 and the queen on d8 and the king on e8, but not the pawns
+        let mut board = Board::from_variant_fen("rnbqkbnr/pppppppp/1N6/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1".to_string(), Variant::Atomic);
+        board.play_unsafe(crate::san::from_san(&board, "Nxd7").unwrap());
+        assert_eq!(board.to_fen(), "rn3bnr/ppp1pppp/8/8/8/8/PPPPPPPP/R1BQKBNR b KQ - 0 1");
+        assert_eq!(Variant::Atomic.winner(&board), Some(Colour::White));
+    }
+
+    #[test]
+    fn check_counters_in_both_formats() {
+        let remaining = |fen: &str| parse_check_counter(fen).map(|checks| (checks.white, checks.black));
+        assert_eq!(remaining("8/8/8/8/8/8/8/8 w - - 2+1 0 1"), Some((2, 1)));
+        assert_eq!(remaining("8/8/8/8/8/8/8/8 w - - 0 1 +1+2"), Some((2, 1)));
+        assert_eq!(remaining("8/8/8/8/8/8/8/8 w - - 4+1 0 1"), None);
+        assert_eq!(remaining("8/8/8/8/8/8/8/8 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for variant in ALL_VARIANTS {
+            assert_eq!(Variant::from_name(variant.name()), Some(variant));
+            assert_eq!(Variant::from_name(variant.pgn_name()), Some(variant));
+        }
+        assert_eq!(Variant::from_name("3check"), Some(Variant::ThreeCheck));
+        assert_eq!(Variant::from_name("crazyhouse"), None);
+    }
+}
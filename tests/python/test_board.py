@@ -0,0 +1,130 @@
+# Run with maturin develop, then pytest from the repository root. See pyproject.toml for setting up the virtualenv
+import chesslibrary
+import pytest
+
+START_FEN = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+
+# Positions from the chessprogramming wiki perft results, with node counts at a depth that is quick in a debug build
+PERFT_POSITIONS = [
+    (START_FEN, 3, 8902),
+    ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 2, 2039),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43238),
+    ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3, 9467),
+    ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 3, 62379),
+    ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 2, 2079),
+]
+
+FENS = [
+    START_FEN,
+    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+    "8/8/8/8/8/8/8/K6k b - - 0 1",
+]
+
+
+@pytest.mark.parametrize("fen,depth,nodes", PERFT_POSITIONS)
+def test_perft(fen, depth, nodes):
+    assert chesslibrary.Board(fen).perft(depth) == nodes
+
+
+def test_perft_chess960():
+    board = chesslibrary.Board("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")
+    assert board.perft(3) == 12189
+
+
+@pytest.mark.parametrize("fen", FENS)
+def test_fen_round_trip(fen):
+    board = chesslibrary.Board(fen)
+    assert chesslibrary.Board(board.fen()).fen() == board.fen()
+    # The halfmove clock and fullmove number are only kept from the FEN by the Python board
+    assert board.fen().split(" ")[:4] == fen.split(" ")[:4]
+
+
+def test_fen_after_moves():
+    board = chesslibrary.Board()
+    for san in ["e4", "c5", "Nf3", "d6"]:
+        board.push_san(san)
+    assert board.fen() == "rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3"
+
+
+def test_invalid_fen():
+    with pytest.raises(chesslibrary.InvalidFenError):
+        chesslibrary.Board("not a fen")
+    with pytest.raises(ValueError):
+        chesslibrary.Board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1")
+
+
+def test_push_and_pop():
+    board = chesslibrary.Board()
+    move = board.push_uci("e2e4")
+    assert board.peek() == move
+    assert board.turn == "black"
+    assert board.pop() == move
+    assert board.fen() == START_FEN
+    with pytest.raises(IndexError):
+        board.pop()
+
+
+def test_illegal_moves():
+    board = chesslibrary.Board()
+    with pytest.raises(chesslibrary.IllegalMoveError):
+        board.push_uci("e2e5")
+    with pytest.raises(chesslibrary.IllegalMoveError):
+        board.push_san("Nf6")
+    with pytest.raises(chesslibrary.IllegalMoveError):
+        board.parse_uci("z9z9")
+
+
+def test_checkmate_outcome():
+    board = chesslibrary.Board()
+    for san in ["f3", "e5", "g4", "Qh4#"]:
+        board.push_san(san)
+    assert board.is_checkmate()
+    outcome = board.outcome()
+    assert outcome.termination == "checkmate"
+    assert outcome.winner == "black"
+    assert outcome.result() == "0-1"
+
+
+def test_squares():
+    assert chesslibrary.Square("e4").index == 28
+    assert chesslibrary.Square(0).name == "a1"
+    with pytest.raises(chesslibrary.InvalidSquareError):
+        chesslibrary.Square("i9")
+    white_knights = chesslibrary.Board().pieces("knight", "white")
+    assert sorted(square.name for square in white_knights) == ["b1", "g1"]
+
+
+def test_undo_to():
+    board = chesslibrary.Board()
+    for uci in ["e2e4", "e7e5", "g1f3", "b8c6"]:
+        board.push_uci(uci)
+    board.undo_to(1)
+    assert board.ply == 1
+    assert board.fen() == "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+
+
+def test_threefold_repetition():
+    board = chesslibrary.Board()
+    for _ in range(2):
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"]:
+            assert not board.is_game_over()
+            board.push_uci(uci)
+    assert board.outcome().termination == "threefold_repetition"
+
+
+def test_resignation_and_pgn():
+    board = chesslibrary.Board()
+    board.push_san("e4", clock=179.5)
+    board.push_san("e5", clock=178)
+    board.resign("black")
+    assert board.outcome().result() == "1-0"
+    with pytest.raises(chesslibrary.IllegalMoveError):
+        board.push_san("Nf3")
+    pgn = board.pgn({"White": "ChessLibrary"})
+    assert '[White "ChessLibrary"]' in pgn
+    assert '[Result "1-0"]' in pgn
+    assert pgn.strip().endswith("1. e4 {[%clk 0:02:59]} 1... e5 {[%clk 0:02:58]} 1-0")
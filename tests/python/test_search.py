@@ -0,0 +1,59 @@
+import time
+
+import chesslibrary
+import pytest
+
+# Positions pick_move is run on, as a starting position and the moves played from it
+SEARCH_POSITIONS = [
+    ("startpos", ""),
+    ("startpos", "e2e4 e7e5 g1f3 b8c6"),
+    ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", ""),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", ""),
+    ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", ""),
+    ("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1", ""),
+]
+
+# Under 30 seconds of base plus 40 times the increment is an ultrabullet time control, which searches for 250ms
+BOT_TIME = (10, 0)
+# Allowed on top of the search time for setting up the board and finishing the depth being searched
+TIME_MARGIN = 2.0
+
+
+def board_after(board_starting_position, move_list):
+    board = chesslibrary.Board() if board_starting_position == "startpos" else chesslibrary.Board(board_starting_position)
+    for uci in move_list.split():
+        board.push_uci(uci)
+    return board
+
+
+@pytest.mark.parametrize("board_starting_position,move_list", SEARCH_POSITIONS)
+def test_pick_move_is_legal(board_starting_position, move_list):
+    board = board_after(board_starting_position, move_list)
+    start = time.monotonic()
+    uci, evaluation = chesslibrary.pick_move(board_starting_position, BOT_TIME, board.turn, move_list)
+    elapsed = time.monotonic() - start
+    assert uci in [move.uci() for move in board.legal_moves()]
+    assert isinstance(evaluation, int)
+    assert elapsed < 0.25 + TIME_MARGIN
+
+
+def test_pick_move_rejects_bad_input():
+    with pytest.raises(chesslibrary.IllegalMoveError):
+        chesslibrary.pick_move("startpos", BOT_TIME, "white", "e2e5")
+    with pytest.raises(chesslibrary.InvalidFenError):
+        chesslibrary.pick_move("not a fen", BOT_TIME, "white", "")
+    with pytest.raises(chesslibrary.InvalidColourError):
+        chesslibrary.pick_move("startpos", BOT_TIME, "green", "")
+
+
+def test_find_mate():
+    assert chesslibrary.find_mate("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "", 1) == ["a1a8"]
+    assert chesslibrary.find_mate("startpos", "", 1, 1000) is None
+
+
+def test_analyse_lines():
+    lines = chesslibrary.analyse("startpos", "", 250, 3)
+    assert len(lines) == 3
+    assert len({line.uci_move for line in lines}) == 3
+    assert all(line.pv[0] == line.uci_move for line in lines)
+    assert [line.evaluation for line in lines] == sorted((line.evaluation for line in lines), reverse=True)
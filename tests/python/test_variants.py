@@ -0,0 +1,90 @@
+# Run with maturin develop, then pytest from the repository root. See pyproject.toml for setting up the virtualenv
+import chesslibrary
+import pytest
+
+START_FEN = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+
+# Positions from the variant perft suites python-chess tests against, with node counts at a depth that is quick in a debug build. King
+# of the Hill has a position counted by hand where moving the king to d4 ends the game, and is 21 instead of 24 in standard chess
+VARIANT_PERFT_POSITIONS = [
+    ("kingofthehill", START_FEN, 3, 8902),
+    ("kingofthehill", "7k/8/8/8/8/2K5/8/8 w - - 0 1", 2, 21),
+    ("threecheck", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1", 3, 8902),
+    ("threecheck", "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 1+1 0 1", 3, 97848),
+    ("atomic", START_FEN, 3, 8902),
+    ("atomic", "rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1", 3, 45237),
+    ("atomic", "rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq - 0 1", 3, 23353),
+    ("antichess", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1", 3, 8067),
+    ("antichess", "8/1p6/8/8/8/8/P7/8 w - - 0 1", 6, 0),
+    ("antichess", "8/2p5/8/8/8/8/P7/8 w - - 0 1", 7, 4),
+    ("horde", "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1", 4, 23310),
+    ("horde", "4k3/pp4q1/3P2p1/8/P3PP2/PPP2r2/PPP5/PPPP4 b - - 0 1", 3, 6633),
+    ("horde", "k7/5p2/4p2P/3p2P1/2p2P2/1p2P2P/p2P2P1/2P2P2 w - - 0 1", 3, 2205),
+]
+
+
+@pytest.mark.parametrize("variant,fen,depth,nodes", VARIANT_PERFT_POSITIONS)
+def test_variant_perft(variant, fen, depth, nodes):
+    assert chesslibrary.Board(fen, variant).perft(depth) == nodes
+
+
+@pytest.mark.parametrize("variant", ["standard", "kingofthehill", "threecheck", "atomic", "antichess", "horde"])
+def test_variant_starting_position(variant):
+    board = chesslibrary.Board(variant=variant)
+    assert board.variant == variant
+    assert chesslibrary.Board(board.fen(), variant).fen() == board.fen()
+
+
+def test_unknown_variant():
+    with pytest.raises(ValueError):
+        chesslibrary.Board(variant="crazyhouse")
+
+
+def test_king_of_the_hill():
+    board = chesslibrary.Board("7k/8/8/8/8/2K5/8/8 w - - 0 1", "kingofthehill")
+    board.push_uci("c3d4")
+    assert board.legal_moves() == []
+    assert board.outcome().termination == "variant_win"
+    assert board.outcome().winner == "white"
+
+
+def test_three_check():
+    board = chesslibrary.Board(variant="threecheck")
+    assert board.fen() == "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1"
+
+    board = chesslibrary.Board("4k3/8/8/8/8/8/8/4K2R w K - 1+3 0 1", "threecheck")
+    board.push_san("Rh8+")
+    assert board.fen() == "4k2R/8/8/8/8/8/8/4K3 b - - 0+3 1 1"
+    assert board.outcome().termination == "variant_win"
+    assert board.outcome().winner == "white"
+    assert '[Variant "Three-check"]' in board.pgn()
+
+
+def test_atomic_explosion():
+    board = chesslibrary.Board("4k3/8/8/3pn3/8/5N2/8/4K3 w - - 0 1", "atomic")
+    board.push_uci("f3e5")
+    assert board.fen() == "4k3/8/8/3p4/8/8/8/4K3 b - - 0 1"
+
+    board = chesslibrary.Board("4k3/4q3/8/8/8/8/8/4R1K1 w - - 0 1", "atomic")
+    board.push_uci("e1e7")
+    assert board.outcome().termination == "variant_win"
+    assert board.outcome().winner == "white"
+
+
+def test_antichess():
+    board = chesslibrary.Board(variant="antichess")
+    board.push_uci("e2e3")
+    board.push_uci("b7b5")
+    assert [mv.uci() for mv in board.legal_moves()] == ["f1b5"]
+
+    board = chesslibrary.Board("8/8/8/8/8/1p6/P7/8 w - - 0 1", "antichess")
+    board.push_uci("a2b3")
+    assert board.outcome().termination == "variant_win"
+    assert board.outcome().winner == "black"
+
+
+def test_horde():
+    board = chesslibrary.Board("4k3/8/8/8/8/8/3q4/4P3 b - - 0 1", "horde")
+    board.push_uci("d2e1")
+    assert board.outcome().termination == "variant_win"
+    assert board.outcome().winner == "black"